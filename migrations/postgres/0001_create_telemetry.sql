@@ -0,0 +1,12 @@
+-- Telemetry readings, one row per record
+CREATE TABLE IF NOT EXISTS telemetry (
+    id UUID PRIMARY KEY,
+    device_id TEXT NOT NULL,
+    temperature REAL NOT NULL,
+    humidity REAL,
+    pressure REAL,
+    timestamp TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_telemetry_device_timestamp
+    ON telemetry (device_id, timestamp);
@@ -0,0 +1,5 @@
+-- Cover the (timestamp, id) sort key used for paging, in either direction
+CREATE INDEX IF NOT EXISTS idx_telemetry_device_timestamp_id
+    ON telemetry (device_id, timestamp, id);
+
+DROP INDEX IF EXISTS idx_telemetry_device_timestamp;
@@ -0,0 +1,13 @@
+-- Readings become a JSON object of named metrics instead of fixed columns
+ALTER TABLE telemetry ADD COLUMN metrics JSONB NOT NULL DEFAULT '{}';
+
+UPDATE telemetry SET metrics = jsonb_strip_nulls(jsonb_build_object(
+    'temperature', temperature,
+    'humidity', humidity,
+    'pressure', pressure
+));
+
+ALTER TABLE telemetry
+    DROP COLUMN temperature,
+    DROP COLUMN humidity,
+    DROP COLUMN pressure;
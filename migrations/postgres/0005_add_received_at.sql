@@ -0,0 +1,10 @@
+-- Server receive time. Existing rows predate the column, so their device
+-- timestamp is the best estimate.
+ALTER TABLE telemetry ADD COLUMN received_at TIMESTAMPTZ;
+
+UPDATE telemetry SET received_at = timestamp;
+
+ALTER TABLE telemetry ALTER COLUMN received_at SET NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_telemetry_device_received_at
+    ON telemetry (device_id, received_at);
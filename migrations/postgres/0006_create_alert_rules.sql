@@ -0,0 +1,14 @@
+-- Threshold alert rules, one row per rule
+CREATE TABLE IF NOT EXISTS alert_rules (
+    id UUID PRIMARY KEY,
+    name TEXT NOT NULL,
+    -- Device ID or glob pattern
+    device TEXT NOT NULL,
+    metric TEXT NOT NULL,
+    op TEXT NOT NULL,
+    threshold DOUBLE PRECISION NOT NULL,
+    duration_secs BIGINT NOT NULL,
+    hysteresis DOUBLE PRECISION NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,3 @@
+-- How often a device is expected to report, in seconds; NULL uses the
+-- server-wide default
+ALTER TABLE devices ADD COLUMN expected_interval_secs BIGINT;
@@ -0,0 +1,44 @@
+-- Per-minute, per-hour and per-day statistics of each metric, kept up to
+-- date as readings arrive and outliving the readings themselves
+CREATE TABLE IF NOT EXISTS telemetry_rollups (
+    device_id TEXT NOT NULL,
+    -- 1m, 1h or 1d
+    tier TEXT NOT NULL,
+    bucket_start TIMESTAMPTZ NOT NULL,
+    metric TEXT NOT NULL,
+    count BIGINT NOT NULL,
+    sum DOUBLE PRECISION NOT NULL,
+    min DOUBLE PRECISION NOT NULL,
+    max DOUBLE PRECISION NOT NULL,
+    -- Value of the newest reading in the bucket, taken at last_at
+    last DOUBLE PRECISION NOT NULL,
+    last_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (device_id, tier, bucket_start, metric)
+);
+
+-- Retention deletes a tier's old buckets across every device
+CREATE INDEX IF NOT EXISTS idx_telemetry_rollups_tier_bucket
+    ON telemetry_rollups (tier, bucket_start);
+
+-- Fold the readings already stored into every tier
+INSERT INTO telemetry_rollups
+    (device_id, tier, bucket_start, metric, count, sum, min, max, last, last_at)
+SELECT device_id, tier, bucket, metric, COUNT(*), SUM(value), MIN(value), MAX(value),
+       MAX(CASE WHEN latest = 1 THEN value END), MAX(timestamp)
+FROM (
+    SELECT device_id, tier, bucket, metric, value, timestamp,
+           ROW_NUMBER() OVER (
+               PARTITION BY device_id, tier, bucket, metric
+               ORDER BY timestamp DESC, id DESC
+           ) AS latest
+    FROM (
+        SELECT t.device_id, tiers.tier,
+               to_timestamp(floor(extract(epoch FROM t.timestamp)::float8 / tiers.width)
+                            * tiers.width) AS bucket,
+               m.key AS metric, m.value::float8 AS value, t.timestamp, t.id
+        FROM telemetry AS t
+        CROSS JOIN LATERAL jsonb_each_text(t.metrics) AS m
+        CROSS JOIN (VALUES ('1m', 60), ('1h', 3600), ('1d', 86400)) AS tiers (tier, width)
+    ) AS readings
+) AS ranked
+GROUP BY device_id, tier, bucket, metric;
@@ -0,0 +1,13 @@
+-- Telemetry readings, one row per record
+CREATE TABLE IF NOT EXISTS telemetry (
+    id BLOB PRIMARY KEY NOT NULL,
+    device_id TEXT NOT NULL,
+    temperature REAL NOT NULL,
+    humidity REAL,
+    pressure REAL,
+    -- Nanoseconds since the Unix epoch, so range filters compare integers
+    timestamp INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_telemetry_device_timestamp
+    ON telemetry (device_id, timestamp);
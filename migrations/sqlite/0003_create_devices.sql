@@ -0,0 +1,13 @@
+-- Device registry, one row per provisioned device
+CREATE TABLE IF NOT EXISTS devices (
+    device_id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    device_type TEXT NOT NULL,
+    location TEXT,
+    -- JSON array of strings
+    tags TEXT NOT NULL,
+    status TEXT NOT NULL,
+    -- Nanoseconds since the Unix epoch
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
@@ -0,0 +1,12 @@
+-- Readings become a JSON object of named metrics instead of fixed columns
+ALTER TABLE telemetry ADD COLUMN metrics TEXT NOT NULL DEFAULT '{}';
+
+UPDATE telemetry SET metrics = json_object('temperature', temperature);
+UPDATE telemetry SET metrics = json_set(metrics, '$.humidity', humidity)
+    WHERE humidity IS NOT NULL;
+UPDATE telemetry SET metrics = json_set(metrics, '$.pressure', pressure)
+    WHERE pressure IS NOT NULL;
+
+ALTER TABLE telemetry DROP COLUMN temperature;
+ALTER TABLE telemetry DROP COLUMN humidity;
+ALTER TABLE telemetry DROP COLUMN pressure;
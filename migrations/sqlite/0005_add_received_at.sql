@@ -0,0 +1,8 @@
+-- Server receive time, in nanoseconds since the Unix epoch. Existing rows
+-- predate the column, so their device timestamp is the best estimate.
+ALTER TABLE telemetry ADD COLUMN received_at INTEGER NOT NULL DEFAULT 0;
+
+UPDATE telemetry SET received_at = timestamp;
+
+CREATE INDEX IF NOT EXISTS idx_telemetry_device_received_at
+    ON telemetry (device_id, received_at);
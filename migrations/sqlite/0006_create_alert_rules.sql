@@ -0,0 +1,15 @@
+-- Threshold alert rules, one row per rule
+CREATE TABLE IF NOT EXISTS alert_rules (
+    id BLOB PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    -- Device ID or glob pattern
+    device TEXT NOT NULL,
+    metric TEXT NOT NULL,
+    op TEXT NOT NULL,
+    threshold REAL NOT NULL,
+    duration_secs INTEGER NOT NULL,
+    hysteresis REAL NOT NULL,
+    -- Nanoseconds since the Unix epoch
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
@@ -0,0 +1,12 @@
+-- Webhook subscriptions, one row per endpoint
+CREATE TABLE IF NOT EXISTS webhooks (
+    id BLOB PRIMARY KEY NOT NULL,
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    -- Device ID or glob pattern
+    device TEXT NOT NULL,
+    -- JSON array of event names
+    events TEXT NOT NULL,
+    -- Nanoseconds since the Unix epoch
+    created_at INTEGER NOT NULL
+);
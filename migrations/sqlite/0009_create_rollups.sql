@@ -0,0 +1,45 @@
+-- Per-minute, per-hour and per-day statistics of each metric, kept up to
+-- date as readings arrive and outliving the readings themselves
+CREATE TABLE IF NOT EXISTS telemetry_rollups (
+    device_id TEXT NOT NULL,
+    -- 1m, 1h or 1d
+    tier TEXT NOT NULL,
+    -- Nanoseconds since the Unix epoch
+    bucket_start INTEGER NOT NULL,
+    metric TEXT NOT NULL,
+    count INTEGER NOT NULL,
+    sum REAL NOT NULL,
+    min REAL NOT NULL,
+    max REAL NOT NULL,
+    -- Value of the newest reading in the bucket, taken at last_at
+    last REAL NOT NULL,
+    last_at INTEGER NOT NULL,
+    PRIMARY KEY (device_id, tier, bucket_start, metric)
+);
+
+-- Retention deletes a tier's old buckets across every device
+CREATE INDEX IF NOT EXISTS idx_telemetry_rollups_tier_bucket
+    ON telemetry_rollups (tier, bucket_start);
+
+-- Fold the readings already stored into every tier
+INSERT INTO telemetry_rollups
+    (device_id, tier, bucket_start, metric, count, sum, min, max, last, last_at)
+SELECT device_id, tier, bucket, metric, COUNT(*), SUM(value), MIN(value), MAX(value),
+       MAX(CASE WHEN latest = 1 THEN value END), MAX(timestamp)
+FROM (
+    SELECT device_id, tier, bucket, metric, value, timestamp,
+           ROW_NUMBER() OVER (
+               PARTITION BY device_id, tier, bucket, metric
+               ORDER BY timestamp DESC, id DESC
+           ) AS latest
+    FROM (
+        SELECT t.device_id, tiers.tier,
+               t.timestamp - (((t.timestamp % tiers.width) + tiers.width) % tiers.width) AS bucket,
+               m.key AS metric, CAST(m.value AS REAL) AS value, t.timestamp, t.id
+        FROM telemetry AS t, json_each(t.metrics) AS m,
+             (SELECT '1m' AS tier, 60000000000 AS width
+              UNION ALL SELECT '1h', 3600000000000
+              UNION ALL SELECT '1d', 86400000000000) AS tiers
+    )
+)
+GROUP BY device_id, tier, bucket, metric;
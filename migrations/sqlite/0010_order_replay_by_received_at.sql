@@ -0,0 +1,9 @@
+-- Cover the (received_at, id) order live streams replay missed readings in,
+-- for one device and across the fleet
+CREATE INDEX IF NOT EXISTS idx_telemetry_device_received_at_id
+    ON telemetry (device_id, received_at, id);
+
+CREATE INDEX IF NOT EXISTS idx_telemetry_received_at_id
+    ON telemetry (received_at, id);
+
+DROP INDEX IF EXISTS idx_telemetry_device_received_at;
@@ -1,11 +1,36 @@
-use actix_web::{web, HttpResponse};
+use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse};
 use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::errors::AppError;
-use crate::models::{CreateTelemetryRequest, TelemetryQuery};
-use crate::services::TelemetryService;
+use crate::models::{
+    decode_device_cursor, parse_bucket, parse_metric_list, AggregateFunction, AggregateQuery,
+    AlertQuery, AlertRuleRequest, CreateDeviceRequest, CreateTelemetryRequest, DeadLetterQuery,
+    DeliveryQuery, DeviceQuery, FleetStatusQuery, MemoryUsageQuery, TelemetryCursor,
+    TelemetryQuery, TelemetryTier, TransitionQuery, UpdateDeviceRequest, WebhookRequest,
+};
+use crate::services::{
+    AlertService, DeviceService, HeartbeatService, RetentionService, TelemetryService,
+    WebhookService,
+};
+
+/// Header carrying a client-chosen ID for deduplicating retried submissions
+const IDEMPOTENCY_KEY: &str = "Idempotency-Key";
+
+/// Response header naming the telemetry tier an aggregation was computed from
+const TIER_HEADER: &str = "X-Telemetry-Tier";
+
+/// Number of NDJSON lines handed to the service in each bulk write
+const NDJSON_CHUNK_SIZE: usize = 500;
+
+/// Longest NDJSON line accepted before the upload is aborted
+const NDJSON_MAX_LINE_BYTES: usize = 64 * 1024;
+
+/// Maximum number of line failures listed in an NDJSON import response
+const NDJSON_MAX_REPORTED_ERRORS: usize = 1000;
 
 /// Health check response
 #[derive(Serialize)]
@@ -26,6 +51,102 @@ struct CreateResponse {
     id: Uuid,
 }
 
+/// Outcome of a single item in a batch request
+///
+/// Exactly one of `id` and `error` is set.
+#[derive(Serialize)]
+struct BatchItemResult {
+    index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Response for a batch creation request
+#[derive(Serialize)]
+struct BatchCreateResponse {
+    accepted: usize,
+    rejected: usize,
+    results: Vec<BatchItemResult>,
+}
+
+/// A rejected line in an NDJSON import
+#[derive(Serialize)]
+struct LineError {
+    line: usize,
+    error: String,
+}
+
+/// Response for an NDJSON import
+///
+/// `errors` lists at most `NDJSON_MAX_REPORTED_ERRORS` failures; `rejected`
+/// always holds the full count.
+#[derive(Default, Serialize)]
+struct ImportResponse {
+    accepted: usize,
+    rejected: usize,
+    errors: Vec<LineError>,
+}
+
+/// Running state of an NDJSON import
+#[derive(Default)]
+struct NdjsonImport {
+    line_number: usize,
+    pending: Vec<(usize, CreateTelemetryRequest)>,
+    response: ImportResponse,
+}
+
+impl NdjsonImport {
+    /// Parse one line, flushing pending readings to the service when a chunk fills up
+    async fn push_line(&mut self, service: &TelemetryService, line: &[u8]) -> Result<(), AppError> {
+        self.line_number += 1;
+
+        let line = line.trim_ascii();
+        if line.is_empty() {
+            return Ok(());
+        }
+
+        match serde_json::from_slice::<CreateTelemetryRequest>(line) {
+            Ok(request) => {
+                self.pending.push((self.line_number, request));
+                if self.pending.len() >= NDJSON_CHUNK_SIZE {
+                    self.flush(service).await?;
+                }
+            }
+            Err(e) => self.reject(self.line_number, format!("Invalid request: {}", e)),
+        }
+
+        Ok(())
+    }
+
+    /// Store every pending reading in a single pass through the service
+    async fn flush(&mut self, service: &TelemetryService) -> Result<(), AppError> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let (lines, requests): (Vec<_>, Vec<_>) =
+            std::mem::take(&mut self.pending).into_iter().unzip();
+        let outcomes = service.create_telemetry_batch(requests).await?;
+        for (line, outcome) in lines.into_iter().zip(outcomes) {
+            match outcome {
+                Ok(_) => self.response.accepted += 1,
+                Err(e) => self.reject(line, e.to_string()),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn reject(&mut self, line: usize, error: String) {
+        self.response.rejected += 1;
+        if self.response.errors.len() < NDJSON_MAX_REPORTED_ERRORS {
+            self.response.errors.push(LineError { line, error });
+        }
+    }
+}
+
 /// Response for successful record deletion
 #[derive(Serialize)]
 struct DeleteResponse {
@@ -42,16 +163,150 @@ pub async fn health_check() -> HttpResponse {
 }
 
 /// Create a new telemetry record
+///
+/// An `Idempotency-Key` header is used as the request's `message_id`.
 pub async fn create_telemetry(
     service: web::Data<TelemetryService>,
+    req: HttpRequest,
     payload: web::Json<CreateTelemetryRequest>,
 ) -> Result<HttpResponse, AppError> {
-    let id = service.create_telemetry(payload.into_inner()).await?;
+    let mut request = payload.into_inner();
+    if let Some(key) = req.headers().get(IDEMPOTENCY_KEY) {
+        let key = key
+            .to_str()
+            .map_err(|_| AppError::BadRequest("Idempotency-Key must be ASCII".to_string()))?;
+        match &request.message_id {
+            Some(message_id) if message_id != key => {
+                return Err(AppError::BadRequest(
+                    "Idempotency-Key does not match message_id".to_string(),
+                ));
+            }
+            _ => request.message_id = Some(key.to_string()),
+        }
+    }
+
+    let id = service.create_telemetry(request).await?;
 
     let response = CreateResponse { id };
     Ok(HttpResponse::Created().json(response))
 }
 
+/// Create several telemetry records from a JSON array
+///
+/// Items are accepted or rejected individually. The response is `201 Created`
+/// when every item was stored and `207 Multi-Status` otherwise.
+pub async fn create_telemetry_batch(
+    service: web::Data<TelemetryService>,
+    payload: web::Json<Vec<serde_json::Value>>,
+) -> Result<HttpResponse, AppError> {
+    let items = payload.into_inner();
+    if items.is_empty() {
+        return Err(AppError::BadRequest(
+            "Batch must contain at least one item".to_string(),
+        ));
+    }
+
+    // Items that fail to deserialize are rejected up front; the rest are
+    // handed to the service together, remembering their original positions
+    let mut results = Vec::with_capacity(items.len());
+    let mut requests = Vec::new();
+    let mut positions = Vec::new();
+    for (index, item) in items.into_iter().enumerate() {
+        match serde_json::from_value::<CreateTelemetryRequest>(item) {
+            Ok(request) => {
+                positions.push(index);
+                requests.push(request);
+                results.push(None);
+            }
+            Err(e) => results.push(Some(BatchItemResult {
+                index,
+                id: None,
+                error: Some(format!("Invalid request: {}", e)),
+            })),
+        }
+    }
+
+    let outcomes = service.create_telemetry_batch(requests).await?;
+    for (index, outcome) in positions.into_iter().zip(outcomes) {
+        let (id, error) = match outcome {
+            Ok(id) => (Some(id), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        results[index] = Some(BatchItemResult { index, id, error });
+    }
+
+    let results: Vec<BatchItemResult> = results.into_iter().flatten().collect();
+    let accepted = results.iter().filter(|r| r.id.is_some()).count();
+    let response = BatchCreateResponse {
+        accepted,
+        rejected: results.len() - accepted,
+        results,
+    };
+
+    if response.rejected == 0 {
+        Ok(HttpResponse::Created().json(response))
+    } else {
+        Ok(HttpResponse::MultiStatus().json(response))
+    }
+}
+
+/// Import telemetry records from a newline-delimited JSON body
+///
+/// The body is consumed incrementally, so arbitrarily large backfills can be
+/// streamed without buffering the whole upload. Each line holds one
+/// `CreateTelemetryRequest`; blank lines are ignored and line numbers in the
+/// response are 1-based.
+pub async fn import_telemetry_ndjson(
+    service: web::Data<TelemetryService>,
+    req: HttpRequest,
+    mut payload: web::Payload,
+) -> Result<HttpResponse, AppError> {
+    let is_ndjson = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.starts_with("application/x-ndjson"))
+        .unwrap_or(false);
+    if !is_ndjson {
+        return Err(AppError::BadRequest(
+            "Content-Type must be application/x-ndjson".to_string(),
+        ));
+    }
+
+    let mut import = NdjsonImport::default();
+    let mut buffer: Vec<u8> = Vec::new();
+
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.map_err(|e| AppError::BadRequest(e.to_string()))?;
+        buffer.extend_from_slice(&chunk);
+
+        // Process every complete line in the buffer, keeping the partial tail
+        let mut start = 0;
+        while let Some(offset) = buffer[start..].iter().position(|&b| b == b'\n') {
+            let end = start + offset;
+            import.push_line(&service, &buffer[start..end]).await?;
+            start = end + 1;
+        }
+        buffer.drain(..start);
+
+        if buffer.len() > NDJSON_MAX_LINE_BYTES {
+            return Err(AppError::BadRequest(format!(
+                "Line {} exceeds {} bytes",
+                import.line_number + 1,
+                NDJSON_MAX_LINE_BYTES
+            )));
+        }
+    }
+
+    // The final line may not be newline-terminated
+    if !buffer.is_empty() {
+        import.push_line(&service, &buffer).await?;
+    }
+    import.flush(&service).await?;
+
+    Ok(HttpResponse::Ok().json(import.response))
+}
+
 /// Get telemetry data by ID
 pub async fn get_telemetry_by_id(
     service: web::Data<TelemetryService>,
@@ -72,11 +327,151 @@ pub async fn get_device_telemetry(
     query: web::Query<TelemetryQuery>,
 ) -> Result<HttpResponse, AppError> {
     let device_id = path.into_inner();
-    let telemetry = service
-        .get_device_telemetry(&device_id, query.start_time, query.end_time, query.limit)
+    let cursor = query
+        .cursor
+        .as_deref()
+        .map(TelemetryCursor::decode)
+        .transpose()
+        .map_err(AppError::BadRequest)?;
+
+    let page = service
+        .get_device_telemetry(
+            &device_id,
+            &query.filter(),
+            cursor,
+            query.order,
+            page_limit(query.limit)?,
+        )
         .await?;
 
-    Ok(HttpResponse::Ok().json(telemetry))
+    Ok(HttpResponse::Ok().json(page))
+}
+
+/// List devices that have reported telemetry
+pub async fn list_devices(
+    service: web::Data<TelemetryService>,
+    registry: Option<web::Data<DeviceService>>,
+    query: web::Query<DeviceQuery>,
+) -> Result<HttpResponse, AppError> {
+    let after = query
+        .cursor
+        .as_deref()
+        .map(decode_device_cursor)
+        .transpose()
+        .map_err(AppError::BadRequest)?;
+    let limit = page_limit(query.limit)?;
+
+    if query.registered {
+        let registry = registry.ok_or_else(|| {
+            AppError::InternalError("Device registry is not configured".to_string())
+        })?;
+        let page = registry
+            .list_devices(query.prefix.as_deref(), after.as_deref(), limit)
+            .await?;
+        return Ok(HttpResponse::Ok().json(page));
+    }
+
+    let page = service
+        .list_devices(query.prefix.as_deref(), after.as_deref(), limit)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(page))
+}
+
+/// Register a new device
+pub async fn register_device(
+    service: web::Data<DeviceService>,
+    payload: web::Json<CreateDeviceRequest>,
+) -> Result<HttpResponse, AppError> {
+    let device = service.register_device(payload.into_inner()).await?;
+
+    Ok(HttpResponse::Created().json(device))
+}
+
+/// Get a registered device
+pub async fn get_device(
+    service: web::Data<DeviceService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let device_id = path.into_inner();
+    let device = service.get_device(&device_id).await?;
+
+    Ok(HttpResponse::Ok().json(device))
+}
+
+/// Replace a registered device's details
+pub async fn update_device(
+    service: web::Data<DeviceService>,
+    path: web::Path<String>,
+    payload: web::Json<UpdateDeviceRequest>,
+) -> Result<HttpResponse, AppError> {
+    let device_id = path.into_inner();
+    let device = service
+        .update_device(&device_id, payload.into_inner())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(device))
+}
+
+/// Remove a device from the registry
+pub async fn delete_device(
+    service: web::Data<DeviceService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let device_id = path.into_inner();
+    service.delete_device(&device_id).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Get the newest reading for a specific device
+pub async fn get_latest_telemetry(
+    service: web::Data<TelemetryService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let device_id = path.into_inner();
+    let latest = service.get_latest_telemetry(&device_id).await?;
+
+    Ok(HttpResponse::Ok().json(latest))
+}
+
+/// Get time-bucketed statistics for a specific device
+pub async fn aggregate_device_telemetry(
+    service: web::Data<TelemetryService>,
+    path: web::Path<String>,
+    query: web::Query<AggregateQuery>,
+) -> Result<HttpResponse, AppError> {
+    let device_id = path.into_inner();
+    let bucket = parse_bucket(&query.bucket).map_err(AppError::BadRequest)?;
+    let functions =
+        AggregateFunction::parse_list(&query.functions).map_err(AppError::BadRequest)?;
+    let tier = query
+        .tier
+        .as_deref()
+        .map(str::parse::<TelemetryTier>)
+        .transpose()
+        .map_err(AppError::BadRequest)?;
+    let metrics = query
+        .metrics
+        .as_deref()
+        .map(parse_metric_list)
+        .unwrap_or_default();
+
+    let (tier, buckets) = service
+        .aggregate_device_telemetry(
+            &device_id,
+            query.start_time,
+            query.end_time,
+            bucket,
+            tier,
+            &metrics,
+            &functions,
+        )
+        .await?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header((TIER_HEADER, tier.as_str()))
+        .json(buckets))
 }
 
 /// Delete telemetry records older than a specific timestamp
@@ -96,3 +491,190 @@ pub async fn delete_old_records(
 
     Ok(HttpResponse::Ok().json(response))
 }
+
+/// Create a new alert rule
+pub async fn create_alert_rule(
+    service: web::Data<AlertService>,
+    payload: web::Json<AlertRuleRequest>,
+) -> Result<HttpResponse, AppError> {
+    let rule = service.create_rule(payload.into_inner()).await?;
+
+    Ok(HttpResponse::Created().json(rule))
+}
+
+/// List every alert rule
+pub async fn list_alert_rules(service: web::Data<AlertService>) -> Result<HttpResponse, AppError> {
+    let rules = service.list_rules().await?;
+
+    Ok(HttpResponse::Ok().json(rules))
+}
+
+/// Get an alert rule
+pub async fn get_alert_rule(
+    service: web::Data<AlertService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let id = parse_uuid(&path)?;
+    let rule = service.get_rule(id).await?;
+
+    Ok(HttpResponse::Ok().json(rule))
+}
+
+/// Replace an alert rule
+pub async fn update_alert_rule(
+    service: web::Data<AlertService>,
+    path: web::Path<String>,
+    payload: web::Json<AlertRuleRequest>,
+) -> Result<HttpResponse, AppError> {
+    let id = parse_uuid(&path)?;
+    let rule = service.update_rule(id, payload.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(rule))
+}
+
+/// Remove an alert rule
+pub async fn delete_alert_rule(
+    service: web::Data<AlertService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let id = parse_uuid(&path)?;
+    service.delete_rule(id).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// List firing and resolved alerts
+pub async fn list_alerts(
+    service: web::Data<AlertService>,
+    query: web::Query<AlertQuery>,
+) -> Result<HttpResponse, AppError> {
+    let alerts = service.list_alerts(&query);
+
+    Ok(HttpResponse::Ok().json(alerts))
+}
+
+/// Create a new webhook
+pub async fn create_webhook(
+    service: web::Data<WebhookService>,
+    payload: web::Json<WebhookRequest>,
+) -> Result<HttpResponse, AppError> {
+    let webhook = service.create_webhook(payload.into_inner()).await?;
+
+    Ok(HttpResponse::Created().json(webhook))
+}
+
+/// List every webhook
+pub async fn list_webhooks(service: web::Data<WebhookService>) -> Result<HttpResponse, AppError> {
+    let webhooks = service.list_webhooks().await?;
+
+    Ok(HttpResponse::Ok().json(webhooks))
+}
+
+/// Get a webhook
+pub async fn get_webhook(
+    service: web::Data<WebhookService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let id = parse_uuid(&path)?;
+    let webhook = service.get_webhook(id).await?;
+
+    Ok(HttpResponse::Ok().json(webhook))
+}
+
+/// Remove a webhook
+pub async fn delete_webhook(
+    service: web::Data<WebhookService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let id = parse_uuid(&path)?;
+    service.delete_webhook(id).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// List recent deliveries to a webhook
+pub async fn list_webhook_deliveries(
+    service: web::Data<WebhookService>,
+    path: web::Path<String>,
+    query: web::Query<DeliveryQuery>,
+) -> Result<HttpResponse, AppError> {
+    let id = parse_uuid(&path)?;
+    let deliveries = service.list_deliveries(id, &query).await?;
+
+    Ok(HttpResponse::Ok().json(deliveries))
+}
+
+/// List deliveries that failed every attempt
+pub async fn list_dead_letters(
+    service: web::Data<WebhookService>,
+    query: web::Query<DeadLetterQuery>,
+) -> Result<HttpResponse, AppError> {
+    let deliveries = service.dead_letters(query.webhook_id, query.limit);
+
+    Ok(HttpResponse::Ok().json(deliveries))
+}
+
+/// Get whether a device is online, stale or offline
+pub async fn get_device_status(
+    service: web::Data<HeartbeatService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let status = service.device_status(&path).await?;
+
+    Ok(HttpResponse::Ok().json(status))
+}
+
+/// List a device's status changes, most recent first
+pub async fn list_status_transitions(
+    service: web::Data<HeartbeatService>,
+    path: web::Path<String>,
+    query: web::Query<TransitionQuery>,
+) -> Result<HttpResponse, AppError> {
+    let transitions = service.transitions(&path, query.limit).await?;
+
+    Ok(HttpResponse::Ok().json(transitions))
+}
+
+/// Summarize the connectivity of every tracked device
+pub async fn get_fleet_status(
+    service: web::Data<HeartbeatService>,
+    query: web::Query<FleetStatusQuery>,
+) -> HttpResponse {
+    HttpResponse::Ok().json(service.fleet_status(&query))
+}
+
+/// Show the retention policy and the outcome of past sweeps
+pub async fn get_retention_status(service: web::Data<RetentionService>) -> HttpResponse {
+    HttpResponse::Ok().json(service.status())
+}
+
+/// Run a retention sweep now
+pub async fn run_retention_sweep(
+    service: web::Data<RetentionService>,
+) -> Result<HttpResponse, AppError> {
+    let run = service.sweep().await?;
+
+    Ok(HttpResponse::Ok().json(run))
+}
+
+/// Report the memory held by the in-memory store, largest devices first
+pub async fn get_memory_usage(
+    service: web::Data<TelemetryService>,
+    query: web::Query<MemoryUsageQuery>,
+) -> Result<HttpResponse, AppError> {
+    let usage = service.memory_usage(&query).await?;
+
+    Ok(HttpResponse::Ok().json(usage))
+}
+
+/// Check a cursor-paginated page size, which must let the client make progress
+fn page_limit(limit: usize) -> Result<usize, AppError> {
+    if limit == 0 {
+        return Err(AppError::BadRequest("limit must be at least 1".to_string()));
+    }
+    Ok(limit)
+}
+
+fn parse_uuid(id: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid UUID format".to_string()))
+}
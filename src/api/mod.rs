@@ -1,2 +1,4 @@
 mod handlers;
 pub mod routes;
+mod stream;
+mod ws;
@@ -1,6 +1,6 @@
 use actix_web::web;
 
-use super::handlers;
+use super::{handlers, stream, ws};
 
 /// Configure the API routes
 pub fn configure(cfg: &mut web::ServiceConfig) {
@@ -11,16 +11,106 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                 web::scope("/telemetry")
                     // POST /api/v1/telemetry - Create a new telemetry record
                     .route("", web::post().to(handlers::create_telemetry))
+                    // POST /api/v1/telemetry/batch - Create several telemetry records
+                    .route("/batch", web::post().to(handlers::create_telemetry_batch))
+                    // POST /api/v1/telemetry/import - Stream telemetry records as NDJSON
+                    .route("/import", web::post().to(handlers::import_telemetry_ndjson))
+                    // GET /api/v1/telemetry/stream - Live readings from every device (SSE)
+                    .route("/stream", web::get().to(stream::stream_telemetry))
+                    // GET /api/v1/telemetry/ws - WebSocket for filtered live subscriptions
+                    .route("/ws", web::get().to(ws::subscribe_telemetry))
                     // GET /api/v1/telemetry/{id} - Get a specific telemetry record
                     .route("/{id}", web::get().to(handlers::get_telemetry_by_id)),
             )
             // Device endpoints
+            // GET /api/v1/devices - List devices that have reported telemetry, or registered ones
+            .route("/devices", web::get().to(handlers::list_devices))
+            // POST /api/v1/devices - Register a device
+            .route("/devices", web::post().to(handlers::register_device))
             .service(
                 web::scope("/devices/{device_id}")
+                    // GET /api/v1/devices/{device_id} - Get a registered device
+                    .route("", web::get().to(handlers::get_device))
+                    // PUT /api/v1/devices/{device_id} - Update a registered device
+                    .route("", web::put().to(handlers::update_device))
+                    // DELETE /api/v1/devices/{device_id} - Remove a device from the registry
+                    .route("", web::delete().to(handlers::delete_device))
                     // GET /api/v1/devices/{device_id}/telemetry - Get telemetry for a device
                     .route("/telemetry", web::get().to(handlers::get_device_telemetry))
                     // DELETE /api/v1/devices/{device_id}/telemetry - Delete old telemetry records
-                    .route("/telemetry", web::delete().to(handlers::delete_old_records)),
+                    .route("/telemetry", web::delete().to(handlers::delete_old_records))
+                    // GET /api/v1/devices/{device_id}/latest - Newest reading for a device
+                    .route("/latest", web::get().to(handlers::get_latest_telemetry))
+                    // GET /api/v1/devices/{device_id}/stream - Live readings for a device (SSE)
+                    .route("/stream", web::get().to(stream::stream_device_telemetry))
+                    // GET /api/v1/devices/{device_id}/status - Whether the device is online
+                    .route("/status", web::get().to(handlers::get_device_status))
+                    // GET /api/v1/devices/{device_id}/status/history - Status changes
+                    .route(
+                        "/status/history",
+                        web::get().to(handlers::list_status_transitions),
+                    )
+                    // GET /api/v1/devices/{device_id}/telemetry/aggregate - Time-bucketed statistics
+                    .route(
+                        "/telemetry/aggregate",
+                        web::get().to(handlers::aggregate_device_telemetry),
+                    ),
+            )
+            // GET /api/v1/fleet/status - Connectivity summary of every device
+            .route("/fleet/status", web::get().to(handlers::get_fleet_status))
+            // Alerting endpoints
+            .service(
+                web::scope("/alerts")
+                    // GET /api/v1/alerts - List firing and resolved alerts
+                    .route("", web::get().to(handlers::list_alerts))
+                    // POST /api/v1/alerts/rules - Create an alert rule
+                    .route("/rules", web::post().to(handlers::create_alert_rule))
+                    // GET /api/v1/alerts/rules - List alert rules
+                    .route("/rules", web::get().to(handlers::list_alert_rules))
+                    // GET /api/v1/alerts/rules/{rule_id} - Get an alert rule
+                    .route("/rules/{rule_id}", web::get().to(handlers::get_alert_rule))
+                    // PUT /api/v1/alerts/rules/{rule_id} - Replace an alert rule
+                    .route(
+                        "/rules/{rule_id}",
+                        web::put().to(handlers::update_alert_rule),
+                    )
+                    // DELETE /api/v1/alerts/rules/{rule_id} - Remove an alert rule
+                    .route(
+                        "/rules/{rule_id}",
+                        web::delete().to(handlers::delete_alert_rule),
+                    ),
+            )
+            // Webhook endpoints
+            .service(
+                web::scope("/webhooks")
+                    // POST /api/v1/webhooks - Create a webhook
+                    .route("", web::post().to(handlers::create_webhook))
+                    // GET /api/v1/webhooks - List webhooks
+                    .route("", web::get().to(handlers::list_webhooks))
+                    // GET /api/v1/webhooks/dead-letters - Deliveries that failed every attempt
+                    .route("/dead-letters", web::get().to(handlers::list_dead_letters))
+                    // GET /api/v1/webhooks/{webhook_id} - Get a webhook
+                    .route("/{webhook_id}", web::get().to(handlers::get_webhook))
+                    // DELETE /api/v1/webhooks/{webhook_id} - Remove a webhook
+                    .route("/{webhook_id}", web::delete().to(handlers::delete_webhook))
+                    // GET /api/v1/webhooks/{webhook_id}/deliveries - Recent deliveries
+                    .route(
+                        "/{webhook_id}/deliveries",
+                        web::get().to(handlers::list_webhook_deliveries),
+                    ),
+            )
+            // Administration endpoints
+            .service(
+                web::scope("/admin")
+                    // GET /api/v1/admin/memory - Memory held by the in-memory store per device
+                    .route("/memory", web::get().to(handlers::get_memory_usage))
+                    // GET /api/v1/admin/retention - Retention policy and sweep statistics
+                    .route("/retention", web::get().to(handlers::get_retention_status))
+                    // POST /api/v1/admin/retention/run - Run a retention sweep now
+                    .route(
+                        "/retention/run",
+                        web::post().to(handlers::run_retention_sweep),
+                    ),
             )
             // Health check endpoint
             .route("/health", web::get().to(handlers::health_check)),
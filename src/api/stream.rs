@@ -0,0 +1,169 @@
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::time::Duration;
+
+use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse};
+use futures_util::future::{self, Either};
+use futures_util::stream::{self, StreamExt};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::{interval_at, Instant, Interval};
+use uuid::Uuid;
+
+use crate::errors::AppError;
+use crate::models::{TelemetryCursor, TelemetryData};
+use crate::services::{Replay, TelemetryService};
+
+/// Header an EventSource sends when reconnecting, holding the last event ID it saw
+const LAST_EVENT_ID: &str = "Last-Event-ID";
+
+/// How often an idle stream sends a comment to keep intermediaries from closing it
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Stream readings for a specific device as Server-Sent Events
+pub async fn stream_device_telemetry(
+    service: web::Data<TelemetryService>,
+    path: web::Path<String>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    live_stream(&service, Some(path.into_inner()), &req).await
+}
+
+/// Stream readings from every device as Server-Sent Events
+pub async fn stream_telemetry(
+    service: web::Data<TelemetryService>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    live_stream(&service, None, &req).await
+}
+
+/// Build an event stream of accepted readings, optionally for one device
+///
+/// Each event's ID is the reading's receive position. A client sending one
+/// back in `Last-Event-ID` first gets the stored readings received after it.
+/// When there are too many to replay, or the stream falls too far behind,
+/// an `overflow` event is sent and the stream closed, so the client
+/// reconnects and catches up from storage.
+async fn live_stream(
+    service: &TelemetryService,
+    device_id: Option<String>,
+    req: &HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let resume_from = req
+        .headers()
+        .get(LAST_EVENT_ID)
+        .map(|v| {
+            v.to_str()
+                .map_err(|e| e.to_string())
+                .and_then(TelemetryCursor::decode)
+        })
+        .transpose()
+        .map_err(|_| AppError::BadRequest(format!("Invalid {} header", LAST_EVENT_ID)))?;
+
+    // Subscribe before replaying so readings accepted meanwhile are not missed
+    let receiver = service.subscribe();
+    let replay = match resume_from {
+        Some(position) => {
+            service
+                .received_after(device_id.as_deref(), position)
+                .await?
+        }
+        None => Replay::default(),
+    };
+
+    let live = LiveEvents {
+        receiver,
+        device_id,
+        replayed: replay.readings.iter().map(|t| t.id).collect(),
+        keep_alive: interval_at(Instant::now() + KEEP_ALIVE_INTERVAL, KEEP_ALIVE_INTERVAL),
+        closed: false,
+    };
+    // Going live after a cut-short replay would skip the readings left out
+    let rest = if replay.overflowed {
+        Either::Left(stream::once(future::ready(Ok(overflow("replay_limit")))))
+    } else {
+        Either::Right(stream::unfold(live, LiveEvents::next))
+    };
+    let events = stream::iter(replay.readings)
+        .map(|telemetry| Ok::<_, Infallible>(event(&telemetry)))
+        .chain(rest);
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(events))
+}
+
+/// State of the live part of an event stream
+struct LiveEvents {
+    receiver: broadcast::Receiver<TelemetryData>,
+
+    /// Only readings from this device are sent, or every reading when `None`
+    device_id: Option<String>,
+
+    /// Readings already sent while replaying, which may also arrive live
+    replayed: HashSet<Uuid>,
+
+    keep_alive: Interval,
+
+    /// Set once the stream has sent its last chunk
+    closed: bool,
+}
+
+impl LiveEvents {
+    /// Wait for the next chunk to send, or `None` once the stream should end
+    async fn next(mut self) -> Option<(Result<web::Bytes, Infallible>, Self)> {
+        if self.closed {
+            return None;
+        }
+        loop {
+            tokio::select! {
+                received = self.receiver.recv() => match received {
+                    Ok(telemetry) => {
+                        if self.wants(&telemetry) {
+                            return Some((Ok(event(&telemetry)), self));
+                        }
+                    }
+                    // Lagging behind ends the stream once the client is told why
+                    Err(RecvError::Lagged(_)) => {
+                        self.closed = true;
+                        return Some((Ok(overflow("lagged")), self));
+                    }
+                    Err(RecvError::Closed) => return None,
+                },
+                _ = self.keep_alive.tick() => {
+                    return Some((Ok(web::Bytes::from_static(b": keep-alive\n\n")), self));
+                }
+            }
+        }
+    }
+
+    fn wants(&self, telemetry: &TelemetryData) -> bool {
+        self.device_id
+            .as_deref()
+            .map(|device_id| telemetry.device_id == device_id)
+            .unwrap_or(true)
+            && !self.replayed.contains(&telemetry.id)
+    }
+}
+
+/// Format a reading as a `telemetry` event
+fn event(telemetry: &TelemetryData) -> web::Bytes {
+    let data = serde_json::to_string(telemetry).unwrap_or_default();
+    web::Bytes::from(format!(
+        "id: {}\nevent: telemetry\ndata: {}\n\n",
+        TelemetryCursor::received(telemetry).encode(),
+        data
+    ))
+}
+
+/// Format an `overflow` event, telling the client it missed readings
+///
+/// The event has no ID, so a reconnecting client resumes after the last
+/// reading it was sent.
+fn overflow(reason: &str) -> web::Bytes {
+    web::Bytes::from(format!(
+        "event: overflow\ndata: {}\n\n",
+        serde_json::json!({ "reason": reason })
+    ))
+}
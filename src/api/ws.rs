@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+
+use actix_web::{rt, web, HttpRequest, HttpResponse};
+use actix_ws::{Message, MessageStream, Session};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::errors::AppError;
+use crate::models::{Subscription, TelemetryData};
+use crate::services::TelemetryService;
+
+/// Most subscriptions a single connection may hold at once
+const MAX_SUBSCRIPTIONS_PER_CONNECTION: usize = 64;
+
+/// Command sent by a client over the WebSocket
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+enum ClientMessage {
+    /// Add a subscription, replacing any existing one with the same ID
+    Subscribe(Subscription),
+    /// Remove a subscription
+    Unsubscribe { id: String },
+}
+
+/// Message sent to a client over the WebSocket
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum ServerMessage<'a> {
+    Subscribed {
+        id: &'a str,
+    },
+    Unsubscribed {
+        id: &'a str,
+    },
+    /// A reading matching one or more of the client's subscriptions
+    Telemetry {
+        subscriptions: Vec<&'a str>,
+        data: &'a TelemetryData,
+    },
+    /// Readings were dropped because the client was not keeping up
+    Lagged {
+        skipped: u64,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Open a WebSocket for subscribing to live telemetry
+pub async fn subscribe_telemetry(
+    service: web::Data<TelemetryService>,
+    req: HttpRequest,
+    body: web::Payload,
+) -> Result<HttpResponse, AppError> {
+    let (response, session, messages) =
+        actix_ws::handle(&req, body).map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    rt::spawn(run_session(session, messages, service.subscribe()));
+    Ok(response)
+}
+
+/// Serve one connection until either side closes it
+///
+/// Readings reach the connection through its own bounded buffer, so a slow
+/// client only delays itself: once the buffer is full the oldest readings are
+/// dropped and the client is sent a `lagged` message with the number skipped.
+async fn run_session(
+    mut session: Session,
+    mut messages: MessageStream,
+    mut readings: broadcast::Receiver<TelemetryData>,
+) {
+    let mut subscriptions: BTreeMap<String, Subscription> = BTreeMap::new();
+
+    loop {
+        let reply = tokio::select! {
+            message = messages.recv() => match message {
+                Some(Ok(Message::Text(text))) => handle_command(&mut subscriptions, &text),
+                Some(Ok(Message::Ping(bytes))) => {
+                    if session.pong(&bytes).await.is_err() {
+                        return;
+                    }
+                    continue;
+                }
+                Some(Ok(Message::Close(reason))) => {
+                    let _ = session.close(reason).await;
+                    return;
+                }
+                Some(Ok(_)) => continue,
+                Some(Err(_)) | None => break,
+            },
+            reading = readings.recv() => match reading {
+                Ok(telemetry) => {
+                    let matched: Vec<&str> = subscriptions
+                        .values()
+                        .filter(|s| s.matches(&telemetry))
+                        .map(|s| s.id.as_str())
+                        .collect();
+                    if matched.is_empty() {
+                        continue;
+                    }
+                    to_text(&ServerMessage::Telemetry {
+                        subscriptions: matched,
+                        data: &telemetry,
+                    })
+                }
+                Err(RecvError::Lagged(skipped)) => to_text(&ServerMessage::Lagged { skipped }),
+                Err(RecvError::Closed) => break,
+            },
+        };
+
+        if session.text(reply).await.is_err() {
+            return;
+        }
+    }
+
+    let _ = session.close(None).await;
+}
+
+/// Apply a client command and build the reply
+fn handle_command(subscriptions: &mut BTreeMap<String, Subscription>, text: &str) -> String {
+    let command = match serde_json::from_str::<ClientMessage>(text) {
+        Ok(command) => command,
+        Err(e) => return error(format!("Invalid message: {}", e)),
+    };
+
+    match command {
+        ClientMessage::Subscribe(subscription) => {
+            if subscription.id.is_empty() {
+                return error("Subscription id must not be empty");
+            }
+            if subscription.thresholds.iter().any(|t| !t.value.is_finite()) {
+                return error("Threshold values must be finite");
+            }
+            if !subscriptions.contains_key(&subscription.id)
+                && subscriptions.len() >= MAX_SUBSCRIPTIONS_PER_CONNECTION
+            {
+                return error(format!(
+                    "At most {} subscriptions per connection",
+                    MAX_SUBSCRIPTIONS_PER_CONNECTION
+                ));
+            }
+
+            let reply = to_text(&ServerMessage::Subscribed {
+                id: &subscription.id,
+            });
+            subscriptions.insert(subscription.id.clone(), subscription);
+            reply
+        }
+        ClientMessage::Unsubscribe { id } => match subscriptions.remove(&id) {
+            Some(_) => to_text(&ServerMessage::Unsubscribed { id: &id }),
+            None => error(format!("No subscription with id {}", id)),
+        },
+    }
+}
+
+fn error(message: impl Into<String>) -> String {
+    to_text(&ServerMessage::Error {
+        message: message.into(),
+    })
+}
+
+fn to_text(message: &ServerMessage) -> String {
+    serde_json::to_string(message).unwrap_or_default()
+}
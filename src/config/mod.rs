@@ -1,7 +1,10 @@
 use dotenvy::dotenv;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
 
+use crate::models::TelemetryTier;
+
 /// Application configuration settings
 #[derive(Debug, Clone, Deserialize)]
 pub struct AppConfig {
@@ -15,8 +18,286 @@ pub struct AppConfig {
     pub log_level: String,
 
     /// Optional database URL for persistent storage
-    #[allow(dead_code)] // Will be used when database features are enabled
     pub database_url: Option<String>,
+
+    /// Maximum number of pooled database connections
+    pub database_max_connections: u32,
+
+    /// Reject telemetry from devices missing from the device registry
+    pub require_registered_devices: bool,
+
+    /// Limits applied to incoming telemetry
+    pub validation: ValidationConfig,
+
+    /// How long client message IDs are remembered for deduplication, in seconds
+    pub idempotency_window_secs: i64,
+
+    /// How many times a webhook delivery is attempted before it is dead-lettered
+    pub webhook_max_attempts: u32,
+
+    /// Delay before the first webhook retry, in milliseconds; doubled for each further retry
+    pub webhook_retry_base_ms: u64,
+
+    /// Deliveries to one webhook that may be under way at once before further ones are dead-lettered
+    pub webhook_max_in_flight: usize,
+
+    /// How often devices without an interval of their own are expected to report, in seconds
+    pub heartbeat_interval_secs: i64,
+
+    /// Expected intervals a device may stay silent before it is considered offline
+    pub heartbeat_offline_factor: i32,
+
+    /// How often device connectivity is re-evaluated, in seconds
+    pub heartbeat_check_secs: u64,
+
+    /// How long telemetry is kept before the background sweeper deletes it
+    pub retention: RetentionConfig,
+
+    /// Caps on the telemetry held by the in-memory store
+    pub memory_limits: MemoryLimits,
+
+    /// Where and how the in-memory store persists telemetry across restarts
+    pub persistence: PersistenceConfig,
+}
+
+/// Inclusive range of accepted values for a metric
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct MetricRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl MetricRange {
+    /// Parse a range written as `min..max`, e.g. `-40..85`
+    pub fn parse(range: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid range '{}', expected e.g. -40..85", range);
+
+        let (min, max) = range.split_once("..").ok_or_else(invalid)?;
+        let min: f64 = min.trim().parse().map_err(|_| invalid())?;
+        let max: f64 = max.trim().parse().map_err(|_| invalid())?;
+        if min.is_nan() || max.is_nan() || min > max {
+            return Err(invalid());
+        }
+
+        Ok(Self { min, max })
+    }
+
+    pub fn contains(&self, value: f64) -> bool {
+        self.min <= value && value <= self.max
+    }
+}
+
+/// Rules incoming telemetry must satisfy before it is stored
+#[derive(Debug, Clone, Deserialize)]
+pub struct ValidationConfig {
+    /// Accepted values per metric name; metrics without a range accept any finite value
+    pub metric_ranges: BTreeMap<String, MetricRange>,
+
+    /// Longest accepted device ID, in bytes
+    pub device_id_max_length: usize,
+
+    /// How far into the future a reading's timestamp may be, in seconds
+    pub max_clock_skew_secs: i64,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        let ranges = [
+            ("temperature", -100.0, 200.0),
+            ("humidity", 0.0, 100.0),
+            ("pressure", 300.0, 1100.0),
+        ];
+
+        Self {
+            metric_ranges: ranges
+                .into_iter()
+                .map(|(name, min, max)| (name.to_string(), MetricRange { min, max }))
+                .collect(),
+            device_id_max_length: 128,
+            max_clock_skew_secs: 300,
+        }
+    }
+}
+
+impl ValidationConfig {
+    /// Override metric ranges from a list such as `humidity=0..100,co2=0..10000`
+    fn apply_ranges(&mut self, list: &str) -> Result<(), String> {
+        for entry in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (name, range) = entry.split_once('=').ok_or_else(|| {
+                format!("Invalid metric range '{}', expected name=min..max", entry)
+            })?;
+            self.metric_ranges
+                .insert(name.trim().to_string(), MetricRange::parse(range)?);
+        }
+        Ok(())
+    }
+}
+
+/// Caps on the telemetry held by the in-memory store; the oldest readings are
+/// evicted to stay within them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub struct MemoryLimits {
+    /// Most readings kept per device
+    pub max_records_per_device: Option<usize>,
+
+    /// Most readings kept across every device
+    pub max_total_records: Option<usize>,
+
+    /// Approximate memory the readings may take, in bytes
+    pub max_bytes: Option<usize>,
+}
+
+impl MemoryLimits {
+    /// Whether a cap spanning every device is set
+    pub fn has_global_cap(&self) -> bool {
+        self.max_total_records.is_some() || self.max_bytes.is_some()
+    }
+}
+
+/// When writes to the write-ahead log are flushed to disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FsyncPolicy {
+    /// After every write; nothing acknowledged is lost on power failure
+    Always,
+    /// Every `fsync_interval_ms`; a power failure loses at most that much
+    Interval,
+    /// Left to the operating system; only a process crash is survived
+    Never,
+}
+
+impl std::str::FromStr for FsyncPolicy {
+    type Err = String;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy.trim().to_ascii_lowercase().as_str() {
+            "always" => Ok(Self::Always),
+            "interval" => Ok(Self::Interval),
+            "never" => Ok(Self::Never),
+            _ => Err(format!(
+                "Invalid fsync policy '{}', expected always, interval or never",
+                policy
+            )),
+        }
+    }
+}
+
+/// Persistence of the in-memory store to a write-ahead log and snapshots
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PersistenceConfig {
+    /// Directory holding the log and snapshot; the store is not persisted without one
+    pub data_dir: Option<String>,
+
+    pub fsync: FsyncPolicy,
+
+    /// How often the log is flushed to disk under [`FsyncPolicy::Interval`], in milliseconds
+    pub fsync_interval_ms: u64,
+
+    /// How often the log is compacted into a snapshot, in seconds
+    pub snapshot_interval_secs: u64,
+}
+
+impl Default for PersistenceConfig {
+    fn default() -> Self {
+        Self {
+            data_dir: None,
+            fsync: FsyncPolicy::Interval,
+            fsync_interval_ms: 1000,
+            snapshot_interval_secs: 5 * 60,
+        }
+    }
+}
+
+/// Retention period for the devices matching a pattern
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct DeviceRetention {
+    /// Device ID or glob pattern (`*`, `?`)
+    pub device: String,
+    pub days: i64,
+}
+
+/// How long telemetry is kept, enforced by a periodic sweep
+///
+/// A reading is kept for the first matching device override, else for the
+/// default; with neither it is kept forever. Rollups are kept for their
+/// tier's period, or forever without one.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RetentionConfig {
+    /// Days readings are kept when nothing more specific applies
+    pub default_days: Option<i64>,
+
+    /// Per-device overrides, the first matching one wins
+    pub devices: Vec<DeviceRetention>,
+
+    /// Days kept per rollup tier
+    pub tiers: BTreeMap<TelemetryTier, i64>,
+
+    /// How often the sweep runs, in seconds
+    pub sweep_interval_secs: u64,
+}
+
+impl RetentionConfig {
+    /// Whether any retention period is configured
+    pub fn is_enabled(&self) -> bool {
+        self.default_days.is_some() || !self.devices.is_empty() || !self.tiers.is_empty()
+    }
+
+    /// Days readings of `device_id` are kept, or `None` to keep them forever
+    pub fn raw_days(&self, device_id: &str) -> Option<i64> {
+        self.devices
+            .iter()
+            .find(|d| crate::models::glob_match(&d.device, device_id))
+            .map(|d| d.days)
+            .or(self.default_days)
+    }
+
+    /// Days rollups in `tier` are kept, or `None` to keep them forever
+    pub fn rollup_days(&self, tier: TelemetryTier) -> Option<i64> {
+        self.tiers.get(&tier).copied()
+    }
+
+    /// Set device overrides from a list such as `freezer-*=90,test-1=1`
+    fn apply_devices(&mut self, list: &str) -> Result<(), String> {
+        for entry in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (device, days) = entry.split_once('=').ok_or_else(|| {
+                format!("Invalid device retention '{}', expected device=days", entry)
+            })?;
+            self.devices.push(DeviceRetention {
+                device: device.trim().to_string(),
+                days: parse_days(days)?,
+            });
+        }
+        Ok(())
+    }
+
+    /// Set rollup tier periods from a list such as `1m=30,1h=365`
+    fn apply_tiers(&mut self, list: &str) -> Result<(), String> {
+        for entry in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (tier, days) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid tier retention '{}', expected tier=days", entry))?;
+            let tier: TelemetryTier = tier.trim().parse()?;
+            // Raw readings are kept for the default, so it has a single setting
+            if tier == TelemetryTier::Raw {
+                return Err(
+                    "Raw readings are kept for RETENTION_DAYS, not a raw tier period".to_string(),
+                );
+            }
+            self.tiers.insert(tier, parse_days(days)?);
+        }
+        Ok(())
+    }
+}
+
+/// Parse a positive number of days
+fn parse_days(days: &str) -> Result<i64, String> {
+    match days.trim().parse() {
+        Ok(days) if days > 0 => Ok(days),
+        _ => Err(format!(
+            "Invalid retention '{}', expected a positive number of days",
+            days
+        )),
+    }
 }
 
 impl AppConfig {
@@ -31,6 +312,22 @@ impl AppConfig {
             port: 8080,
             log_level: "info".to_string(),
             database_url: None,
+            database_max_connections: 10,
+            require_registered_devices: false,
+            validation: ValidationConfig::default(),
+            idempotency_window_secs: 24 * 60 * 60,
+            webhook_max_attempts: 5,
+            webhook_retry_base_ms: 1000,
+            webhook_max_in_flight: 100,
+            heartbeat_interval_secs: 300,
+            heartbeat_offline_factor: 3,
+            heartbeat_check_secs: 30,
+            retention: RetentionConfig {
+                sweep_interval_secs: 60 * 60,
+                ..RetentionConfig::default()
+            },
+            memory_limits: MemoryLimits::default(),
+            persistence: PersistenceConfig::default(),
         };
 
         // Load configuration from environment variables
@@ -41,12 +338,131 @@ impl AppConfig {
             .unwrap_or(default_config.port);
         let log_level = env::var("LOG_LEVEL").unwrap_or(default_config.log_level);
         let database_url = env::var("DATABASE_URL").ok();
+        let database_max_connections = env::var("DATABASE_MAX_CONNECTIONS")
+            .ok()
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(default_config.database_max_connections);
+        let require_registered_devices = env::var("REQUIRE_REGISTERED_DEVICES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default_config.require_registered_devices);
+
+        let mut validation = default_config.validation;
+        if let Ok(ranges) = env::var("METRIC_RANGES") {
+            validation
+                .apply_ranges(&ranges)
+                .map_err(config::ConfigError::Message)?;
+        }
+        if let Some(length) = env::var("DEVICE_ID_MAX_LENGTH")
+            .ok()
+            .and_then(|n| n.parse().ok())
+        {
+            validation.device_id_max_length = length;
+        }
+        if let Some(skew) = env::var("MAX_CLOCK_SKEW_SECS")
+            .ok()
+            .and_then(|n| n.parse().ok())
+        {
+            validation.max_clock_skew_secs = skew;
+        }
+        let idempotency_window_secs = env::var("IDEMPOTENCY_WINDOW_SECS")
+            .ok()
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(default_config.idempotency_window_secs);
+        let webhook_max_attempts = env::var("WEBHOOK_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(default_config.webhook_max_attempts);
+        let webhook_retry_base_ms = env::var("WEBHOOK_RETRY_BASE_MS")
+            .ok()
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(default_config.webhook_retry_base_ms);
+        let webhook_max_in_flight = env::var("WEBHOOK_MAX_IN_FLIGHT")
+            .ok()
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(default_config.webhook_max_in_flight);
+        let heartbeat_interval_secs = env::var("HEARTBEAT_INTERVAL_SECS")
+            .ok()
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(default_config.heartbeat_interval_secs);
+        let heartbeat_offline_factor = env::var("HEARTBEAT_OFFLINE_FACTOR")
+            .ok()
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(default_config.heartbeat_offline_factor);
+        let heartbeat_check_secs = env::var("HEARTBEAT_CHECK_SECS")
+            .ok()
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(default_config.heartbeat_check_secs);
+
+        let mut retention = default_config.retention;
+        if let Ok(days) = env::var("RETENTION_DAYS") {
+            retention.default_days = Some(parse_days(&days).map_err(config::ConfigError::Message)?);
+        }
+        if let Ok(devices) = env::var("RETENTION_DEVICE_DAYS") {
+            retention
+                .apply_devices(&devices)
+                .map_err(config::ConfigError::Message)?;
+        }
+        if let Ok(tiers) = env::var("RETENTION_TIER_DAYS") {
+            retention
+                .apply_tiers(&tiers)
+                .map_err(config::ConfigError::Message)?;
+        }
+        if let Some(secs) = env::var("RETENTION_SWEEP_SECS")
+            .ok()
+            .and_then(|n| n.parse().ok())
+        {
+            retention.sweep_interval_secs = secs;
+        }
+
+        let memory_limits = MemoryLimits {
+            max_records_per_device: env::var("MEMORY_MAX_RECORDS_PER_DEVICE")
+                .ok()
+                .and_then(|n| n.parse().ok()),
+            max_total_records: env::var("MEMORY_MAX_TOTAL_RECORDS")
+                .ok()
+                .and_then(|n| n.parse().ok()),
+            max_bytes: env::var("MEMORY_MAX_BYTES")
+                .ok()
+                .and_then(|n| n.parse().ok()),
+        };
+
+        let mut persistence = default_config.persistence;
+        persistence.data_dir = env::var("DATA_DIR").ok().filter(|d| !d.is_empty());
+        if let Ok(policy) = env::var("WAL_FSYNC") {
+            persistence.fsync = policy.parse().map_err(config::ConfigError::Message)?;
+        }
+        if let Some(ms) = env::var("WAL_FSYNC_INTERVAL_MS")
+            .ok()
+            .and_then(|n| n.parse().ok())
+        {
+            persistence.fsync_interval_ms = ms;
+        }
+        if let Some(secs) = env::var("SNAPSHOT_INTERVAL_SECS")
+            .ok()
+            .and_then(|n| n.parse().ok())
+        {
+            persistence.snapshot_interval_secs = secs;
+        }
 
         Ok(Self {
             host,
             port,
             log_level,
             database_url,
+            database_max_connections,
+            require_registered_devices,
+            validation,
+            idempotency_window_secs,
+            webhook_max_attempts,
+            webhook_retry_base_ms,
+            webhook_max_in_flight,
+            heartbeat_interval_secs,
+            heartbeat_offline_factor,
+            heartbeat_check_secs,
+            retention,
+            memory_limits,
+            persistence,
         })
     }
 }
@@ -10,6 +10,12 @@ pub enum AppError {
     #[error("Invalid request: {0}")]
     BadRequest(String),
 
+    #[error("Invalid request: {}", describe_fields(.0))]
+    InvalidFields(Vec<FieldError>),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
     #[error("Internal server error: {0}")]
     InternalError(String),
 
@@ -18,19 +24,55 @@ pub enum AppError {
     Unauthorized(String),
 }
 
+/// A request field that failed validation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldError {
+    /// Path of the offending field, e.g. `metrics.humidity`
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+fn describe_fields(fields: &[FieldError]) -> String {
+    fields
+        .iter()
+        .map(|f| format!("{} {}", f.field, f.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 #[derive(Serialize)]
-struct ErrorResponse {
+struct ErrorResponse<'a> {
     error: String,
+
+    /// Every failing field, for validation errors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<&'a [FieldError]>,
 }
 
 impl ResponseError for AppError {
     fn error_response(&self) -> HttpResponse {
         let error = self.to_string();
-        let response = ErrorResponse { error };
+        let details = match self {
+            AppError::InvalidFields(fields) => Some(fields.as_slice()),
+            _ => None,
+        };
+        let response = ErrorResponse { error, details };
 
         match self {
             AppError::NotFound(_) => HttpResponse::NotFound().json(response),
-            AppError::BadRequest(_) => HttpResponse::BadRequest().json(response),
+            AppError::BadRequest(_) | AppError::InvalidFields(_) => {
+                HttpResponse::BadRequest().json(response)
+            }
+            AppError::Conflict(_) => HttpResponse::Conflict().json(response),
             AppError::InternalError(_) => {
                 tracing::error!("Internal error: {}", self);
                 HttpResponse::InternalServerError().json(response)
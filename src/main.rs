@@ -1,17 +1,16 @@
-mod api;
-mod config;
-mod errors;
-mod models;
-mod services;
-mod storage;
+use std::sync::Arc;
 
 use actix_web::{web, App, HttpServer};
+use chrono::Duration;
 use tracing_actix_web::TracingLogger;
 
-use crate::api::routes;
-use crate::config::AppConfig;
-use crate::services::TelemetryService;
-use crate::storage::TelemetryStore;
+use rustegrate::api::routes;
+use rustegrate::config::AppConfig;
+use rustegrate::services::{
+    AlertService, DeviceService, HeartbeatService, RetentionService, TelemetryService,
+    WebhookService,
+};
+use rustegrate::storage;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -27,12 +26,73 @@ async fn main() -> std::io::Result<()> {
         .with_env_filter(config.log_level.clone())
         .init();
 
-    // Initialize telemetry store
-    let telemetry_store = TelemetryStore::new();
+    // Initialize the storage backends selected by the configuration
+    let storage = storage::create_storage(&config)
+        .await
+        .expect("Failed to initialize storage backend");
+
+    // Create alerting service, shared with the telemetry service that feeds it
+    let alert_service = Arc::new(AlertService::with_repository(storage.alert_rules));
+
+    // Create webhook service, likewise fed by the telemetry service
+    let webhook_service = Arc::new(
+        WebhookService::with_repository(storage.webhooks)
+            .with_retry(
+                config.webhook_max_attempts,
+                std::time::Duration::from_millis(config.webhook_retry_base_ms),
+            )
+            .with_max_in_flight(config.webhook_max_in_flight),
+    );
+
+    // Track device connectivity, starting from when each device was last seen
+    let heartbeat_service = Arc::new(
+        HeartbeatService::new()
+            .with_default_interval(Duration::seconds(config.heartbeat_interval_secs))
+            .with_offline_factor(config.heartbeat_offline_factor)
+            .with_registry(storage.devices.clone()),
+    );
+    if let Err(e) = heartbeat_service.seed(storage.telemetry.as_ref()).await {
+        tracing::warn!("Failed to load last seen times: {}", e);
+    }
+    tokio::spawn(
+        heartbeat_service
+            .clone()
+            .run(std::time::Duration::from_secs(
+                config.heartbeat_check_secs.max(1),
+            )),
+    );
 
     // Create telemetry service
-    let telemetry_service = TelemetryService::new(telemetry_store);
-    let service_data = web::Data::new(telemetry_service);
+    let mut telemetry_service = TelemetryService::with_repository(storage.telemetry)
+        .with_validation(config.validation.clone())
+        .with_idempotency_window(Duration::seconds(config.idempotency_window_secs))
+        .with_alerts(alert_service.clone())
+        .with_webhooks(webhook_service.clone())
+        .with_heartbeats(heartbeat_service.clone());
+    if config.require_registered_devices {
+        telemetry_service = telemetry_service.require_registered_devices(storage.devices.clone());
+    }
+    let telemetry_service = Arc::new(telemetry_service);
+
+    // Delete expired telemetry in the background
+    let retention_service = Arc::new(RetentionService::new(
+        telemetry_service.clone(),
+        config.retention.clone(),
+    ));
+    tokio::spawn(retention_service.clone().run());
+
+    let service_data = web::Data::from(telemetry_service);
+
+    // Create device registry service
+    let device_service = web::Data::new(
+        DeviceService::with_repository(storage.devices)
+            .with_validation(config.validation.clone())
+            .with_heartbeats(heartbeat_service.clone()),
+    );
+    let alert_service = web::Data::from(alert_service);
+    let webhook_service = web::Data::from(webhook_service);
+    let heartbeat_service = web::Data::from(heartbeat_service);
+    let retention_service = web::Data::from(retention_service);
 
     // Start HTTP server
     tracing::info!("Starting server at http://{}:{}", host, port);
@@ -40,6 +100,11 @@ async fn main() -> std::io::Result<()> {
         App::new()
             .wrap(TracingLogger::default())
             .app_data(service_data.clone())
+            .app_data(device_service.clone())
+            .app_data(alert_service.clone())
+            .app_data(webhook_service.clone())
+            .app_data(heartbeat_service.clone())
+            .app_data(retention_service.clone())
             .app_data(web::Data::new(config.clone()))
             .configure(routes::configure)
     })
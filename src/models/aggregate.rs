@@ -0,0 +1,203 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Statistic that can be requested for each aggregation bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateFunction {
+    Avg,
+    Min,
+    Max,
+    Count,
+    /// Value of the most recent reading in the bucket
+    Last,
+}
+
+impl AggregateFunction {
+    /// Parse a comma-separated list such as `avg,min,max,count`
+    pub fn parse_list(list: &str) -> Result<Vec<Self>, String> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| match name {
+                "avg" => Ok(Self::Avg),
+                "min" => Ok(Self::Min),
+                "max" => Ok(Self::Max),
+                "count" => Ok(Self::Count),
+                "last" => Ok(Self::Last),
+                other => Err(format!("Unknown aggregate function '{}'", other)),
+            })
+            .collect()
+    }
+}
+
+/// Parse a bucket width such as `30s`, `5m`, `1h` or `1d`
+pub fn parse_bucket(bucket: &str) -> Result<Duration, String> {
+    let invalid = || {
+        format!(
+            "Invalid bucket '{}', expected e.g. 30s, 5m, 1h or 1d",
+            bucket
+        )
+    };
+
+    let split = bucket.len().checked_sub(1).ok_or_else(invalid)?;
+    let (amount, unit) = bucket.split_at(split);
+    let amount: i64 = amount.parse().map_err(|_| invalid())?;
+    if amount <= 0 {
+        return Err(invalid());
+    }
+
+    let seconds = match unit {
+        "s" => Some(amount),
+        "m" => amount.checked_mul(60),
+        "h" => amount.checked_mul(60 * 60),
+        "d" => amount.checked_mul(24 * 60 * 60),
+        _ => None,
+    };
+    seconds.and_then(Duration::try_seconds).ok_or_else(invalid)
+}
+
+/// Start of the bucket containing `timestamp`, with buckets aligned to the Unix epoch
+pub fn bucket_start(timestamp: DateTime<Utc>, bucket: Duration) -> DateTime<Utc> {
+    let width = bucket.num_seconds();
+    let start = timestamp.timestamp().div_euclid(width) * width;
+    DateTime::from_timestamp(start, 0).unwrap_or(timestamp)
+}
+
+/// Running statistics for a single metric within a bucket
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct MetricStats {
+    pub count: u64,
+    pub sum: f64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+
+    /// Value of the most recent sample, by timestamp
+    pub last: Option<f64>,
+    pub last_at: Option<DateTime<Utc>>,
+}
+
+impl MetricStats {
+    /// Fold a sample taken at `timestamp` into the statistics
+    pub fn record(&mut self, timestamp: DateTime<Utc>, value: f64) {
+        self.merge(&Self {
+            count: 1,
+            sum: value,
+            min: Some(value),
+            max: Some(value),
+            last: Some(value),
+            last_at: Some(timestamp),
+        });
+    }
+
+    /// Fold the statistics of other samples of the same metric into these
+    pub fn merge(&mut self, other: &Self) {
+        self.count += other.count;
+        self.sum += other.sum;
+        self.min = match (self.min, other.min) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.max = match (self.max, other.max) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        // On equal timestamps the sample folded in later wins
+        if other.last_at.is_some() && other.last_at >= self.last_at {
+            self.last = other.last;
+            self.last_at = other.last_at;
+        }
+    }
+
+    /// Project the statistics onto the requested functions
+    pub fn project(&self, functions: &[AggregateFunction]) -> MetricAggregate {
+        let wants = |f| functions.contains(&f);
+        let avg = (self.count > 0).then(|| self.sum / self.count as f64);
+
+        MetricAggregate {
+            avg: avg.filter(|_| wants(AggregateFunction::Avg)),
+            min: self.min.filter(|_| wants(AggregateFunction::Min)),
+            max: self.max.filter(|_| wants(AggregateFunction::Max)),
+            count: wants(AggregateFunction::Count).then_some(self.count),
+            last: self.last.filter(|_| wants(AggregateFunction::Last)),
+        }
+    }
+}
+
+/// Statistics for every metric within one time bucket, as computed by storage
+#[derive(Debug, Clone, PartialEq)]
+pub struct BucketStats {
+    pub bucket_start: DateTime<Utc>,
+
+    /// Statistics keyed by metric name; metrics absent from the bucket are omitted
+    pub metrics: BTreeMap<String, MetricStats>,
+}
+
+impl BucketStats {
+    /// Create an empty bucket starting at `bucket_start`
+    pub fn new(bucket_start: DateTime<Utc>) -> Self {
+        Self {
+            bucket_start,
+            metrics: BTreeMap::new(),
+        }
+    }
+}
+
+/// Requested statistics for one metric in an aggregation response
+#[derive(Debug, Serialize)]
+pub struct MetricAggregate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last: Option<f64>,
+}
+
+/// One time bucket in an aggregation response
+#[derive(Debug, Serialize)]
+pub struct AggregateBucket {
+    pub bucket_start: DateTime<Utc>,
+    pub metrics: BTreeMap<String, MetricAggregate>,
+}
+
+/// Query parameters for aggregating telemetry data
+#[derive(Debug, Deserialize)]
+pub struct AggregateQuery {
+    /// Bucket width, e.g. `5m`
+    pub bucket: String,
+
+    /// Comma-separated statistics to compute
+    #[serde(rename = "fn", default = "default_functions")]
+    pub functions: String,
+
+    /// Comma-separated metric names to aggregate; all metrics when absent
+    pub metrics: Option<String>,
+
+    /// Optional start time filter (inclusive)
+    pub start_time: Option<DateTime<Utc>>,
+
+    /// Optional end time filter (inclusive)
+    pub end_time: Option<DateTime<Utc>>,
+
+    /// Tier to read, e.g. `raw` or `1h`; the coarsest that answers exactly when absent
+    pub tier: Option<String>,
+}
+
+/// Parse a comma-separated list of metric names such as `co2,temperature`
+pub fn parse_metric_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn default_functions() -> String {
+    "avg,min,max,count".to_string()
+}
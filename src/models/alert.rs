@@ -0,0 +1,141 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::Comparator;
+
+/// Threshold rule evaluated against every accepted reading
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub id: Uuid,
+
+    /// Human-readable name, copied onto the alerts the rule raises
+    pub name: String,
+
+    /// Device ID or glob pattern (`*`, `?`) selecting the devices checked
+    pub device: String,
+
+    /// Metric compared against the threshold
+    pub metric: String,
+
+    pub op: Comparator,
+
+    pub threshold: f64,
+
+    /// How long the condition must hold before the alert fires
+    pub duration_secs: i64,
+
+    /// How far back past the threshold a value must go to resolve the alert
+    pub hysteresis: f64,
+
+    /// When the rule was created
+    pub created_at: DateTime<Utc>,
+
+    /// When the rule was last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+impl AlertRule {
+    /// Create a rule from a request, with a new ID
+    pub fn new(request: AlertRuleRequest) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            name: request.name,
+            device: request.device,
+            metric: request.metric,
+            op: request.op,
+            threshold: request.threshold,
+            duration_secs: request.duration_secs,
+            hysteresis: request.hysteresis,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// How long the condition must hold before the alert fires
+    ///
+    /// A duration too long to represent never elapses.
+    pub fn duration(&self) -> Duration {
+        Duration::try_seconds(self.duration_secs).unwrap_or(Duration::MAX)
+    }
+
+    /// Whether `value` breaches the threshold
+    pub fn breached(&self, value: f64) -> bool {
+        self.op.compare(value, self.threshold)
+    }
+
+    /// Whether `value` is far enough back past the threshold to resolve a firing alert
+    pub fn recovered(&self, value: f64) -> bool {
+        let threshold = match self.op {
+            Comparator::Gt | Comparator::Gte => self.threshold - self.hysteresis,
+            Comparator::Lt | Comparator::Lte => self.threshold + self.hysteresis,
+        };
+        !self.op.compare(value, threshold)
+    }
+}
+
+/// Represents a request to create or replace an alert rule
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AlertRuleRequest {
+    pub name: String,
+    pub device: String,
+    pub metric: String,
+    pub op: Comparator,
+    pub threshold: f64,
+    #[serde(default)]
+    pub duration_secs: i64,
+    #[serde(default)]
+    pub hysteresis: f64,
+}
+
+/// Whether an alert is still active
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertState {
+    Firing,
+    Resolved,
+}
+
+/// One occurrence of a rule's condition holding for a device
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub id: Uuid,
+    pub rule_id: Uuid,
+    pub rule_name: String,
+    pub device_id: String,
+    pub metric: String,
+    pub state: AlertState,
+
+    /// Threshold of the rule when the alert fired
+    pub threshold: f64,
+
+    /// Value of the reading that fired the alert
+    pub value: f64,
+
+    /// Value of the most recent reading evaluated for the alert
+    pub last_value: f64,
+
+    /// Timestamp of the reading that fired the alert
+    pub started_at: DateTime<Utc>,
+
+    /// Timestamp of the reading that resolved the alert
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+/// Query parameters for listing alerts
+#[derive(Debug, Deserialize)]
+pub struct AlertQuery {
+    pub state: Option<AlertState>,
+    pub device_id: Option<String>,
+    pub rule_id: Option<Uuid>,
+
+    /// Maximum number of alerts to return
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+fn default_limit() -> usize {
+    100
+}
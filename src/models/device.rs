@@ -0,0 +1,204 @@
+use std::str::FromStr;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Lifecycle status of a registered device
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceStatus {
+    /// Provisioned and expected to report
+    #[default]
+    Active,
+    /// Provisioned but not currently expected to report
+    Inactive,
+    /// Temporarily out of service
+    Maintenance,
+    /// Permanently retired
+    Decommissioned,
+}
+
+impl DeviceStatus {
+    /// Name used when persisting the status
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Active => "active",
+            Self::Inactive => "inactive",
+            Self::Maintenance => "maintenance",
+            Self::Decommissioned => "decommissioned",
+        }
+    }
+
+    /// Whether telemetry is accepted from a device in this status
+    pub fn accepts_telemetry(&self) -> bool {
+        matches!(self, Self::Active | Self::Maintenance)
+    }
+}
+
+impl FromStr for DeviceStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "active" => Ok(Self::Active),
+            "inactive" => Ok(Self::Inactive),
+            "maintenance" => Ok(Self::Maintenance),
+            "decommissioned" => Ok(Self::Decommissioned),
+            other => Err(format!("Unknown device status '{}'", other)),
+        }
+    }
+}
+
+/// A device provisioned in the device registry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Device {
+    pub device_id: String,
+
+    /// Human-readable name
+    pub name: String,
+
+    /// Kind of device, e.g. "freezer-sensor"
+    #[serde(rename = "type")]
+    pub device_type: String,
+
+    /// Optional physical location
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+
+    /// Free-form labels used for grouping
+    pub tags: Vec<String>,
+
+    pub status: DeviceStatus,
+
+    /// How often the device is expected to report, overriding the default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_interval_secs: Option<i64>,
+
+    /// When the device was registered
+    pub created_at: DateTime<Utc>,
+
+    /// When the registration was last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Represents a request to register a new device
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreateDeviceRequest {
+    pub device_id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub device_type: String,
+    pub location: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub status: DeviceStatus,
+    pub expected_interval_secs: Option<i64>,
+}
+
+/// Represents a request to replace a device's registration details
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UpdateDeviceRequest {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub device_type: String,
+    pub location: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub status: DeviceStatus,
+    pub expected_interval_secs: Option<i64>,
+}
+
+impl From<CreateDeviceRequest> for Device {
+    fn from(req: CreateDeviceRequest) -> Self {
+        let now = Utc::now();
+        Self {
+            device_id: req.device_id,
+            name: req.name,
+            device_type: req.device_type,
+            location: req.location,
+            tags: req.tags,
+            status: req.status,
+            expected_interval_secs: req.expected_interval_secs,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// Summary of the telemetry stored for one device
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceSummary {
+    pub device_id: String,
+
+    /// Number of stored telemetry records
+    pub record_count: u64,
+
+    /// Timestamp of the oldest stored record
+    pub first_timestamp: DateTime<Utc>,
+
+    /// Timestamp of the newest stored record
+    pub last_timestamp: DateTime<Utc>,
+
+    /// When the server last accepted a stored reading from the device
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Query parameters for listing devices
+#[derive(Debug, Deserialize)]
+pub struct DeviceQuery {
+    /// Only list devices whose ID starts with this prefix
+    pub prefix: Option<String>,
+
+    /// Maximum number of devices to return
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+
+    /// Opaque cursor from a previous page's `next_cursor`
+    pub cursor: Option<String>,
+
+    /// List registered devices instead of devices with stored telemetry
+    #[serde(default)]
+    pub registered: bool,
+}
+
+fn default_limit() -> usize {
+    100
+}
+
+/// One page of devices, ordered by device ID
+#[derive(Debug, Serialize)]
+pub struct DevicePage {
+    pub data: Vec<DeviceSummary>,
+
+    /// Cursor for the next page, absent on the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+/// One page of registered devices, ordered by device ID
+#[derive(Debug, Serialize)]
+pub struct RegisteredDevicePage {
+    pub data: Vec<Device>,
+
+    /// Cursor for the next page, absent on the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+/// Encode the device ID a page ended at as an opaque cursor
+pub fn encode_device_cursor(device_id: &str) -> String {
+    URL_SAFE_NO_PAD.encode(device_id)
+}
+
+/// Decode a cursor produced by [`encode_device_cursor`]
+pub fn decode_device_cursor(cursor: &str) -> Result<String, String> {
+    URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|raw| String::from_utf8(raw).ok())
+        .ok_or_else(|| "Invalid cursor".to_string())
+}
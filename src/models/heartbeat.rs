@@ -0,0 +1,77 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Whether a device is reporting as often as it is expected to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Connectivity {
+    /// Reported within its expected interval
+    Online,
+    /// Overdue, but not yet for long enough to be considered offline
+    Stale,
+    /// Silent for several expected intervals, or never seen
+    Offline,
+}
+
+/// Connectivity of one device as last evaluated
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceConnectivity {
+    pub device_id: String,
+    pub status: Connectivity,
+
+    /// When the server last accepted a reading from the device
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<DateTime<Utc>>,
+
+    /// How often the device is expected to report
+    pub expected_interval_secs: i64,
+
+    /// When the device entered its current status
+    pub since: DateTime<Utc>,
+}
+
+/// A change in a device's connectivity
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusTransition {
+    pub device_id: String,
+    pub from: Connectivity,
+    pub to: Connectivity,
+
+    /// When the change was detected
+    pub at: DateTime<Utc>,
+}
+
+/// Connectivity counts across every tracked device
+#[derive(Debug, Serialize)]
+pub struct FleetStatus {
+    pub total: usize,
+    pub online: usize,
+    pub stale: usize,
+    pub offline: usize,
+
+    /// Devices matching the query, ordered by device ID
+    pub devices: Vec<DeviceConnectivity>,
+}
+
+/// Query parameters for the fleet summary
+#[derive(Debug, Deserialize)]
+pub struct FleetStatusQuery {
+    /// Only list devices with this status
+    pub status: Option<Connectivity>,
+
+    /// Maximum number of devices to list
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+/// Query parameters for a device's transition history
+#[derive(Debug, Deserialize)]
+pub struct TransitionQuery {
+    /// Maximum number of transitions to return
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+fn default_limit() -> usize {
+    100
+}
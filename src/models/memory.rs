@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::MemoryLimits;
+
+/// Readings evicted to stay within each memory cap
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct EvictionCounts {
+    /// Evicted because their device held too many readings
+    pub device_cap: u64,
+
+    /// Evicted because the store held too many readings
+    pub total_cap: u64,
+
+    /// Evicted because the readings took too much memory
+    pub byte_cap: u64,
+}
+
+/// Memory held for one device's readings
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceMemoryUsage {
+    pub device_id: String,
+    pub records: usize,
+
+    /// Approximate memory the readings take, in bytes
+    pub approx_bytes: usize,
+
+    /// Readings of this device evicted since startup
+    pub evicted: u64,
+}
+
+/// Memory held by the in-memory store and what it evicted to stay within its caps
+#[derive(Debug, Serialize)]
+pub struct MemoryUsage {
+    pub limits: MemoryLimits,
+    pub total_records: usize,
+
+    /// Approximate memory every reading takes, in bytes
+    pub approx_bytes: usize,
+
+    pub evictions: EvictionCounts,
+
+    /// Devices matching the query, largest first
+    pub devices: Vec<DeviceMemoryUsage>,
+}
+
+/// Query parameters for memory usage
+#[derive(Debug, Deserialize)]
+pub struct MemoryUsageQuery {
+    /// Only report this device
+    pub device_id: Option<String>,
+
+    /// Maximum number of devices to report
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+fn default_limit() -> usize {
+    100
+}
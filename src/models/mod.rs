@@ -1,3 +1,21 @@
+mod aggregate;
+mod alert;
+mod device;
+mod heartbeat;
+mod memory;
+mod retention;
+mod rollup;
+mod subscription;
 mod telemetry;
+mod webhook;
 
+pub use aggregate::*;
+pub use alert::*;
+pub use device::*;
+pub use heartbeat::*;
+pub use memory::*;
+pub use retention::*;
+pub use rollup::*;
+pub use subscription::*;
 pub use telemetry::*;
+pub use webhook::*;
@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::config::RetentionConfig;
+
+/// Outcome of one retention sweep
+#[derive(Debug, Clone, Serialize)]
+pub struct RetentionRun {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+
+    /// Devices with stored telemetry that were looked at
+    pub devices_checked: usize,
+
+    /// Devices that had records deleted
+    pub devices_pruned: usize,
+
+    pub records_deleted: usize,
+
+    /// Per-metric rollups deleted across every rollup tier
+    pub rollups_deleted: usize,
+
+    /// Devices or rollup tiers whose data could not be deleted
+    pub failures: usize,
+
+    /// Why the last failure happened
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// Retention policy and what the sweeper has done since startup
+#[derive(Debug, Serialize)]
+pub struct RetentionStatus {
+    pub policy: RetentionConfig,
+
+    /// Whether a sweep is in progress
+    pub running: bool,
+
+    /// Sweeps completed since startup
+    pub runs: u64,
+
+    /// Records deleted by every sweep since startup
+    pub total_deleted: u64,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_run: Option<RetentionRun>,
+}
@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{bucket_start, MetricStats, TelemetryData};
+
+/// Resolution at which telemetry is stored
+///
+/// Raw readings are folded into each rollup tier as they arrive, so coarse
+/// queries need not scan every reading and rollups can outlive the readings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+pub enum TelemetryTier {
+    /// Individual readings
+    #[serde(rename = "raw")]
+    Raw,
+    /// Per-minute statistics
+    #[serde(rename = "1m")]
+    Minute,
+    /// Per-hour statistics
+    #[serde(rename = "1h")]
+    Hour,
+    /// Per-day statistics
+    #[serde(rename = "1d")]
+    Day,
+}
+
+impl TelemetryTier {
+    /// Tiers holding statistics rather than readings, finest first
+    pub const ROLLUPS: [Self; 3] = [Self::Minute, Self::Hour, Self::Day];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Raw => "raw",
+            Self::Minute => "1m",
+            Self::Hour => "1h",
+            Self::Day => "1d",
+        }
+    }
+
+    /// Width of the tier's buckets, or `None` for raw readings
+    pub fn width(&self) -> Option<Duration> {
+        match self {
+            Self::Raw => None,
+            Self::Minute => Some(Duration::minutes(1)),
+            Self::Hour => Some(Duration::hours(1)),
+            Self::Day => Some(Duration::days(1)),
+        }
+    }
+
+    /// Whether the tier's buckets fit evenly into buckets of width `bucket`
+    pub fn fits(&self, bucket: Duration) -> bool {
+        self.width()
+            .map(|width| bucket.num_seconds() % width.num_seconds() == 0)
+            .unwrap_or(true)
+    }
+
+    /// Whether `timestamp` falls on a boundary between the tier's buckets
+    pub fn aligned(&self, timestamp: DateTime<Utc>) -> bool {
+        self.width()
+            .map(|width| bucket_start(timestamp, width) == timestamp)
+            .unwrap_or(true)
+    }
+
+    /// Coarsest tier answering a query for buckets of width `bucket` exactly
+    ///
+    /// A rollup tier qualifies only when its buckets fit into `bucket` and
+    /// `start` and `end` fall on its boundaries, so that no rollup straddles
+    /// an edge of the range; otherwise raw readings must be scanned.
+    pub fn coarsest_for(
+        bucket: Duration,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self::ROLLUPS
+            .into_iter()
+            .rev()
+            .find(|tier| {
+                tier.fits(bucket)
+                    && start.map(|start| tier.aligned(start)).unwrap_or(true)
+                    && end.map(|end| tier.aligned(end)).unwrap_or(true)
+            })
+            .unwrap_or(Self::Raw)
+    }
+}
+
+impl fmt::Display for TelemetryTier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TelemetryTier {
+    type Err = String;
+
+    fn from_str(tier: &str) -> Result<Self, Self::Err> {
+        match tier {
+            "raw" => Ok(Self::Raw),
+            "1m" => Ok(Self::Minute),
+            "1h" => Ok(Self::Hour),
+            "1d" => Ok(Self::Day),
+            _ => Err(format!(
+                "Unknown telemetry tier '{}', expected raw, 1m, 1h or 1d",
+                tier
+            )),
+        }
+    }
+}
+
+/// Statistics of one metric within one rollup bucket of one device
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MetricRollup {
+    pub device_id: String,
+    pub tier: TelemetryTier,
+    pub bucket_start: DateTime<Utc>,
+    pub metric: String,
+    pub stats: MetricStats,
+}
+
+impl MetricRollup {
+    /// Fold readings into every rollup tier, one entry per device, tier, bucket and metric
+    pub fn fold<'a>(records: impl IntoIterator<Item = &'a TelemetryData>) -> Vec<Self> {
+        let mut rollups: BTreeMap<(&str, TelemetryTier, DateTime<Utc>, &str), MetricStats> =
+            BTreeMap::new();
+        for record in records {
+            for tier in TelemetryTier::ROLLUPS {
+                let Some(width) = tier.width() else {
+                    continue;
+                };
+                let start = bucket_start(record.timestamp, width);
+                for (metric, &value) in &record.metrics {
+                    rollups
+                        .entry((&record.device_id, tier, start, metric))
+                        .or_default()
+                        .record(record.timestamp, value);
+                }
+            }
+        }
+
+        rollups
+            .into_iter()
+            .map(|((device_id, tier, bucket_start, metric), stats)| Self {
+                device_id: device_id.to_string(),
+                tier,
+                bucket_start,
+                metric: metric.to_string(),
+                stats,
+            })
+            .collect()
+    }
+}
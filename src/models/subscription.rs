@@ -0,0 +1,135 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::TelemetryData;
+
+/// How a metric value is compared against a threshold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Comparator {
+    #[serde(alias = ">")]
+    Gt,
+    #[serde(alias = ">=")]
+    Gte,
+    #[serde(alias = "<")]
+    Lt,
+    #[serde(alias = "<=")]
+    Lte,
+}
+
+impl Comparator {
+    /// Name used when persisting the comparator
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Gt => "gt",
+            Self::Gte => "gte",
+            Self::Lt => "lt",
+            Self::Lte => "lte",
+        }
+    }
+
+    /// Whether `value` passes the comparison against `threshold`
+    pub fn compare(&self, value: f64, threshold: f64) -> bool {
+        match self {
+            Self::Gt => value > threshold,
+            Self::Gte => value >= threshold,
+            Self::Lt => value < threshold,
+            Self::Lte => value <= threshold,
+        }
+    }
+}
+
+impl FromStr for Comparator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gt" => Ok(Self::Gt),
+            "gte" => Ok(Self::Gte),
+            "lt" => Ok(Self::Lt),
+            "lte" => Ok(Self::Lte),
+            other => Err(format!("Unknown comparator '{}'", other)),
+        }
+    }
+}
+
+/// Condition on a single metric of a reading
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MetricThreshold {
+    pub metric: String,
+    pub op: Comparator,
+    pub value: f64,
+}
+
+impl MetricThreshold {
+    /// Whether the reading has the metric and its value passes the comparison
+    pub fn matches(&self, telemetry: &TelemetryData) -> bool {
+        telemetry
+            .metrics
+            .get(&self.metric)
+            .map(|&value| self.op.compare(value, self.value))
+            .unwrap_or(false)
+    }
+}
+
+/// Live telemetry subscription held by a WebSocket client
+#[derive(Debug, Clone, Deserialize)]
+pub struct Subscription {
+    /// Client-chosen name, used to unsubscribe and to tag matching readings
+    pub id: String,
+
+    /// Device IDs or glob patterns (`*`, `?`); every device when empty
+    #[serde(default)]
+    pub devices: Vec<String>,
+
+    /// Conditions a reading must all satisfy; every reading when empty
+    #[serde(default)]
+    pub thresholds: Vec<MetricThreshold>,
+}
+
+impl Subscription {
+    /// Whether the reading should be delivered to this subscription
+    pub fn matches(&self, telemetry: &TelemetryData) -> bool {
+        (self.devices.is_empty()
+            || self
+                .devices
+                .iter()
+                .any(|pattern| glob_match(pattern, &telemetry.device_id)))
+            && self.thresholds.iter().all(|t| t.matches(telemetry))
+    }
+}
+
+/// Match `text` against a glob where `*` is any run of characters and `?` is one
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it currently absorbs up to
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                // Let the last `*` absorb one more character and retry
+                Some((star_p, star_t)) => {
+                    star = Some((star_p, star_t + 1));
+                    p = star_p + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -1,4 +1,8 @@
-use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -12,42 +16,71 @@ pub struct TelemetryData {
     /// Identifier of the device that sent the telemetry
     pub device_id: String,
 
-    /// Temperature reading from the device (in Celsius)
-    pub temperature: f32,
-
-    /// Optional humidity reading (in percentage)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub humidity: Option<f32>,
-
-    /// Optional pressure reading (in hPa)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub pressure: Option<f32>,
+    /// Numeric readings keyed by metric name, e.g. `temperature` or `co2`
+    pub metrics: BTreeMap<String, f64>,
 
-    /// Timestamp when the telemetry was recorded
+    /// Timestamp when the telemetry was recorded, by the device's clock
     #[serde(default = "Utc::now")]
     pub timestamp: DateTime<Utc>,
+
+    /// When the server accepted the telemetry
+    #[serde(default = "Utc::now")]
+    pub received_at: DateTime<Utc>,
 }
 
 /// Represents a request to create a new telemetry record
+///
+/// Readings go in `metrics`; the legacy top-level `temperature`, `humidity`
+/// and `pressure` fields are still accepted and stored as metrics of the
+/// same name.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CreateTelemetryRequest {
     pub device_id: String,
-    pub temperature: f32,
-    pub humidity: Option<f32>,
-    pub pressure: Option<f32>,
+
+    /// Client-chosen ID for this submission; retries with the same ID from
+    /// the same device return the original record ID instead of a duplicate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+
+    #[serde(default)]
+    pub metrics: BTreeMap<String, f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub humidity: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pressure: Option<f64>,
     #[serde(default = "Utc::now")]
     pub timestamp: DateTime<Utc>,
 }
 
+impl CreateTelemetryRequest {
+    /// Readings supplied through the legacy top-level fields, by metric name
+    pub fn legacy_metrics(&self) -> impl Iterator<Item = (&'static str, f64)> {
+        [
+            ("temperature", self.temperature),
+            ("humidity", self.humidity),
+            ("pressure", self.pressure),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|v| (name, v)))
+    }
+}
+
 impl From<CreateTelemetryRequest> for TelemetryData {
     fn from(req: CreateTelemetryRequest) -> Self {
+        let legacy: Vec<_> = req.legacy_metrics().collect();
+        let mut metrics = req.metrics;
+        for (name, value) in legacy {
+            metrics.insert(name.to_string(), value);
+        }
+
         Self {
             id: Uuid::new_v4(),
             device_id: req.device_id,
-            temperature: req.temperature,
-            humidity: req.humidity,
-            pressure: req.pressure,
+            metrics,
             timestamp: req.timestamp,
+            received_at: Utc::now(),
         }
     }
 }
@@ -61,9 +94,146 @@ pub struct TelemetryQuery {
     /// Optional end time filter (inclusive)
     pub end_time: Option<DateTime<Utc>>,
 
+    /// Optional filter on the earliest server receive time (inclusive)
+    pub received_start_time: Option<DateTime<Utc>>,
+
+    /// Optional filter on the latest server receive time (inclusive)
+    pub received_end_time: Option<DateTime<Utc>>,
+
     /// Maximum number of records to return
     #[serde(default = "default_limit")]
     pub limit: usize,
+
+    /// Opaque cursor from a previous page's `next_cursor`
+    pub cursor: Option<String>,
+
+    /// Whether to return the oldest (`asc`) or newest (`desc`) records first
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+impl TelemetryQuery {
+    /// Time-range filters requested by the query
+    pub fn filter(&self) -> TelemetryFilter {
+        TelemetryFilter {
+            start_time: self.start_time,
+            end_time: self.end_time,
+            received_start_time: self.received_start_time,
+            received_end_time: self.received_end_time,
+        }
+    }
+}
+
+/// Time-range filters for a device history query, all inclusive
+///
+/// `start_time` and `end_time` apply to the device timestamp, the
+/// `received_*` bounds to the server receive time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TelemetryFilter {
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub received_start_time: Option<DateTime<Utc>>,
+    pub received_end_time: Option<DateTime<Utc>>,
+}
+
+impl TelemetryFilter {
+    /// Filter on the device timestamp only
+    pub fn between(start_time: Option<DateTime<Utc>>, end_time: Option<DateTime<Utc>>) -> Self {
+        Self {
+            start_time,
+            end_time,
+            ..Self::default()
+        }
+    }
+
+    /// Whether a record received at `received_at` passes the receive-time bounds
+    pub fn accepts_received_at(&self, received_at: DateTime<Utc>) -> bool {
+        self.received_start_time
+            .map(|start| received_at >= start)
+            .unwrap_or(true)
+            && self
+                .received_end_time
+                .map(|end| received_at <= end)
+                .unwrap_or(true)
+    }
+}
+
+/// Order in which a device's history is returned
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    /// Oldest records first
+    #[default]
+    Asc,
+    /// Newest records first
+    Desc,
+}
+
+/// Position in a device's history, ordered by timestamp and then ID
+///
+/// Clients only ever see the opaque encoded form. The cursor does not record
+/// a direction; it is interpreted in the sort order of the query it is used with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TelemetryCursor {
+    pub timestamp: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl TelemetryCursor {
+    /// Cursor pointing just past the given record
+    pub fn after(telemetry: &TelemetryData) -> Self {
+        Self {
+            timestamp: telemetry.timestamp,
+            id: telemetry.id,
+        }
+    }
+
+    /// Position of the given record in the order records were received
+    ///
+    /// Used as the event ID of live streams, so reconnecting clients can
+    /// resume after the last reading they saw.
+    pub fn received(telemetry: &TelemetryData) -> Self {
+        Self {
+            timestamp: telemetry.received_at,
+            id: telemetry.id,
+        }
+    }
+
+    /// Encode the cursor as an opaque URL-safe token
+    pub fn encode(&self) -> String {
+        let raw = format!(
+            "{}|{}",
+            self.timestamp.to_rfc3339_opts(SecondsFormat::Nanos, true),
+            self.id
+        );
+        URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    /// Decode a token produced by [`TelemetryCursor::encode`]
+    pub fn decode(token: &str) -> Result<Self, String> {
+        let invalid = || "Invalid cursor".to_string();
+
+        let raw = URL_SAFE_NO_PAD.decode(token).map_err(|_| invalid())?;
+        let raw = String::from_utf8(raw).map_err(|_| invalid())?;
+        let (timestamp, id) = raw.split_once('|').ok_or_else(invalid)?;
+
+        Ok(Self {
+            timestamp: DateTime::parse_from_rfc3339(timestamp)
+                .map_err(|_| invalid())?
+                .with_timezone(&Utc),
+            id: Uuid::parse_str(id).map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// One page of a device's telemetry history
+#[derive(Debug, Serialize)]
+pub struct TelemetryPage {
+    pub data: Vec<TelemetryData>,
+
+    /// Cursor for the next page, absent on the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 fn default_limit() -> usize {
@@ -0,0 +1,157 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Ingestion event a webhook can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WebhookEvent {
+    /// A reading was validated and stored
+    ReadingAccepted,
+    /// A reading failed validation or came from an unregistered device
+    ReadingRejected,
+    /// Old records of a device were deleted
+    RecordsDeleted,
+}
+
+impl WebhookEvent {
+    /// Name used in payloads and when persisting the event type
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ReadingAccepted => "reading-accepted",
+            Self::ReadingRejected => "reading-rejected",
+            Self::RecordsDeleted => "records-deleted",
+        }
+    }
+}
+
+impl FromStr for WebhookEvent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reading-accepted" => Ok(Self::ReadingAccepted),
+            "reading-rejected" => Ok(Self::ReadingRejected),
+            "records-deleted" => Ok(Self::RecordsDeleted),
+            other => Err(format!("Unknown webhook event '{}'", other)),
+        }
+    }
+}
+
+/// Subscription delivering ingestion events to an HTTP endpoint
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Webhook {
+    pub id: Uuid,
+
+    /// Endpoint the events are POSTed to
+    pub url: String,
+
+    /// Key for the HMAC-SHA256 signature of each payload; never returned by the API
+    #[serde(skip_serializing)]
+    pub secret: String,
+
+    /// Device ID or glob pattern (`*`, `?`) selecting the devices reported
+    pub device: String,
+
+    /// Events delivered to the endpoint
+    pub events: Vec<WebhookEvent>,
+
+    /// When the webhook was created
+    pub created_at: DateTime<Utc>,
+}
+
+impl Webhook {
+    /// Create a webhook from a request, with a new ID
+    pub fn new(request: WebhookRequest) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            url: request.url,
+            secret: request.secret,
+            device: request.device,
+            events: request.events,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// Represents a request to create a webhook
+#[derive(Debug, Deserialize, Serialize)]
+pub struct WebhookRequest {
+    pub url: String,
+    pub secret: String,
+    #[serde(default = "every_device")]
+    pub device: String,
+    pub events: Vec<WebhookEvent>,
+}
+
+fn every_device() -> String {
+    "*".to_string()
+}
+
+/// Progress of a webhook delivery
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    /// Not yet accepted by the endpoint; more attempts will be made
+    Pending,
+    /// Accepted by the endpoint with a 2xx response
+    Delivered,
+    /// Every attempt failed; kept in the dead-letter list
+    Dead,
+}
+
+/// One event sent, or being sent, to a webhook
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub event: WebhookEvent,
+    pub status: DeliveryStatus,
+
+    /// Number of attempts made so far
+    pub attempts: u32,
+
+    /// Status code of the last response, if one was received
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_status: Option<u16>,
+
+    /// Why the last attempt failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+
+    /// Body POSTed to the endpoint
+    pub payload: serde_json::Value,
+
+    /// When the event occurred
+    pub created_at: DateTime<Utc>,
+
+    /// When the last attempt was made
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_attempt_at: Option<DateTime<Utc>>,
+}
+
+/// Query parameters for listing webhook deliveries
+#[derive(Debug, Deserialize)]
+pub struct DeliveryQuery {
+    pub status: Option<DeliveryStatus>,
+
+    /// Maximum number of deliveries to return
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+/// Query parameters for listing dead-lettered deliveries
+#[derive(Debug, Deserialize)]
+pub struct DeadLetterQuery {
+    pub webhook_id: Option<Uuid>,
+
+    /// Maximum number of deliveries to return
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+fn default_limit() -> usize {
+    100
+}
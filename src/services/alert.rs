@@ -0,0 +1,342 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::errors::{AppError, FieldError};
+use crate::models::{
+    glob_match, Alert, AlertQuery, AlertRule, AlertRuleRequest, AlertState, TelemetryData,
+};
+use crate::storage::AlertRuleRepository;
+
+/// Resolved alerts kept for listing before the oldest are dropped
+const MAX_RESOLVED_ALERTS: usize = 10_000;
+
+/// Longest a rule's condition may be required to hold, in seconds
+const MAX_RULE_DURATION_SECS: i64 = 365 * 24 * 60 * 60;
+
+/// Service for managing alert rules and tracking the alerts they raise
+///
+/// Rules are persisted in the repository; alert state is kept in memory.
+pub struct AlertService {
+    store: Arc<dyn AlertRuleRepository>,
+
+    /// Every rule, loaded from the store on first use
+    rules: RwLock<Option<Arc<Vec<AlertRule>>>>,
+
+    alerts: Mutex<AlertBook>,
+}
+
+/// Evaluation state of every (rule, device) pair
+#[derive(Default)]
+struct AlertBook {
+    /// When a breach not yet held for the rule's duration began
+    pending: HashMap<(Uuid, String), DateTime<Utc>>,
+
+    /// Currently firing alerts
+    firing: HashMap<(Uuid, String), Alert>,
+
+    /// Most recently resolved alerts, oldest first
+    resolved: VecDeque<Alert>,
+}
+
+impl AlertService {
+    /// Create a new alert service with the provided rule store
+    pub fn new(store: impl AlertRuleRepository + 'static) -> Self {
+        Self::with_repository(Arc::new(store))
+    }
+
+    /// Create a new alert service backed by a shared rule store
+    pub fn with_repository(store: Arc<dyn AlertRuleRepository>) -> Self {
+        Self {
+            store,
+            rules: RwLock::new(None),
+            alerts: Mutex::new(AlertBook::default()),
+        }
+    }
+
+    /// Create a new alert rule
+    pub async fn create_rule(&self, request: AlertRuleRequest) -> Result<AlertRule, AppError> {
+        validate_rule(&request)?;
+        let rule = AlertRule::new(request);
+
+        let mut cached = self.rules.write().await;
+        let mut rules = self.load(&mut cached).await?.as_ref().clone();
+        self.store
+            .insert(rule.clone())
+            .await
+            .map_err(AppError::InternalError)?;
+
+        rules.push(rule.clone());
+        *cached = Some(Arc::new(rules));
+        Ok(rule)
+    }
+
+    /// List every alert rule, oldest first
+    pub async fn list_rules(&self) -> Result<Vec<AlertRule>, AppError> {
+        Ok(self.rules().await?.as_ref().clone())
+    }
+
+    /// Get an alert rule by ID
+    pub async fn get_rule(&self, id: Uuid) -> Result<AlertRule, AppError> {
+        self.rules()
+            .await?
+            .iter()
+            .find(|rule| rule.id == id)
+            .cloned()
+            .ok_or_else(|| rule_not_found(id))
+    }
+
+    /// Replace an alert rule
+    ///
+    /// Alerts raised by the previous version of the rule are resolved.
+    pub async fn update_rule(
+        &self,
+        id: Uuid,
+        request: AlertRuleRequest,
+    ) -> Result<AlertRule, AppError> {
+        validate_rule(&request)?;
+
+        let mut cached = self.rules.write().await;
+        let mut rules = self.load(&mut cached).await?.as_ref().clone();
+        let existing = rules
+            .iter_mut()
+            .find(|rule| rule.id == id)
+            .ok_or_else(|| rule_not_found(id))?;
+
+        let rule = AlertRule {
+            id,
+            created_at: existing.created_at,
+            ..AlertRule::new(request)
+        };
+        let updated = self
+            .store
+            .update(rule.clone())
+            .await
+            .map_err(AppError::InternalError)?;
+        if !updated {
+            return Err(rule_not_found(id));
+        }
+
+        *existing = rule.clone();
+        *cached = Some(Arc::new(rules));
+        self.retire(id);
+        Ok(rule)
+    }
+
+    /// Remove an alert rule, resolving the alerts it raised
+    pub async fn delete_rule(&self, id: Uuid) -> Result<(), AppError> {
+        let mut cached = self.rules.write().await;
+        let mut rules = self.load(&mut cached).await?.as_ref().clone();
+        let deleted = self
+            .store
+            .delete(id)
+            .await
+            .map_err(AppError::InternalError)?;
+        if !deleted {
+            return Err(rule_not_found(id));
+        }
+
+        rules.retain(|rule| rule.id != id);
+        *cached = Some(Arc::new(rules));
+        self.retire(id);
+        Ok(())
+    }
+
+    /// List firing and recently resolved alerts, most recently started first
+    pub fn list_alerts(&self, query: &AlertQuery) -> Vec<Alert> {
+        let book = self.book();
+        let mut alerts: Vec<Alert> = book
+            .firing
+            .values()
+            .chain(book.resolved.iter())
+            .filter(|alert| query.state.map(|s| alert.state == s).unwrap_or(true))
+            .filter(|alert| {
+                query
+                    .device_id
+                    .as_deref()
+                    .map(|d| alert.device_id == d)
+                    .unwrap_or(true)
+            })
+            .filter(|alert| query.rule_id.map(|r| alert.rule_id == r).unwrap_or(true))
+            .cloned()
+            .collect();
+        drop(book);
+
+        alerts.sort_by(|a, b| b.started_at.cmp(&a.started_at).then(b.id.cmp(&a.id)));
+        alerts.truncate(query.limit);
+        alerts
+    }
+
+    /// Evaluate every rule selecting the reading's device against it
+    ///
+    /// Failing to load the rules is logged rather than returned, so alerting
+    /// problems never cause a reading to be rejected.
+    pub async fn evaluate(&self, telemetry: &TelemetryData) {
+        let rules = match self.rules().await {
+            Ok(rules) => rules,
+            Err(e) => {
+                tracing::warn!("Skipping alert evaluation: {}", e);
+                return;
+            }
+        };
+
+        let mut book = self.book();
+        for rule in rules.iter() {
+            if !glob_match(&rule.device, &telemetry.device_id) {
+                continue;
+            }
+            if let Some(&value) = telemetry.metrics.get(&rule.metric) {
+                book.observe(rule, telemetry, value);
+            }
+        }
+    }
+
+    /// Every rule, loading them from the store if needed
+    async fn rules(&self) -> Result<Arc<Vec<AlertRule>>, AppError> {
+        if let Some(rules) = self.rules.read().await.as_ref() {
+            return Ok(rules.clone());
+        }
+        let mut cached = self.rules.write().await;
+        self.load(&mut cached).await
+    }
+
+    /// Fill `cached` from the store unless it is already loaded
+    async fn load(
+        &self,
+        cached: &mut Option<Arc<Vec<AlertRule>>>,
+    ) -> Result<Arc<Vec<AlertRule>>, AppError> {
+        if let Some(rules) = cached {
+            return Ok(rules.clone());
+        }
+        let rules = Arc::new(self.store.list().await.map_err(AppError::InternalError)?);
+        *cached = Some(rules.clone());
+        Ok(rules)
+    }
+
+    /// Lock the alert state
+    ///
+    /// The state stays consistent between updates, so a panic while it was
+    /// held does not stop readings being evaluated afterwards.
+    fn book(&self) -> MutexGuard<'_, AlertBook> {
+        self.alerts.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Forget the evaluation state of a rule, resolving its firing alerts now
+    fn retire(&self, rule_id: Uuid) {
+        let mut book = self.book();
+        book.pending.retain(|(id, _), _| *id != rule_id);
+
+        let retired: Vec<(Uuid, String)> = book
+            .firing
+            .keys()
+            .filter(|(id, _)| *id == rule_id)
+            .cloned()
+            .collect();
+        let now = Utc::now();
+        for key in retired {
+            if let Some(alert) = book.firing.remove(&key) {
+                book.resolve(alert, now);
+            }
+        }
+    }
+}
+
+impl AlertBook {
+    /// Advance the state of `rule` for the reading's device given its value
+    fn observe(&mut self, rule: &AlertRule, telemetry: &TelemetryData, value: f64) {
+        let key = (rule.id, telemetry.device_id.clone());
+        let at = telemetry.timestamp;
+
+        if let Entry::Occupied(mut firing) = self.firing.entry(key.clone()) {
+            firing.get_mut().last_value = value;
+            if rule.recovered(value) {
+                let alert = firing.remove();
+                self.resolve(alert, at);
+            }
+            return;
+        }
+
+        if !rule.breached(value) {
+            self.pending.remove(&key);
+            return;
+        }
+
+        let since = *self.pending.entry(key.clone()).or_insert(at);
+        if at - since >= rule.duration() {
+            self.pending.remove(&key);
+            self.firing.insert(
+                key,
+                Alert {
+                    id: Uuid::new_v4(),
+                    rule_id: rule.id,
+                    rule_name: rule.name.clone(),
+                    device_id: telemetry.device_id.clone(),
+                    metric: rule.metric.clone(),
+                    state: AlertState::Firing,
+                    threshold: rule.threshold,
+                    value,
+                    last_value: value,
+                    started_at: at,
+                    resolved_at: None,
+                },
+            );
+        }
+    }
+
+    /// Move a firing alert to the resolved history
+    fn resolve(&mut self, mut alert: Alert, at: DateTime<Utc>) {
+        alert.state = AlertState::Resolved;
+        alert.resolved_at = Some(at);
+
+        self.resolved.push_back(alert);
+        if self.resolved.len() > MAX_RESOLVED_ALERTS {
+            self.resolved.pop_front();
+        }
+    }
+}
+
+/// Check an alert rule request, reporting every invalid field
+fn validate_rule(request: &AlertRuleRequest) -> Result<(), AppError> {
+    let mut errors = Vec::new();
+
+    if request.name.trim().is_empty() {
+        errors.push(FieldError::new("name", "must not be empty"));
+    }
+    if request.device.trim().is_empty() {
+        errors.push(FieldError::new("device", "must not be empty"));
+    }
+    if request.metric.trim().is_empty() {
+        errors.push(FieldError::new("metric", "must not be empty"));
+    }
+    if !request.threshold.is_finite() {
+        errors.push(FieldError::new("threshold", "must be a finite number"));
+    }
+    if request.duration_secs < 0 {
+        errors.push(FieldError::new("duration_secs", "must not be negative"));
+    } else if request.duration_secs > MAX_RULE_DURATION_SECS {
+        errors.push(FieldError::new(
+            "duration_secs",
+            format!("must be at most {}", MAX_RULE_DURATION_SECS),
+        ));
+    }
+    if !request.hysteresis.is_finite() || request.hysteresis < 0.0 {
+        errors.push(FieldError::new(
+            "hysteresis",
+            "must be a finite, non-negative number",
+        ));
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::InvalidFields(errors))
+    }
+}
+
+fn rule_not_found(id: Uuid) -> AppError {
+    AppError::NotFound(format!("Alert rule {} not found", id))
+}
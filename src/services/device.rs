@@ -0,0 +1,198 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+
+use super::heartbeat::HeartbeatService;
+use super::validation::validate_device_id;
+use crate::config::ValidationConfig;
+use crate::errors::AppError;
+use crate::models::{
+    encode_device_cursor, CreateDeviceRequest, Device, RegisteredDevicePage, UpdateDeviceRequest,
+};
+use crate::storage::DeviceRepository;
+
+/// Longest interval a device may be expected to report at, in seconds
+const MAX_EXPECTED_INTERVAL_SECS: i64 = 365 * 24 * 60 * 60;
+
+/// Service for managing the device registry
+pub struct DeviceService {
+    registry: Arc<dyn DeviceRepository>,
+
+    /// When set, told about changes to devices' expected intervals
+    heartbeats: Option<Arc<HeartbeatService>>,
+
+    /// Rules registered device IDs must satisfy
+    validation: ValidationConfig,
+}
+
+impl DeviceService {
+    /// Create a new device service with the provided registry
+    pub fn new(registry: impl DeviceRepository + 'static) -> Self {
+        Self::with_repository(Arc::new(registry))
+    }
+
+    /// Create a new device service backed by a shared registry
+    pub fn with_repository(registry: Arc<dyn DeviceRepository>) -> Self {
+        Self {
+            registry,
+            heartbeats: None,
+            validation: ValidationConfig::default(),
+        }
+    }
+
+    /// Validate device IDs against `validation` instead of the defaults
+    pub fn with_validation(mut self, validation: ValidationConfig) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    /// Keep the expected intervals used by `heartbeats` up to date
+    pub fn with_heartbeats(mut self, heartbeats: Arc<HeartbeatService>) -> Self {
+        self.heartbeats = Some(heartbeats);
+        self
+    }
+
+    /// Register a new device
+    pub async fn register_device(&self, request: CreateDeviceRequest) -> Result<Device, AppError> {
+        validate_device_id(&request.device_id, &self.validation)?;
+        if request.name.trim().is_empty() {
+            return Err(AppError::BadRequest("name must not be empty".to_string()));
+        }
+        validate_interval(request.expected_interval_secs)?;
+
+        let device = Device::from(request);
+        let inserted = self
+            .registry
+            .insert(device.clone())
+            .await
+            .map_err(AppError::InternalError)?;
+
+        if !inserted {
+            return Err(AppError::Conflict(format!(
+                "Device {} is already registered",
+                device.device_id
+            )));
+        }
+
+        self.interval_changed(&device);
+        Ok(device)
+    }
+
+    /// Get a registered device by ID
+    pub async fn get_device(&self, device_id: &str) -> Result<Device, AppError> {
+        self.registry
+            .get(device_id)
+            .await
+            .map_err(AppError::InternalError)?
+            .ok_or_else(|| not_registered(device_id))
+    }
+
+    /// List registered devices, ordered by device ID
+    ///
+    /// `after` is the device ID the previous page ended at.
+    pub async fn list_devices(
+        &self,
+        prefix: Option<&str>,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<RegisteredDevicePage, AppError> {
+        // Fetch one extra device to learn whether another page follows
+        let mut devices = self
+            .registry
+            .list(prefix, after, limit.saturating_add(1))
+            .await
+            .map_err(AppError::InternalError)?;
+
+        let next_cursor = if devices.len() > limit {
+            devices.truncate(limit);
+            devices.last().map(|d| encode_device_cursor(&d.device_id))
+        } else {
+            None
+        };
+
+        Ok(RegisteredDevicePage {
+            data: devices,
+            next_cursor,
+        })
+    }
+
+    /// Replace the registration details of a device
+    pub async fn update_device(
+        &self,
+        device_id: &str,
+        request: UpdateDeviceRequest,
+    ) -> Result<Device, AppError> {
+        if request.name.trim().is_empty() {
+            return Err(AppError::BadRequest("name must not be empty".to_string()));
+        }
+        validate_interval(request.expected_interval_secs)?;
+
+        let existing = self.get_device(device_id).await?;
+        let device = Device {
+            device_id: existing.device_id,
+            name: request.name,
+            device_type: request.device_type,
+            location: request.location,
+            tags: request.tags,
+            status: request.status,
+            expected_interval_secs: request.expected_interval_secs,
+            created_at: existing.created_at,
+            updated_at: Utc::now(),
+        };
+
+        let updated = self
+            .registry
+            .update(device.clone())
+            .await
+            .map_err(AppError::InternalError)?;
+
+        // The device may have been removed since it was read
+        if !updated {
+            return Err(not_registered(device_id));
+        }
+
+        self.interval_changed(&device);
+        Ok(device)
+    }
+
+    /// Remove a device from the registry
+    pub async fn delete_device(&self, device_id: &str) -> Result<(), AppError> {
+        let deleted = self
+            .registry
+            .delete(device_id)
+            .await
+            .map_err(AppError::InternalError)?;
+
+        if !deleted {
+            return Err(not_registered(device_id));
+        }
+
+        if let Some(heartbeats) = &self.heartbeats {
+            heartbeats.set_interval(device_id, None);
+        }
+        Ok(())
+    }
+
+    fn interval_changed(&self, device: &Device) {
+        if let Some(heartbeats) = &self.heartbeats {
+            heartbeats.set_interval(&device.device_id, device.expected_interval_secs);
+        }
+    }
+}
+
+fn validate_interval(interval_secs: Option<i64>) -> Result<(), AppError> {
+    match interval_secs {
+        Some(secs) if secs <= 0 => Err(AppError::BadRequest(
+            "expected_interval_secs must be positive".to_string(),
+        )),
+        Some(secs) if secs > MAX_EXPECTED_INTERVAL_SECS => Err(AppError::BadRequest(format!(
+            "expected_interval_secs must be at most {}",
+            MAX_EXPECTED_INTERVAL_SECS
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn not_registered(device_id: &str) -> AppError {
+    AppError::NotFound(format!("Device {} is not registered", device_id))
+}
@@ -0,0 +1,352 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::errors::AppError;
+use crate::models::{
+    Connectivity, Device, DeviceConnectivity, FleetStatus, FleetStatusQuery, StatusTransition,
+    TelemetryData,
+};
+use crate::storage::{DeviceRepository, TelemetryRepository};
+
+/// Expected reporting interval of devices without one of their own
+const DEFAULT_INTERVAL_SECS: i64 = 5 * 60;
+
+/// Expected intervals a device may stay silent before it is offline
+const DEFAULT_OFFLINE_FACTOR: i32 = 3;
+
+/// Transitions kept per device before the oldest are dropped
+const MAX_TRANSITIONS_PER_DEVICE: usize = 100;
+
+/// Service tracking when each device last reported and whether it is overdue
+///
+/// A device is online while it reports within its expected interval, stale
+/// once it is overdue and offline after several intervals of silence.
+/// Statuses change when a reading arrives or when [`HeartbeatService::check`]
+/// runs, normally from the background task started by [`HeartbeatService::run`].
+pub struct HeartbeatService {
+    /// Source of per-device expected intervals
+    registry: Option<Arc<dyn DeviceRepository>>,
+
+    default_interval: Duration,
+    offline_factor: i32,
+    devices: Mutex<HashMap<String, Heartbeat>>,
+}
+
+/// Tracked state of one device
+struct Heartbeat {
+    last_seen: Option<DateTime<Utc>>,
+    interval: Duration,
+    status: Connectivity,
+    since: DateTime<Utc>,
+
+    /// Most recent status changes, oldest first
+    transitions: VecDeque<StatusTransition>,
+}
+
+impl Heartbeat {
+    /// Move to `status` at `at`, recording the transition
+    fn transition(&mut self, device_id: &str, status: Connectivity, at: DateTime<Utc>) {
+        if self.status == status {
+            return;
+        }
+        tracing::info!("Device {} is now {:?}", device_id, status);
+
+        self.transitions.push_back(StatusTransition {
+            device_id: device_id.to_string(),
+            from: self.status,
+            to: status,
+            at,
+        });
+        if self.transitions.len() > MAX_TRANSITIONS_PER_DEVICE {
+            self.transitions.pop_front();
+        }
+        self.status = status;
+        self.since = at;
+    }
+
+    fn snapshot(&self, device_id: &str) -> DeviceConnectivity {
+        DeviceConnectivity {
+            device_id: device_id.to_string(),
+            status: self.status,
+            last_seen: self.last_seen,
+            expected_interval_secs: self.interval.num_seconds(),
+            since: self.since,
+        }
+    }
+}
+
+impl Default for HeartbeatService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HeartbeatService {
+    /// Create a heartbeat service using the default expected interval
+    pub fn new() -> Self {
+        Self {
+            registry: None,
+            default_interval: Duration::seconds(DEFAULT_INTERVAL_SECS),
+            offline_factor: DEFAULT_OFFLINE_FACTOR,
+            devices: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Expect devices without an interval of their own to report every `interval`
+    pub fn with_default_interval(mut self, interval: Duration) -> Self {
+        self.default_interval = interval;
+        self
+    }
+
+    /// Consider a device offline after `factor` expected intervals of silence
+    pub fn with_offline_factor(mut self, factor: i32) -> Self {
+        self.offline_factor = factor.max(1);
+        self
+    }
+
+    /// Look up per-device expected intervals in `registry`
+    pub fn with_registry(mut self, registry: Arc<dyn DeviceRepository>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Start tracking every device with stored telemetry from when it was last seen
+    pub async fn seed(&self, store: &dyn TelemetryRepository) -> Result<usize, AppError> {
+        const PAGE_SIZE: usize = 1000;
+
+        let mut tracked = 0;
+        let mut after: Option<String> = None;
+        loop {
+            let page = store
+                .list_devices(None, after.as_deref(), PAGE_SIZE)
+                .await
+                .map_err(AppError::InternalError)?;
+            let done = page.len() < PAGE_SIZE;
+
+            for summary in &page {
+                let interval = self.registered_interval(&summary.device_id).await;
+                let now = Utc::now();
+                let status = self.evaluate(Some(summary.last_seen), interval, now);
+                self.devices
+                    .lock()
+                    .unwrap()
+                    .entry(summary.device_id.clone())
+                    .or_insert_with(|| Heartbeat {
+                        last_seen: Some(summary.last_seen),
+                        interval,
+                        status,
+                        since: now,
+                        transitions: VecDeque::new(),
+                    });
+            }
+            tracked += page.len();
+            after = page.last().map(|d| d.device_id.clone());
+
+            if done {
+                return Ok(tracked);
+            }
+        }
+    }
+
+    /// Note an accepted reading, bringing its device online
+    pub async fn record(&self, telemetry: &TelemetryData) {
+        let device_id = &telemetry.device_id;
+        let at = telemetry.received_at;
+
+        if let Some(heartbeat) = self.devices().get_mut(device_id) {
+            heartbeat.last_seen = heartbeat.last_seen.max(Some(at));
+            heartbeat.transition(device_id, Connectivity::Online, at);
+            return;
+        }
+
+        // First reading from this device; the registry may know its interval
+        let interval = self.registered_interval(device_id).await;
+        let mut devices = self.devices();
+        let heartbeat = devices
+            .entry(device_id.clone())
+            .or_insert_with(|| Heartbeat {
+                last_seen: Some(at),
+                interval,
+                status: Connectivity::Online,
+                since: at,
+                transitions: VecDeque::new(),
+            });
+        heartbeat.last_seen = heartbeat.last_seen.max(Some(at));
+        heartbeat.transition(device_id, Connectivity::Online, at);
+    }
+
+    /// Change a device's expected interval, or restore the default with `None`
+    pub fn set_interval(&self, device_id: &str, interval_secs: Option<i64>) {
+        let interval = self.interval(interval_secs);
+        if let Some(heartbeat) = self.devices().get_mut(device_id) {
+            heartbeat.interval = interval;
+        }
+    }
+
+    /// Re-evaluate every tracked device, returning the transitions detected
+    pub fn check(&self) -> Vec<StatusTransition> {
+        let now = Utc::now();
+        let mut detected = Vec::new();
+
+        let mut devices = self.devices();
+        for (device_id, heartbeat) in devices.iter_mut() {
+            let status = self.evaluate(heartbeat.last_seen, heartbeat.interval, now);
+            if status != heartbeat.status {
+                heartbeat.transition(device_id, status, now);
+                detected.extend(heartbeat.transitions.back().cloned());
+            }
+        }
+        detected
+    }
+
+    /// Check every device each `period`, forever
+    pub async fn run(self: Arc<Self>, period: std::time::Duration) {
+        let mut ticks = tokio::time::interval(period);
+        ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticks.tick().await;
+            self.check();
+        }
+    }
+
+    /// Connectivity of a device
+    ///
+    /// A registered device that has not reported yet is offline.
+    pub async fn device_status(&self, device_id: &str) -> Result<DeviceConnectivity, AppError> {
+        if let Some(heartbeat) = self.devices().get(device_id) {
+            return Ok(heartbeat.snapshot(device_id));
+        }
+
+        let device = self.registered(device_id).await?;
+        Ok(DeviceConnectivity {
+            device_id: device.device_id,
+            status: Connectivity::Offline,
+            last_seen: None,
+            expected_interval_secs: device
+                .expected_interval_secs
+                .unwrap_or(self.default_interval.num_seconds()),
+            since: device.created_at,
+        })
+    }
+
+    /// Status changes of a device, most recent first
+    pub async fn transitions(
+        &self,
+        device_id: &str,
+        limit: usize,
+    ) -> Result<Vec<StatusTransition>, AppError> {
+        if let Some(heartbeat) = self.devices().get(device_id) {
+            return Ok(heartbeat
+                .transitions
+                .iter()
+                .rev()
+                .take(limit)
+                .cloned()
+                .collect());
+        }
+
+        self.registered(device_id).await?;
+        Ok(Vec::new())
+    }
+
+    /// Status counts across every tracked device, with the devices matching `query`
+    pub fn fleet_status(&self, query: &FleetStatusQuery) -> FleetStatus {
+        let devices = self.devices();
+        let count = |status| devices.values().filter(|h| h.status == status).count();
+        let (online, stale, offline) = (
+            count(Connectivity::Online),
+            count(Connectivity::Stale),
+            count(Connectivity::Offline),
+        );
+
+        let mut listed: Vec<DeviceConnectivity> = devices
+            .iter()
+            .filter(|(_, h)| query.status.map(|s| h.status == s).unwrap_or(true))
+            .map(|(device_id, h)| h.snapshot(device_id))
+            .collect();
+        drop(devices);
+
+        listed.sort_by(|a, b| a.device_id.cmp(&b.device_id));
+        listed.truncate(query.limit);
+        FleetStatus {
+            total: online + stale + offline,
+            online,
+            stale,
+            offline,
+            devices: listed,
+        }
+    }
+
+    /// Status of a device last seen at `last_seen`, as of `now`
+    fn evaluate(
+        &self,
+        last_seen: Option<DateTime<Utc>>,
+        interval: Duration,
+        now: DateTime<Utc>,
+    ) -> Connectivity {
+        let Some(last_seen) = last_seen else {
+            return Connectivity::Offline;
+        };
+
+        let silent = now - last_seen;
+        if silent <= interval {
+            return Connectivity::Online;
+        }
+        // Intervals too long to multiply are never followed by going offline
+        match interval.checked_mul(self.offline_factor) {
+            Some(offline_after) if silent > offline_after => Connectivity::Offline,
+            _ => Connectivity::Stale,
+        }
+    }
+
+    /// Expected interval for `interval_secs`, or the default with `None`
+    ///
+    /// Intervals too long to represent fall back to the default too.
+    fn interval(&self, interval_secs: Option<i64>) -> Duration {
+        interval_secs
+            .and_then(Duration::try_seconds)
+            .unwrap_or(self.default_interval)
+    }
+
+    /// Lock the tracked devices
+    ///
+    /// Their state stays consistent between updates, so a panic while it was
+    /// held does not stop readings being recorded afterwards.
+    fn devices(&self) -> MutexGuard<'_, HashMap<String, Heartbeat>> {
+        self.devices.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Expected interval of a device, from the registry when it has one
+    ///
+    /// Registry failures are logged and the default interval used, so they
+    /// never cause a reading to be rejected.
+    async fn registered_interval(&self, device_id: &str) -> Duration {
+        let Some(registry) = &self.registry else {
+            return self.default_interval;
+        };
+
+        match registry.get(device_id).await {
+            Ok(device) => self.interval(device.and_then(|d| d.expected_interval_secs)),
+            Err(e) => {
+                tracing::warn!("Using default interval for device {}: {}", device_id, e);
+                self.default_interval
+            }
+        }
+    }
+
+    /// Registry entry of a device that has not reported yet
+    async fn registered(&self, device_id: &str) -> Result<Device, AppError> {
+        let device = match &self.registry {
+            Some(registry) => registry
+                .get(device_id)
+                .await
+                .map_err(AppError::InternalError)?,
+            None => None,
+        };
+        device.ok_or_else(|| {
+            AppError::NotFound(format!("No readings seen from device {}", device_id))
+        })
+    }
+}
@@ -0,0 +1,88 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use chrono::{DateTime, Duration, Utc};
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use uuid::Uuid;
+
+/// Number of claims between sweeps of expired entries
+const PRUNE_INTERVAL: usize = 1024;
+
+/// A message ID seen recently, and the record it created
+struct Claim {
+    id: Uuid,
+    claimed_at: DateTime<Utc>,
+}
+
+/// Recently seen client message IDs, scoped per device
+///
+/// Used to answer retried submissions with the ID of the record the first
+/// attempt created. Entries are only kept in memory for `window`.
+pub struct IdempotencyCache {
+    window: Duration,
+    claims: DashMap<(String, String), Claim>,
+    claims_since_prune: AtomicUsize,
+}
+
+impl IdempotencyCache {
+    /// Create a cache that remembers message IDs for `window`
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            claims: DashMap::new(),
+            claims_since_prune: AtomicUsize::new(0),
+        }
+    }
+
+    /// Claim `message_id` from `device_id` for the new record `id`
+    ///
+    /// Returns the ID of the record created by an earlier submission of the
+    /// same message within the window, in which case nothing is claimed.
+    pub fn claim(
+        &self,
+        device_id: &str,
+        message_id: &str,
+        id: Uuid,
+        now: DateTime<Utc>,
+    ) -> Option<Uuid> {
+        if self.window <= Duration::zero() {
+            return None;
+        }
+
+        if self.claims_since_prune.fetch_add(1, Ordering::Relaxed) + 1 >= PRUNE_INTERVAL {
+            self.claims_since_prune.store(0, Ordering::Relaxed);
+            self.claims.retain(|_, claim| !self.expired(claim, now));
+        }
+
+        let claim = Claim {
+            id,
+            claimed_at: now,
+        };
+        match self
+            .claims
+            .entry((device_id.to_string(), message_id.to_string()))
+        {
+            Entry::Occupied(entry) if !self.expired(entry.get(), now) => Some(entry.get().id),
+            Entry::Occupied(mut entry) => {
+                entry.insert(claim);
+                None
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(claim);
+                None
+            }
+        }
+    }
+
+    /// Drop the claim for `id` after the record failed to be stored
+    pub fn release(&self, device_id: &str, message_id: &str, id: Uuid) {
+        self.claims.remove_if(
+            &(device_id.to_string(), message_id.to_string()),
+            |_, claim| claim.id == id,
+        );
+    }
+
+    fn expired(&self, claim: &Claim, now: DateTime<Utc>) -> bool {
+        now - claim.claimed_at >= self.window
+    }
+}
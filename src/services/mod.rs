@@ -1,6 +1,18 @@
+mod alert;
+mod device;
+mod heartbeat;
+mod idempotency;
+mod retention;
 mod telemetry;
+mod validation;
+mod webhook;
 
-pub use telemetry::TelemetryService;
+pub use alert::AlertService;
+pub use device::DeviceService;
+pub use heartbeat::HeartbeatService;
+pub use retention::RetentionService;
+pub use telemetry::{Replay, TelemetryService};
+pub use webhook::{WebhookService, DELIVERY_HEADER, EVENT_HEADER, SIGNATURE_HEADER};
 
 // Uncomment when used
 // pub use telemetry::TelemetryService;
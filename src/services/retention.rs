@@ -0,0 +1,146 @@
+use std::sync::{Arc, Mutex};
+
+use chrono::{Duration, Utc};
+use tokio::time::{interval_at, Instant};
+
+use super::telemetry::TelemetryService;
+use crate::config::RetentionConfig;
+use crate::errors::AppError;
+use crate::models::{RetentionRun, RetentionStatus, TelemetryTier};
+
+/// Service deleting telemetry that has outlived its retention period
+///
+/// Deletions go through the telemetry service, so caches stay consistent and
+/// webhooks hear about them as they would for a manual delete.
+pub struct RetentionService {
+    telemetry: Arc<TelemetryService>,
+    policy: RetentionConfig,
+
+    /// Held for the duration of a sweep so sweeps never overlap
+    sweeping: tokio::sync::Mutex<()>,
+
+    stats: Mutex<SweepStats>,
+}
+
+/// Totals since startup
+#[derive(Default)]
+struct SweepStats {
+    runs: u64,
+    total_deleted: u64,
+    last_run: Option<RetentionRun>,
+}
+
+impl RetentionService {
+    /// Create a retention service enforcing `policy` on the telemetry service's store
+    pub fn new(telemetry: Arc<TelemetryService>, policy: RetentionConfig) -> Self {
+        Self {
+            telemetry,
+            policy,
+            sweeping: tokio::sync::Mutex::new(()),
+            stats: Mutex::new(SweepStats::default()),
+        }
+    }
+
+    /// Delete every reading and rollup older than its retention period
+    ///
+    /// A device or rollup tier whose data cannot be deleted is counted as a
+    /// failure and the sweep moves on to the next one.
+    pub async fn sweep(&self) -> Result<RetentionRun, AppError> {
+        let Ok(_sweeping) = self.sweeping.try_lock() else {
+            return Err(AppError::Conflict(
+                "A retention sweep is already running".to_string(),
+            ));
+        };
+
+        let started_at = Utc::now();
+        let mut run = RetentionRun {
+            started_at,
+            finished_at: started_at,
+            devices_checked: 0,
+            devices_pruned: 0,
+            records_deleted: 0,
+            rollups_deleted: 0,
+            failures: 0,
+            last_error: None,
+        };
+
+        if self.policy.is_enabled() {
+            for device_id in self.telemetry.all_device_ids().await? {
+                run.devices_checked += 1;
+                let Some(days) = self.policy.raw_days(&device_id) else {
+                    continue;
+                };
+
+                let cutoff = started_at - Duration::days(days);
+                match self.telemetry.delete_old_records(&device_id, cutoff).await {
+                    Ok(0) => {}
+                    Ok(deleted) => {
+                        run.devices_pruned += 1;
+                        run.records_deleted += deleted;
+                    }
+                    Err(e) => {
+                        tracing::warn!("Retention sweep failed for device {}: {}", device_id, e);
+                        run.failures += 1;
+                        run.last_error = Some(e.to_string());
+                    }
+                }
+            }
+
+            for tier in TelemetryTier::ROLLUPS {
+                let Some(days) = self.policy.rollup_days(tier) else {
+                    continue;
+                };
+
+                let cutoff = started_at - Duration::days(days);
+                match self.telemetry.delete_old_rollups(tier, cutoff).await {
+                    Ok(deleted) => run.rollups_deleted += deleted,
+                    Err(e) => {
+                        tracing::warn!("Retention sweep failed for the {} tier: {}", tier, e);
+                        run.failures += 1;
+                        run.last_error = Some(e.to_string());
+                    }
+                }
+            }
+        }
+        run.finished_at = Utc::now();
+
+        let mut stats = self.stats.lock().unwrap();
+        stats.runs += 1;
+        stats.total_deleted += run.records_deleted as u64;
+        stats.last_run = Some(run.clone());
+        Ok(run)
+    }
+
+    /// The policy in force and the outcome of past sweeps
+    pub fn status(&self) -> RetentionStatus {
+        let stats = self.stats.lock().unwrap();
+        RetentionStatus {
+            policy: self.policy.clone(),
+            running: self.sweeping.try_lock().is_err(),
+            runs: stats.runs,
+            total_deleted: stats.total_deleted,
+            last_run: stats.last_run.clone(),
+        }
+    }
+
+    /// Sweep once per configured interval, forever; returns at once without a policy
+    pub async fn run(self: Arc<Self>) {
+        if !self.policy.is_enabled() {
+            return;
+        }
+
+        let period = std::time::Duration::from_secs(self.policy.sweep_interval_secs.max(1));
+        let mut ticks = interval_at(Instant::now() + period, period);
+        loop {
+            ticks.tick().await;
+            match self.sweep().await {
+                Ok(run) => tracing::info!(
+                    "Retention sweep deleted {} records from {} devices",
+                    run.records_deleted,
+                    run.devices_pruned
+                ),
+                Err(e) => tracing::warn!("Retention sweep failed: {}", e),
+            }
+        }
+    }
+}
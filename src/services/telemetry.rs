@@ -1,49 +1,504 @@
-use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use tokio::sync::broadcast;
 use uuid::Uuid;
 
+use super::alert::AlertService;
+use super::heartbeat::HeartbeatService;
+use super::idempotency::IdempotencyCache;
+use super::validation::validate_telemetry;
+use super::webhook::WebhookService;
+use crate::config::ValidationConfig;
 use crate::errors::AppError;
-use crate::models::{CreateTelemetryRequest, TelemetryData};
-use crate::storage::TelemetryStore;
+use crate::models::{
+    bucket_start, encode_device_cursor, AggregateBucket, AggregateFunction, BucketStats,
+    CreateTelemetryRequest, DevicePage, DeviceStatus, MemoryUsage, MemoryUsageQuery, SortOrder,
+    TelemetryCursor, TelemetryData, TelemetryFilter, TelemetryPage, TelemetryTier, WebhookEvent,
+};
+use crate::storage::{DeviceRepository, TelemetryRepository};
 
 /// Service for handling telemetry operations
 pub struct TelemetryService {
-    store: TelemetryStore,
+    store: Arc<dyn TelemetryRepository>,
+
+    /// Last-value cache of the newest reading per device, by timestamp
+    latest: DashMap<String, TelemetryData>,
+
+    /// When set, telemetry is only accepted from devices in this registry
+    registry: Option<Arc<dyn DeviceRepository>>,
+
+    /// Rules incoming telemetry must satisfy
+    validation: ValidationConfig,
+
+    /// Recently seen client message IDs, for deduplicating retries
+    idempotency: IdempotencyCache,
+
+    /// Publishes every accepted reading to live subscribers
+    events: broadcast::Sender<TelemetryData>,
+
+    /// When set, every accepted reading is evaluated against its alert rules
+    alerts: Option<Arc<AlertService>>,
+
+    /// When set, ingestion events are reported to subscribed webhooks
+    webhooks: Option<Arc<WebhookService>>,
+
+    /// When set, every accepted reading counts as a heartbeat of its device
+    heartbeats: Option<Arc<HeartbeatService>>,
+}
+
+/// How long message IDs are remembered unless configured otherwise
+const DEFAULT_IDEMPOTENCY_WINDOW_SECS: i64 = 24 * 60 * 60;
+
+/// Readings buffered per live subscriber before it is considered lagging
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// Most readings replayed to a live subscriber resuming from a position
+pub const MAX_REPLAYED_READINGS: usize = 1000;
+
+/// Stored readings replayed to a live subscriber resuming from a position
+#[derive(Default)]
+pub struct Replay {
+    /// Readings in the order they were received
+    pub readings: Vec<TelemetryData>,
+
+    /// Whether more readings followed than could be replayed
+    pub overflowed: bool,
 }
 
 impl TelemetryService {
     /// Create a new telemetry service with the provided store
-    pub fn new(store: TelemetryStore) -> Self {
-        Self { store }
+    pub fn new(store: impl TelemetryRepository + 'static) -> Self {
+        Self::with_repository(Arc::new(store))
+    }
+
+    /// Create a new telemetry service backed by a shared repository
+    pub fn with_repository(store: Arc<dyn TelemetryRepository>) -> Self {
+        Self {
+            store,
+            latest: DashMap::new(),
+            registry: None,
+            validation: ValidationConfig::default(),
+            idempotency: IdempotencyCache::new(Duration::seconds(DEFAULT_IDEMPOTENCY_WINDOW_SECS)),
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+            alerts: None,
+            webhooks: None,
+            heartbeats: None,
+        }
+    }
+
+    /// Remember client message IDs for `window`; a zero window disables deduplication
+    pub fn with_idempotency_window(mut self, window: Duration) -> Self {
+        self.idempotency = IdempotencyCache::new(window);
+        self
+    }
+
+    /// Validate incoming telemetry against `validation` instead of the defaults
+    pub fn with_validation(mut self, validation: ValidationConfig) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    /// Evaluate every accepted reading against the rules in `alerts`
+    pub fn with_alerts(mut self, alerts: Arc<AlertService>) -> Self {
+        self.alerts = Some(alerts);
+        self
+    }
+
+    /// Report accepted and rejected readings and deleted records to `webhooks`
+    pub fn with_webhooks(mut self, webhooks: Arc<WebhookService>) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    /// Report every accepted reading to `heartbeats` for offline detection
+    pub fn with_heartbeats(mut self, heartbeats: Arc<HeartbeatService>) -> Self {
+        self.heartbeats = Some(heartbeats);
+        self
+    }
+
+    /// Reject telemetry from devices that are not in `registry`
+    pub fn require_registered_devices(mut self, registry: Arc<dyn DeviceRepository>) -> Self {
+        self.registry = Some(registry);
+        self
     }
 
     /// Create a new telemetry record
+    ///
+    /// A retry of a request carrying the same `message_id` returns the ID of
+    /// the record created the first time without storing a duplicate.
     pub async fn create_telemetry(
         &self,
-        request: CreateTelemetryRequest,
+        mut request: CreateTelemetryRequest,
     ) -> Result<Uuid, AppError> {
+        let checked = match validate_telemetry(&request, &self.validation, Utc::now()) {
+            Ok(()) => self.ensure_registered(&request.device_id).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = checked {
+            self.rejected(&request.device_id, &e).await;
+            return Err(e);
+        }
+        let message_id = request.message_id.take();
         let telemetry = TelemetryData::from(request);
-        let id = self
+
+        if let Some(message_id) = &message_id {
+            if let Some(original) = self.idempotency.claim(
+                &telemetry.device_id,
+                message_id,
+                telemetry.id,
+                telemetry.received_at,
+            ) {
+                return Ok(original);
+            }
+        }
+
+        let id = match self.store.add(telemetry.clone()).await {
+            Ok(id) => id,
+            Err(e) => {
+                if let Some(message_id) = &message_id {
+                    self.idempotency
+                        .release(&telemetry.device_id, message_id, telemetry.id);
+                }
+                return Err(AppError::InternalError(e));
+            }
+        };
+
+        self.accepted(telemetry.clone()).await;
+        self.remember_latest(telemetry);
+        Ok(id)
+    }
+
+    /// Create several telemetry records in a single pass through the store
+    ///
+    /// Each request is validated independently; valid requests are stored
+    /// together and invalid ones are reported without affecting the rest.
+    /// Requests the store fails to keep are reported individually, while the
+    /// ones it kept are accepted.
+    /// Requests repeating a recently seen `message_id` report the original
+    /// record's ID and are not stored again. The returned results are in the
+    /// same order as `requests`.
+    pub async fn create_telemetry_batch(
+        &self,
+        requests: Vec<CreateTelemetryRequest>,
+    ) -> Result<Vec<Result<Uuid, AppError>>, AppError> {
+        let mut results = Vec::with_capacity(requests.len());
+        let mut valid = Vec::new();
+        // Message ID claimed by each valid request, released again if it is not stored
+        let mut claimed = Vec::new();
+        // Registration is looked up once per device in the batch
+        let mut registered: HashMap<String, Option<DeviceStatus>> = HashMap::new();
+
+        let now = Utc::now();
+        for mut request in requests {
+            let checked = match validate_telemetry(&request, &self.validation, now) {
+                Ok(()) => match registered.get(&request.device_id) {
+                    Some(&status) => Ok(status),
+                    None => {
+                        let status = self.registered_status(&request.device_id).await;
+                        if let Ok(status) = status {
+                            registered.insert(request.device_id.clone(), status);
+                        }
+                        status
+                    }
+                }
+                .and_then(|status| admit(&request.device_id, status)),
+                Err(e) => Err(e),
+            };
+
+            if let Err(e) = checked {
+                self.rejected(&request.device_id, &e).await;
+                results.push(Err(e));
+                continue;
+            }
+
+            let message_id = request.message_id.take();
+            let telemetry = TelemetryData::from(request);
+            if let Some(message_id) = &message_id {
+                let claim =
+                    self.idempotency
+                        .claim(&telemetry.device_id, message_id, telemetry.id, now);
+                if let Some(original) = claim {
+                    results.push(Ok(original));
+                    continue;
+                }
+            }
+            // Filled in once the store reports the outcome
+            results.push(Ok(telemetry.id));
+            claimed.push((results.len() - 1, message_id));
+            valid.push(telemetry);
+        }
+
+        if valid.is_empty() {
+            return Ok(results);
+        }
+
+        let observed = self.events.receiver_count() > 0
+            || self.alerts.is_some()
+            || self.webhooks.is_some()
+            || self.heartbeats.is_some();
+        let copies = valid.clone();
+        let outcomes = self.store.add_batch(valid).await;
+
+        // Only the newest stored reading per device can affect the cache
+        let mut newest: HashMap<String, TelemetryData> = HashMap::new();
+        for ((telemetry, outcome), (position, message_id)) in
+            copies.into_iter().zip(outcomes).zip(claimed)
+        {
+            if let Err(e) = outcome {
+                if let Some(message_id) = &message_id {
+                    self.idempotency
+                        .release(&telemetry.device_id, message_id, telemetry.id);
+                }
+                results[position] = Err(AppError::InternalError(e));
+                continue;
+            }
+
+            if observed {
+                self.accepted(telemetry.clone()).await;
+            }
+            match newest.get(&telemetry.device_id) {
+                Some(current)
+                    if TelemetryCursor::after(current) >= TelemetryCursor::after(&telemetry) => {}
+                _ => {
+                    newest.insert(telemetry.device_id.clone(), telemetry);
+                }
+            }
+        }
+        for telemetry in newest.into_values() {
+            self.remember_latest(telemetry);
+        }
+
+        Ok(results)
+    }
+
+    /// Receive every reading accepted from now on, in the order accepted
+    ///
+    /// A subscriber that falls more than the channel capacity behind is told
+    /// it lagged and should resume with [`TelemetryService::received_after`].
+    pub fn subscribe(&self) -> broadcast::Receiver<TelemetryData> {
+        self.events.subscribe()
+    }
+
+    /// Stored readings received after `position`, in the order received
+    ///
+    /// Covers one device, or the whole fleet when `device_id` is `None`.
+    /// At most [`MAX_REPLAYED_READINGS`] readings are returned; the replay
+    /// says whether more followed them.
+    pub async fn received_after(
+        &self,
+        device_id: Option<&str>,
+        position: TelemetryCursor,
+    ) -> Result<Replay, AppError> {
+        // Fetch one extra reading to learn whether the replay is cut short
+        let mut readings = self
             .store
-            .add(telemetry)
+            .received_after(device_id, position, MAX_REPLAYED_READINGS + 1)
             .await
             .map_err(AppError::InternalError)?;
 
-        Ok(id)
+        let overflowed = readings.len() > MAX_REPLAYED_READINGS;
+        readings.truncate(MAX_REPLAYED_READINGS);
+        Ok(Replay {
+            readings,
+            overflowed,
+        })
     }
 
-    /// Get telemetry data for a specific device
+    /// Get one page of telemetry data for a specific device
+    ///
+    /// Pass the previous page's `next_cursor` as `cursor` to continue, with
+    /// the same `order` as the previous page.
     pub async fn get_device_telemetry(
+        &self,
+        device_id: &str,
+        filter: &TelemetryFilter,
+        cursor: Option<TelemetryCursor>,
+        order: SortOrder,
+        limit: usize,
+    ) -> Result<TelemetryPage, AppError> {
+        // Fetch one extra record to learn whether another page follows
+        let mut telemetry = self
+            .store
+            .get_by_device(device_id, filter, cursor, order, limit.saturating_add(1))
+            .await
+            .map_err(AppError::InternalError)?;
+
+        let next_cursor = if telemetry.len() > limit {
+            telemetry.truncate(limit);
+            telemetry
+                .last()
+                .map(|last| TelemetryCursor::after(last).encode())
+        } else {
+            None
+        };
+
+        Ok(TelemetryPage {
+            data: telemetry,
+            next_cursor,
+        })
+    }
+
+    /// Aggregate telemetry for a device into fixed-width time buckets
+    ///
+    /// Buckets are computed from `tier`, or else from the coarsest tier that
+    /// answers the query exactly (see [`TelemetryTier::coarsest_for`]); the
+    /// tier used is returned with them. Rollup buckets an explicit tier's
+    /// range only partly covers count in full, and rollups still hold
+    /// readings deleted since. From raw records the storage backend computes
+    /// the buckets when it supports doing so; otherwise they are computed here.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn aggregate_device_telemetry(
         &self,
         device_id: &str,
         start_time: Option<DateTime<Utc>>,
         end_time: Option<DateTime<Utc>>,
+        bucket: Duration,
+        tier: Option<TelemetryTier>,
+        metrics: &[String],
+        functions: &[AggregateFunction],
+    ) -> Result<(TelemetryTier, Vec<AggregateBucket>), AppError> {
+        let tier = match tier {
+            Some(tier) if !tier.fits(bucket) => {
+                return Err(AppError::BadRequest(format!(
+                    "Bucket must be a whole multiple of the {} tier",
+                    tier
+                )))
+            }
+            Some(tier) => tier,
+            None => TelemetryTier::coarsest_for(bucket, start_time, end_time),
+        };
+
+        let pushed_down = match tier {
+            TelemetryTier::Raw => self
+                .store
+                .aggregate(device_id, start_time, end_time, bucket, metrics)
+                .await
+                .map_err(AppError::InternalError)?,
+            _ => {
+                // The end is inclusive, so readings taken at it come from the
+                // raw records rather than the rollup starting there
+                let before_end = end_time.map(|end| end - Duration::nanoseconds(1));
+                let mut stats = self
+                    .store
+                    .rollups(device_id, tier, start_time, before_end, metrics)
+                    .await
+                    .map_err(AppError::InternalError)?;
+                if let Some(end) = end_time.filter(|end| tier.aligned(*end)) {
+                    let at_end = self
+                        .store
+                        .get_by_device(
+                            device_id,
+                            &TelemetryFilter::between(Some(end), Some(end)),
+                            None,
+                            SortOrder::Asc,
+                            usize::MAX,
+                        )
+                        .await
+                        .map_err(AppError::InternalError)?;
+                    stats.extend(aggregate_records(&at_end, bucket, metrics));
+                }
+                Some(regroup_buckets(stats, bucket))
+            }
+        };
+
+        let stats = match pushed_down {
+            Some(stats) => stats,
+            None => {
+                let records = self
+                    .store
+                    .get_by_device(
+                        device_id,
+                        &TelemetryFilter::between(start_time, end_time),
+                        None,
+                        SortOrder::Asc,
+                        usize::MAX,
+                    )
+                    .await
+                    .map_err(AppError::InternalError)?;
+                aggregate_records(&records, bucket, metrics)
+            }
+        };
+
+        let buckets = stats
+            .into_iter()
+            .map(|b| AggregateBucket {
+                bucket_start: b.bucket_start,
+                metrics: b
+                    .metrics
+                    .into_iter()
+                    .map(|(name, stats)| (name, stats.project(functions)))
+                    .collect(),
+            })
+            .collect();
+        Ok((tier, buckets))
+    }
+
+    /// Get the newest reading for a device, by timestamp
+    ///
+    /// Served from the last-value cache; on a cache miss the newest stored
+    /// record is fetched once and cached.
+    pub async fn get_latest_telemetry(&self, device_id: &str) -> Result<TelemetryData, AppError> {
+        if let Some(latest) = self.latest.get(device_id) {
+            return Ok(latest.clone());
+        }
+
+        let newest = self
+            .store
+            .get_by_device(
+                device_id,
+                &TelemetryFilter::default(),
+                None,
+                SortOrder::Desc,
+                1,
+            )
+            .await
+            .map_err(AppError::InternalError)?
+            .pop()
+            .ok_or_else(|| {
+                AppError::NotFound(format!("No telemetry found for device {}", device_id))
+            })?;
+
+        // A reading accepted meanwhile may already have filled the cache
+        let cached = self
+            .latest
+            .entry(device_id.to_string())
+            .or_insert(newest)
+            .clone();
+
+        Ok(cached)
+    }
+
+    /// List devices with stored telemetry, ordered by device ID
+    ///
+    /// `after` is the device ID the previous page ended at.
+    pub async fn list_devices(
+        &self,
+        prefix: Option<&str>,
+        after: Option<&str>,
         limit: usize,
-    ) -> Result<Vec<TelemetryData>, AppError> {
-        let telemetry = self
+    ) -> Result<DevicePage, AppError> {
+        // Fetch one extra device to learn whether another page follows
+        let mut devices = self
             .store
-            .get_by_device(device_id, start_time, end_time, limit)
-            .await;
-        Ok(telemetry)
+            .list_devices(prefix, after, limit.saturating_add(1))
+            .await
+            .map_err(AppError::InternalError)?;
+
+        let next_cursor = if devices.len() > limit {
+            devices.truncate(limit);
+            devices.last().map(|d| encode_device_cursor(&d.device_id))
+        } else {
+            None
+        };
+
+        Ok(DevicePage {
+            data: devices,
+            next_cursor,
+        })
     }
 
     /// Get a specific telemetry record by ID
@@ -51,6 +506,7 @@ impl TelemetryService {
         self.store
             .get_by_id(id)
             .await
+            .map_err(AppError::InternalError)?
             .ok_or_else(|| AppError::NotFound(format!("Telemetry with ID {} not found", id)))
     }
 
@@ -60,7 +516,215 @@ impl TelemetryService {
         device_id: &str,
         older_than: DateTime<Utc>,
     ) -> Result<usize, AppError> {
-        let count = self.store.delete_old_records(device_id, older_than).await;
+        let count = self
+            .store
+            .delete_old_records(device_id, older_than)
+            .await
+            .map_err(AppError::InternalError)?;
+
+        // The cached reading may have been among the deleted records
+        self.latest
+            .remove_if(device_id, |_, latest| latest.timestamp < older_than);
+
+        if count > 0 {
+            if let Some(webhooks) = &self.webhooks {
+                let data = serde_json::json!({
+                    "device_id": device_id,
+                    "older_than": older_than,
+                    "deleted": count,
+                });
+                webhooks
+                    .notify(WebhookEvent::RecordsDeleted, device_id, data)
+                    .await;
+            }
+        }
+
         Ok(count)
     }
+
+    /// Memory held by the in-memory store, per device
+    pub async fn memory_usage(&self, query: &MemoryUsageQuery) -> Result<MemoryUsage, AppError> {
+        self.store
+            .memory_usage(query)
+            .await
+            .map_err(AppError::InternalError)?
+            .ok_or_else(|| {
+                AppError::NotFound(
+                    "Memory usage is only reported by the in-memory store".to_string(),
+                )
+            })
+    }
+
+    /// Delete every device's rollups in `tier` whose bucket starts before `older_than`
+    pub async fn delete_old_rollups(
+        &self,
+        tier: TelemetryTier,
+        older_than: DateTime<Utc>,
+    ) -> Result<usize, AppError> {
+        self.store
+            .delete_old_rollups(tier, older_than)
+            .await
+            .map_err(AppError::InternalError)
+    }
+
+    /// IDs of every device with stored telemetry
+    pub(crate) async fn all_device_ids(&self) -> Result<Vec<String>, AppError> {
+        const PAGE_SIZE: usize = 1000;
+
+        let mut device_ids = Vec::new();
+        loop {
+            let page = self
+                .store
+                .list_devices(None, device_ids.last().map(String::as_str), PAGE_SIZE)
+                .await
+                .map_err(AppError::InternalError)?;
+            let done = page.len() < PAGE_SIZE;
+            device_ids.extend(page.into_iter().map(|d| d.device_id));
+            if done {
+                return Ok(device_ids);
+            }
+        }
+    }
+
+    /// Evaluate alert rules for a stored reading and report it to subscribers
+    async fn accepted(&self, telemetry: TelemetryData) {
+        if let Some(heartbeats) = &self.heartbeats {
+            heartbeats.record(&telemetry).await;
+        }
+        if let Some(alerts) = &self.alerts {
+            alerts.evaluate(&telemetry).await;
+        }
+        if let Some(webhooks) = &self.webhooks {
+            if let Ok(data) = serde_json::to_value(&telemetry) {
+                webhooks
+                    .notify(WebhookEvent::ReadingAccepted, &telemetry.device_id, data)
+                    .await;
+            }
+        }
+        // Sending only fails when nobody is subscribed
+        let _ = self.events.send(telemetry);
+    }
+
+    /// Report a reading that failed validation or registration to webhooks
+    async fn rejected(&self, device_id: &str, error: &AppError) {
+        let Some(webhooks) = &self.webhooks else {
+            return;
+        };
+
+        let mut data = serde_json::json!({
+            "device_id": device_id,
+            "error": error.to_string(),
+        });
+        if let AppError::InvalidFields(details) = error {
+            data["details"] = serde_json::json!(details);
+        }
+        webhooks
+            .notify(WebhookEvent::ReadingRejected, device_id, data)
+            .await;
+    }
+
+    /// Fail if registration is required and the device may not report
+    async fn ensure_registered(&self, device_id: &str) -> Result<(), AppError> {
+        admit(device_id, self.registered_status(device_id).await?)
+    }
+
+    /// Registration status of `device_id`, or `None` if it is not registered
+    ///
+    /// Without a registry every device counts as active.
+    async fn registered_status(&self, device_id: &str) -> Result<Option<DeviceStatus>, AppError> {
+        let Some(registry) = &self.registry else {
+            return Ok(Some(DeviceStatus::Active));
+        };
+
+        let device = registry
+            .get(device_id)
+            .await
+            .map_err(AppError::InternalError)?;
+
+        Ok(device.map(|d| d.status))
+    }
+
+    /// Record `telemetry` in the last-value cache if it is the device's newest reading
+    fn remember_latest(&self, telemetry: TelemetryData) {
+        match self.latest.entry(telemetry.device_id.clone()) {
+            Entry::Occupied(mut entry) => {
+                if TelemetryCursor::after(&telemetry) > TelemetryCursor::after(entry.get()) {
+                    entry.insert(telemetry);
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(telemetry);
+            }
+        }
+    }
+}
+
+fn not_registered(device_id: &str) -> AppError {
+    AppError::NotFound(format!("Device {} is not registered", device_id))
+}
+
+/// Accept telemetry from a device with registration `status`, `None` if unregistered
+///
+/// Unregistered devices are `NotFound`; inactive and decommissioned ones a `Conflict`.
+fn admit(device_id: &str, status: Option<DeviceStatus>) -> Result<(), AppError> {
+    match status {
+        None => Err(not_registered(device_id)),
+        Some(status) if !status.accepts_telemetry() => Err(AppError::Conflict(format!(
+            "Device {} is {} and not accepting telemetry",
+            device_id,
+            status.as_str()
+        ))),
+        Some(_) => Ok(()),
+    }
+}
+
+/// Group raw records into time buckets, ordered by bucket start
+///
+/// Only the named `metrics` are aggregated, or every metric when empty.
+fn aggregate_records(
+    records: &[TelemetryData],
+    bucket: Duration,
+    metrics: &[String],
+) -> Vec<BucketStats> {
+    let mut buckets: BTreeMap<DateTime<Utc>, BucketStats> = BTreeMap::new();
+
+    for record in records {
+        let start = bucket_start(record.timestamp, bucket);
+        let selected = record
+            .metrics
+            .iter()
+            .filter(|(name, _)| metrics.is_empty() || metrics.contains(name));
+
+        // Buckets holding none of the requested metrics are left out
+        for (name, &value) in selected {
+            buckets
+                .entry(start)
+                .or_insert_with(|| BucketStats::new(start))
+                .metrics
+                .entry(name.clone())
+                .or_default()
+                .record(record.timestamp, value);
+        }
+    }
+
+    buckets.into_values().collect()
+}
+
+/// Merge time-ordered buckets into wider buckets of width `bucket`
+///
+/// `bucket` must be a whole multiple of the width of the buckets merged.
+fn regroup_buckets(stats: Vec<BucketStats>, bucket: Duration) -> Vec<BucketStats> {
+    let mut merged: Vec<BucketStats> = Vec::new();
+    for stats in stats {
+        let start = bucket_start(stats.bucket_start, bucket);
+        if merged.last().map(|b| b.bucket_start) != Some(start) {
+            merged.push(BucketStats::new(start));
+        }
+        if let Some(target) = merged.last_mut() {
+            for (name, stats) in stats.metrics {
+                target.metrics.entry(name).or_default().merge(&stats);
+            }
+        }
+    }
+    merged
 }
@@ -0,0 +1,130 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::config::ValidationConfig;
+use crate::errors::{AppError, FieldError};
+use crate::models::CreateTelemetryRequest;
+
+/// Longest accepted metric name, in bytes
+const METRIC_NAME_MAX_LENGTH: usize = 64;
+
+/// Longest accepted client message ID, in bytes
+const MESSAGE_ID_MAX_LENGTH: usize = 128;
+
+/// Check a telemetry request against `rules`, reporting every failing field
+pub fn validate_telemetry(
+    request: &CreateTelemetryRequest,
+    rules: &ValidationConfig,
+    now: DateTime<Utc>,
+) -> Result<(), AppError> {
+    let mut errors = Vec::new();
+
+    if let Some(message) = check_identifier(&request.device_id, rules.device_id_max_length) {
+        errors.push(FieldError::new("device_id", message));
+    }
+
+    if let Some(message_id) = &request.message_id {
+        if message_id.is_empty() {
+            errors.push(FieldError::new("message_id", "must not be empty"));
+        } else if message_id.len() > MESSAGE_ID_MAX_LENGTH {
+            errors.push(FieldError::new(
+                "message_id",
+                format!("must be at most {} characters", MESSAGE_ID_MAX_LENGTH),
+            ));
+        }
+    }
+
+    let max_skew = Duration::try_seconds(rules.max_clock_skew_secs).unwrap_or(Duration::MAX);
+    if request.timestamp < DateTime::UNIX_EPOCH {
+        errors.push(FieldError::new(
+            "timestamp",
+            "must not be before 1970-01-01T00:00:00Z",
+        ));
+    } else if now
+        .checked_add_signed(max_skew)
+        .is_some_and(|limit| request.timestamp > limit)
+    {
+        errors.push(FieldError::new(
+            "timestamp",
+            format!(
+                "must not be more than {} seconds in the future",
+                rules.max_clock_skew_secs
+            ),
+        ));
+    } else if request.timestamp.timestamp_nanos_opt().is_none() {
+        // Backends store timestamps as nanoseconds since the Unix epoch
+        errors.push(FieldError::new("timestamp", "is too far in the future"));
+    }
+
+    if request.metrics.is_empty() && request.legacy_metrics().next().is_none() {
+        errors.push(FieldError::new(
+            "metrics",
+            "must contain at least one metric",
+        ));
+    }
+
+    for (name, &value) in &request.metrics {
+        let field = format!("metrics.{}", name);
+        if let Some(message) = check_identifier(name, METRIC_NAME_MAX_LENGTH) {
+            errors.push(FieldError::new(field, format!("name {}", message)));
+        } else if let Some(message) = check_value(name, value, rules) {
+            errors.push(FieldError::new(field, message));
+        }
+    }
+
+    for (name, value) in request.legacy_metrics() {
+        if request.metrics.contains_key(name) {
+            errors.push(FieldError::new(name, "is also given in metrics"));
+        } else if let Some(message) = check_value(name, value, rules) {
+            errors.push(FieldError::new(name, message));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::InvalidFields(errors))
+    }
+}
+
+/// Check a device ID against the identifier rules used for telemetry
+pub fn validate_device_id(device_id: &str, rules: &ValidationConfig) -> Result<(), AppError> {
+    match check_identifier(device_id, rules.device_id_max_length) {
+        Some(message) => Err(AppError::InvalidFields(vec![FieldError::new(
+            "device_id",
+            message,
+        )])),
+        None => Ok(()),
+    }
+}
+
+/// Problem with a device ID or metric name, if any
+///
+/// Identifiers are limited to ASCII letters, digits, `-`, `_`, `.` and `:`
+/// so they are safe to use in URLs, logs and storage keys.
+fn check_identifier(identifier: &str, max_length: usize) -> Option<String> {
+    if identifier.is_empty() {
+        Some("must not be empty".to_string())
+    } else if identifier.len() > max_length {
+        Some(format!("must be at most {} characters", max_length))
+    } else if !identifier
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+    {
+        Some("may only contain letters, digits, '-', '_', '.' and ':'".to_string())
+    } else {
+        None
+    }
+}
+
+/// Problem with a metric value, if any
+fn check_value(name: &str, value: f64, rules: &ValidationConfig) -> Option<String> {
+    if !value.is_finite() {
+        return Some("must be a finite number".to_string());
+    }
+
+    rules
+        .metric_ranges
+        .get(name)
+        .filter(|range| !range.contains(value))
+        .map(|range| format!("must be between {} and {}", range.min, range.max))
+}
@@ -0,0 +1,432 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Url;
+use serde_json::json;
+use sha2::Sha256;
+use tokio::sync::{OwnedSemaphorePermit, RwLock, Semaphore};
+use uuid::Uuid;
+
+use crate::errors::{AppError, FieldError};
+use crate::models::{
+    glob_match, DeliveryQuery, DeliveryStatus, Webhook, WebhookDelivery, WebhookEvent,
+    WebhookRequest,
+};
+use crate::storage::WebhookRepository;
+
+/// Header carrying the hex HMAC-SHA256 of the body, as `sha256=<hex>`
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+/// Header naming the event a payload reports
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+
+/// Header carrying the delivery ID, identical across retries
+pub const DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+
+/// Deliveries kept in the log before the oldest are dropped
+const MAX_LOGGED_DELIVERIES: usize = 10_000;
+
+/// Dead-lettered deliveries kept before the oldest are dropped
+const MAX_DEAD_LETTERS: usize = 10_000;
+
+/// How long an endpoint may take to respond to one attempt
+const ATTEMPT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest wait between two attempts
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
+/// Deliveries to one webhook under way at once, unless configured otherwise
+const DEFAULT_MAX_IN_FLIGHT: usize = 100;
+
+/// Service for managing webhooks and delivering ingestion events to them
+///
+/// Webhooks are persisted in the repository; deliveries are sent in the
+/// background and logged in memory. Each webhook has a bounded number of
+/// deliveries under way, so a slow or unreachable endpoint cannot pile up
+/// work; deliveries beyond the bound are dead-lettered at once.
+pub struct WebhookService {
+    store: Arc<dyn WebhookRepository>,
+
+    /// Every webhook, loaded from the store on first use
+    webhooks: RwLock<Option<Arc<Vec<Webhook>>>>,
+
+    client: reqwest::Client,
+    retry: RetryPolicy,
+    deliveries: Arc<Mutex<DeliveryLog>>,
+
+    /// Permits for deliveries under way, per webhook
+    in_flight: Mutex<HashMap<Uuid, Arc<Semaphore>>>,
+    max_in_flight: usize,
+}
+
+/// How failed deliveries are retried
+#[derive(Debug, Clone, Copy)]
+struct RetryPolicy {
+    max_attempts: u32,
+
+    /// Wait before the first retry, doubled for each further retry
+    base_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Wait after the given number of failed attempts
+    fn delay(&self, attempts: u32) -> Duration {
+        let factor = 1u32 << attempts.saturating_sub(1).min(16);
+        self.base_delay.saturating_mul(factor).min(MAX_RETRY_DELAY)
+    }
+}
+
+/// Recent deliveries and those that were given up on
+#[derive(Default)]
+struct DeliveryLog {
+    /// Most recent deliveries, oldest first
+    recent: VecDeque<WebhookDelivery>,
+
+    /// Deliveries whose every attempt failed, oldest first
+    dead: VecDeque<WebhookDelivery>,
+}
+
+impl DeliveryLog {
+    fn record(&mut self, delivery: WebhookDelivery) {
+        self.recent.push_back(delivery);
+        if self.recent.len() > MAX_LOGGED_DELIVERIES {
+            self.recent.pop_front();
+        }
+    }
+
+    /// Store the outcome of an attempt
+    fn update(&mut self, delivery: &WebhookDelivery) {
+        if let Some(logged) = self.recent.iter_mut().rev().find(|d| d.id == delivery.id) {
+            *logged = delivery.clone();
+        }
+        if delivery.status == DeliveryStatus::Dead {
+            self.dead.push_back(delivery.clone());
+            if self.dead.len() > MAX_DEAD_LETTERS {
+                self.dead.pop_front();
+            }
+        }
+    }
+}
+
+impl WebhookService {
+    /// Create a new webhook service with the provided store
+    pub fn new(store: impl WebhookRepository + 'static) -> Self {
+        Self::with_repository(Arc::new(store))
+    }
+
+    /// Create a new webhook service backed by a shared store
+    pub fn with_repository(store: Arc<dyn WebhookRepository>) -> Self {
+        Self {
+            store,
+            webhooks: RwLock::new(None),
+            client: reqwest::Client::builder()
+                .timeout(ATTEMPT_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            retry: RetryPolicy {
+                max_attempts: 5,
+                base_delay: Duration::from_secs(1),
+            },
+            deliveries: Arc::new(Mutex::new(DeliveryLog::default())),
+            in_flight: Mutex::new(HashMap::new()),
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+        }
+    }
+
+    /// Attempt each delivery up to `max_attempts` times, waiting `base_delay`
+    /// before the first retry and twice as long before each further one
+    pub fn with_retry(mut self, max_attempts: u32, base_delay: Duration) -> Self {
+        self.retry = RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            base_delay,
+        };
+        self
+    }
+
+    /// Allow at most `max_in_flight` deliveries to each webhook to be under way at once
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = max_in_flight.max(1);
+        self
+    }
+
+    /// Create a new webhook
+    pub async fn create_webhook(&self, request: WebhookRequest) -> Result<Webhook, AppError> {
+        validate_webhook(&request)?;
+        let webhook = Webhook::new(request);
+
+        let mut cached = self.webhooks.write().await;
+        let mut webhooks = self.load(&mut cached).await?.as_ref().clone();
+        self.store
+            .insert(webhook.clone())
+            .await
+            .map_err(AppError::InternalError)?;
+
+        webhooks.push(webhook.clone());
+        *cached = Some(Arc::new(webhooks));
+        Ok(webhook)
+    }
+
+    /// List every webhook, oldest first
+    pub async fn list_webhooks(&self) -> Result<Vec<Webhook>, AppError> {
+        Ok(self.webhooks().await?.as_ref().clone())
+    }
+
+    /// Get a webhook by ID
+    pub async fn get_webhook(&self, id: Uuid) -> Result<Webhook, AppError> {
+        self.webhooks()
+            .await?
+            .iter()
+            .find(|webhook| webhook.id == id)
+            .cloned()
+            .ok_or_else(|| webhook_not_found(id))
+    }
+
+    /// Remove a webhook; deliveries already under way are still completed
+    pub async fn delete_webhook(&self, id: Uuid) -> Result<(), AppError> {
+        let mut cached = self.webhooks.write().await;
+        let mut webhooks = self.load(&mut cached).await?.as_ref().clone();
+        let deleted = self
+            .store
+            .delete(id)
+            .await
+            .map_err(AppError::InternalError)?;
+        if !deleted {
+            return Err(webhook_not_found(id));
+        }
+
+        webhooks.retain(|webhook| webhook.id != id);
+        *cached = Some(Arc::new(webhooks));
+        self.in_flight.lock().unwrap().remove(&id);
+        Ok(())
+    }
+
+    /// Recent deliveries to a webhook, newest first
+    pub async fn list_deliveries(
+        &self,
+        webhook_id: Uuid,
+        query: &DeliveryQuery,
+    ) -> Result<Vec<WebhookDelivery>, AppError> {
+        self.get_webhook(webhook_id).await?;
+
+        let log = self.deliveries.lock().unwrap();
+        Ok(log
+            .recent
+            .iter()
+            .rev()
+            .filter(|d| d.webhook_id == webhook_id)
+            .filter(|d| query.status.map(|s| d.status == s).unwrap_or(true))
+            .take(query.limit)
+            .cloned()
+            .collect())
+    }
+
+    /// Deliveries that were given up on, newest first
+    pub fn dead_letters(&self, webhook_id: Option<Uuid>, limit: usize) -> Vec<WebhookDelivery> {
+        let log = self.deliveries.lock().unwrap();
+        log.dead
+            .iter()
+            .rev()
+            .filter(|d| webhook_id.map(|id| d.webhook_id == id).unwrap_or(true))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Send an event about `device_id` to every webhook subscribed to it
+    ///
+    /// Deliveries happen in the background, so this returns without waiting
+    /// for any endpoint. A webhook with too many deliveries under way has the
+    /// new one dead-lettered instead. Failing to load the webhooks is logged rather than
+    /// returned, so webhook problems never affect ingestion.
+    pub async fn notify(&self, event: WebhookEvent, device_id: &str, data: serde_json::Value) {
+        let webhooks = match self.webhooks().await {
+            Ok(webhooks) => webhooks,
+            Err(e) => {
+                tracing::warn!("Skipping webhook delivery: {}", e);
+                return;
+            }
+        };
+
+        let now = Utc::now();
+        let subscribed = webhooks
+            .iter()
+            .filter(|w| w.events.contains(&event) && glob_match(&w.device, device_id));
+        for webhook in subscribed {
+            let id = Uuid::new_v4();
+            let delivery = WebhookDelivery {
+                id,
+                webhook_id: webhook.id,
+                event,
+                status: DeliveryStatus::Pending,
+                attempts: 0,
+                last_status: None,
+                last_error: None,
+                payload: json!({
+                    "id": id,
+                    "event": event,
+                    "created_at": now,
+                    "data": data,
+                }),
+                created_at: now,
+                last_attempt_at: None,
+            };
+
+            let Some(permit) = self.permit(webhook.id) else {
+                tracing::warn!("Dead-lettering delivery to busy webhook {}", webhook.id);
+                let delivery = WebhookDelivery {
+                    status: DeliveryStatus::Dead,
+                    last_error: Some("Too many deliveries under way to this webhook".to_string()),
+                    ..delivery
+                };
+                let mut log = self.deliveries.lock().unwrap();
+                log.record(delivery.clone());
+                log.update(&delivery);
+                continue;
+            };
+
+            self.deliveries.lock().unwrap().record(delivery.clone());
+            tokio::spawn(deliver(
+                self.client.clone(),
+                self.retry,
+                self.deliveries.clone(),
+                webhook.clone(),
+                delivery,
+                permit,
+            ));
+        }
+    }
+
+    /// Claim a slot for a delivery to a webhook, or `None` when every slot is taken
+    fn permit(&self, webhook_id: Uuid) -> Option<OwnedSemaphorePermit> {
+        let semaphore = self
+            .in_flight
+            .lock()
+            .unwrap()
+            .entry(webhook_id)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.max_in_flight)))
+            .clone();
+        semaphore.try_acquire_owned().ok()
+    }
+
+    /// Every webhook, loading them from the store if needed
+    async fn webhooks(&self) -> Result<Arc<Vec<Webhook>>, AppError> {
+        if let Some(webhooks) = self.webhooks.read().await.as_ref() {
+            return Ok(webhooks.clone());
+        }
+        let mut cached = self.webhooks.write().await;
+        self.load(&mut cached).await
+    }
+
+    /// Fill `cached` from the store unless it is already loaded
+    async fn load(
+        &self,
+        cached: &mut Option<Arc<Vec<Webhook>>>,
+    ) -> Result<Arc<Vec<Webhook>>, AppError> {
+        if let Some(webhooks) = cached {
+            return Ok(webhooks.clone());
+        }
+        let webhooks = Arc::new(self.store.list().await.map_err(AppError::InternalError)?);
+        *cached = Some(webhooks.clone());
+        Ok(webhooks)
+    }
+}
+
+/// POST a delivery until the endpoint accepts it or the attempts run out
+///
+/// The webhook's slot is held until then.
+async fn deliver(
+    client: reqwest::Client,
+    retry: RetryPolicy,
+    log: Arc<Mutex<DeliveryLog>>,
+    webhook: Webhook,
+    mut delivery: WebhookDelivery,
+    _permit: OwnedSemaphorePermit,
+) {
+    let body = serde_json::to_vec(&delivery.payload).unwrap_or_default();
+    let signature = format!("sha256={}", sign(&webhook.secret, &body));
+
+    loop {
+        delivery.attempts += 1;
+        delivery.last_attempt_at = Some(Utc::now());
+
+        let response = client
+            .post(&webhook.url)
+            .header(CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, delivery.event.as_str())
+            .header(DELIVERY_HEADER, delivery.id.to_string())
+            .header(SIGNATURE_HEADER, &signature)
+            .body(body.clone())
+            .send()
+            .await;
+
+        match response {
+            Ok(response) => {
+                let status = response.status();
+                delivery.last_status = Some(status.as_u16());
+                if status.is_success() {
+                    delivery.status = DeliveryStatus::Delivered;
+                    delivery.last_error = None;
+                } else {
+                    delivery.last_error = Some(format!("Endpoint responded with {}", status));
+                }
+            }
+            Err(e) => {
+                delivery.last_status = None;
+                delivery.last_error = Some(e.to_string());
+            }
+        }
+
+        if delivery.status == DeliveryStatus::Pending && delivery.attempts >= retry.max_attempts {
+            delivery.status = DeliveryStatus::Dead;
+        }
+        log.lock().unwrap().update(&delivery);
+
+        if delivery.status != DeliveryStatus::Pending {
+            return;
+        }
+        tokio::time::sleep(retry.delay(delivery.attempts)).await;
+    }
+}
+
+/// Hex HMAC-SHA256 of `body` keyed with `secret`
+fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Check a webhook request, reporting every invalid field
+fn validate_webhook(request: &WebhookRequest) -> Result<(), AppError> {
+    let mut errors = Vec::new();
+
+    let scheme_ok = Url::parse(&request.url)
+        .map(|url| matches!(url.scheme(), "http" | "https"))
+        .unwrap_or(false);
+    if !scheme_ok {
+        errors.push(FieldError::new("url", "must be an http or https URL"));
+    }
+    if request.secret.is_empty() {
+        errors.push(FieldError::new("secret", "must not be empty"));
+    }
+    if request.device.trim().is_empty() {
+        errors.push(FieldError::new("device", "must not be empty"));
+    }
+    if request.events.is_empty() {
+        errors.push(FieldError::new("events", "must list at least one event"));
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::InvalidFields(errors))
+    }
+}
+
+fn webhook_not_found(id: Uuid) -> AppError {
+    AppError::NotFound(format!("Webhook {} not found", id))
+}
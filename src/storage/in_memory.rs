@@ -1,19 +1,92 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::mem::size_of;
+use std::ops::Bound;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
 use uuid::Uuid;
 
-use crate::models::TelemetryData;
+use super::{AlertRuleRepository, DeviceRepository, TelemetryRepository, WebhookRepository};
+use crate::config::MemoryLimits;
+use crate::models::{
+    bucket_start, AlertRule, BucketStats, Device, DeviceMemoryUsage, DeviceSummary, EvictionCounts,
+    MemoryUsage, MemoryUsageQuery, MetricRollup, MetricStats, SortOrder, TelemetryCursor,
+    TelemetryData, TelemetryFilter, TelemetryTier, Webhook,
+};
 
 /// In-memory telemetry data store using DashMap for concurrent access
+///
+/// Optional [`MemoryLimits`] bound what it holds. Whenever a cap is exceeded
+/// the oldest readings, by timestamp, are evicted, even if that is the one
+/// just added. Rollups are not counted against the caps.
 pub struct TelemetryStore {
-    /// Maps device_id to a vector of telemetry records
-    data: DashMap<String, Vec<TelemetryData>>,
+    /// Maps device_id to its telemetry records
+    data: DashMap<String, DeviceRecords>,
+
+    /// Maps device_id to its rollups
+    rollups: DashMap<String, DeviceRollups>,
+
+    /// Maps each record ID to its device and key in `data`
+    index: DashMap<Uuid, (String, TelemetryCursor)>,
+
+    limits: MemoryLimits,
+
+    /// Key of every record, oldest first; only kept when a cap spans every device
+    oldest: Option<Mutex<BTreeSet<(TelemetryCursor, String)>>>,
+
+    total_records: AtomicUsize,
+    total_bytes: AtomicUsize,
+    device_cap_evictions: AtomicU64,
+    total_cap_evictions: AtomicU64,
+    byte_cap_evictions: AtomicU64,
+}
+
+/// Telemetry records of one device
+#[derive(Default)]
+struct DeviceRecords {
+    /// Ordered by timestamp and then ID
+    records: BTreeMap<TelemetryCursor, TelemetryData>,
+
+    /// Approximate memory the records take
+    bytes: usize,
+
+    /// Records evicted to stay within the memory caps
+    evicted: u64,
+}
+
+/// Rollups of one device, keyed by tier and bucket start and then by metric
+type DeviceRollups = BTreeMap<(TelemetryTier, DateTime<Utc>), BTreeMap<String, MetricStats>>;
+
+impl DeviceRecords {
+    fn insert(&mut self, key: TelemetryCursor, telemetry: TelemetryData) {
+        self.bytes += approx_size(&telemetry);
+        self.records.insert(key, telemetry);
+    }
+
+    fn remove(&mut self, key: &TelemetryCursor) -> Option<TelemetryData> {
+        let telemetry = self.records.remove(key)?;
+        self.bytes -= approx_size(&telemetry);
+        Some(telemetry)
+    }
 }
 
 impl Default for TelemetryStore {
     fn default() -> Self {
         Self {
             data: DashMap::new(),
+            rollups: DashMap::new(),
+            index: DashMap::new(),
+            limits: MemoryLimits::default(),
+            oldest: None,
+            total_records: AtomicUsize::new(0),
+            total_bytes: AtomicUsize::new(0),
+            device_cap_evictions: AtomicU64::new(0),
+            total_cap_evictions: AtomicU64::new(0),
+            byte_cap_evictions: AtomicU64::new(0),
         }
     }
 }
@@ -24,67 +97,607 @@ impl TelemetryStore {
         Self::default()
     }
 
-    /// Add a telemetry record to the store
-    pub async fn add(&self, telemetry: TelemetryData) -> Result<Uuid, String> {
+    /// Evict the oldest readings to stay within `limits`
+    pub fn with_limits(mut self, limits: MemoryLimits) -> Self {
+        self.limits = limits;
+        self.oldest = limits.has_global_cap().then(Mutex::default);
+        self
+    }
+
+    /// Caps bounding what the store holds
+    pub(crate) fn limits(&self) -> MemoryLimits {
+        self.limits
+    }
+
+    /// Copy of every record held, grouped by device and oldest first within each
+    pub(crate) fn all_records(&self) -> Vec<TelemetryData> {
+        self.data
+            .iter()
+            .flat_map(|entry| entry.records.values().cloned().collect::<Vec<_>>())
+            .collect()
+    }
+
+    /// Copy of every rollup held
+    pub(crate) fn all_rollups(&self) -> Vec<MetricRollup> {
+        let mut all = Vec::new();
+        for entry in self.rollups.iter() {
+            for ((tier, bucket_start), metrics) in entry.value() {
+                all.extend(metrics.iter().map(|(metric, stats)| MetricRollup {
+                    device_id: entry.key().clone(),
+                    tier: *tier,
+                    bucket_start: *bucket_start,
+                    metric: metric.clone(),
+                    stats: stats.clone(),
+                }));
+            }
+        }
+        all
+    }
+
+    /// Put back a record without folding it into the rollups again
+    pub(crate) fn restore(&self, telemetry: TelemetryData) -> Result<Uuid, String> {
+        self.insert(telemetry, false)
+    }
+
+    /// Put back a rollup, replacing any statistics held for its bucket and metric
+    pub(crate) fn restore_rollup(&self, rollup: MetricRollup) {
+        self.rollups
+            .entry(rollup.device_id)
+            .or_default()
+            .entry((rollup.tier, rollup.bucket_start))
+            .or_default()
+            .insert(rollup.metric, rollup.stats);
+    }
+
+    /// Store a record, folding it into the rollups when `fold` is set
+    fn insert(&self, telemetry: TelemetryData, fold: bool) -> Result<Uuid, String> {
         let device_id = telemetry.device_id.clone();
         let id = telemetry.id;
+        let key = TelemetryCursor::after(&telemetry);
 
-        // Insert into the device's telemetry list, creating it if it doesn't exist
-        self.data.entry(device_id).or_default().push(telemetry);
+        // Claim the ID first so a duplicate never leaves an unindexed record behind
+        match self.index.entry(id) {
+            Entry::Occupied(_) => return Err(format!("Telemetry with ID {} already exists", id)),
+            Entry::Vacant(entry) => {
+                entry.insert((device_id.clone(), key));
+            }
+        }
+
+        if fold {
+            for rollup in MetricRollup::fold([&telemetry]) {
+                self.rollups
+                    .entry(rollup.device_id)
+                    .or_default()
+                    .entry((rollup.tier, rollup.bucket_start))
+                    .or_default()
+                    .entry(rollup.metric)
+                    .or_default()
+                    .merge(&rollup.stats);
+            }
+        }
+
+        // Insert into the device's ordered history, creating it if it doesn't exist
+        let size = approx_size(&telemetry);
+        let mut data = self.data.entry(device_id.clone()).or_default();
+        data.insert(key, telemetry);
+        self.total_records.fetch_add(1, Ordering::Relaxed);
+        self.total_bytes.fetch_add(size, Ordering::Relaxed);
+        if let Some(oldest) = &self.oldest {
+            oldest.lock().unwrap().insert((key, device_id.clone()));
+        }
+
+        let mut evicted = Vec::new();
+        if let Some(max) = self.limits.max_records_per_device {
+            while data.records.len() > max {
+                let Some((key, _)) = data.records.first_key_value() else {
+                    break;
+                };
+                let key = *key;
+                evicted.extend(data.remove(&key));
+                data.evicted += 1;
+            }
+        }
+        drop(data);
 
+        self.forget(&evicted);
+        self.device_cap_evictions
+            .fetch_add(evicted.len() as u64, Ordering::Relaxed);
+        self.enforce_global_caps();
         Ok(id)
     }
 
-    /// Get telemetry data for a specific device, optionally filtered by time range
-    pub async fn get_by_device(
+    /// Whether a cap spanning every device is exceeded, and which one
+    fn exceeded_global_cap(&self) -> Option<&AtomicU64> {
+        let over = |cap: Option<usize>, used: &AtomicUsize| {
+            cap.map(|cap| used.load(Ordering::Relaxed) > cap)
+                .unwrap_or(false)
+        };
+
+        if over(self.limits.max_total_records, &self.total_records) {
+            Some(&self.total_cap_evictions)
+        } else if over(self.limits.max_bytes, &self.total_bytes) {
+            Some(&self.byte_cap_evictions)
+        } else {
+            None
+        }
+    }
+
+    /// Evict the oldest readings across devices until every global cap is met
+    fn enforce_global_caps(&self) {
+        let Some(oldest) = &self.oldest else {
+            return;
+        };
+
+        while let Some(counter) = self.exceeded_global_cap() {
+            let Some((key, device_id)) = oldest.lock().unwrap().pop_first() else {
+                return;
+            };
+            let evicted = self.data.get_mut(&device_id).and_then(|mut data| {
+                let telemetry = data.remove(&key)?;
+                data.evicted += 1;
+                Some(telemetry)
+            });
+            if let Some(telemetry) = evicted {
+                self.forget([&telemetry]);
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Drop the index entries, totals and eviction order of records removed from `data`
+    fn forget<'a>(&self, records: impl IntoIterator<Item = &'a TelemetryData>) {
+        let mut oldest = self.oldest.as_ref().map(|o| o.lock().unwrap());
+        for telemetry in records {
+            self.index.remove(&telemetry.id);
+            self.total_records.fetch_sub(1, Ordering::Relaxed);
+            self.total_bytes
+                .fetch_sub(approx_size(telemetry), Ordering::Relaxed);
+            if let Some(oldest) = oldest.as_mut() {
+                oldest.remove(&(
+                    TelemetryCursor::after(telemetry),
+                    telemetry.device_id.clone(),
+                ));
+            }
+        }
+    }
+}
+
+/// Approximate memory a stored record takes, including its index entry
+fn approx_size(telemetry: &TelemetryData) -> usize {
+    // Map nodes cost roughly this much beyond their keys and values
+    const NODE_OVERHEAD: usize = 16;
+    const RECORD_SIZE: usize = size_of::<TelemetryCursor>()
+        + size_of::<TelemetryData>()
+        + size_of::<Uuid>()
+        + size_of::<(String, TelemetryCursor)>()
+        + 2 * NODE_OVERHEAD;
+    const METRIC_SIZE: usize = size_of::<String>() + size_of::<f64>() + NODE_OVERHEAD;
+
+    // The device ID is held by both the record and its index entry
+    RECORD_SIZE
+        + 2 * telemetry.device_id.len()
+        + telemetry
+            .metrics
+            .keys()
+            .map(|name| METRIC_SIZE + name.len())
+            .sum::<usize>()
+}
+
+/// Key range covering a time window, narrowed by a pagination cursor
+///
+/// Returns `None` when the range is empty, since `BTreeMap::range` panics on
+/// inverted bounds.
+fn key_range(
+    start_time: Option<DateTime<Utc>>,
+    end_time: Option<DateTime<Utc>>,
+    after: Option<TelemetryCursor>,
+    order: SortOrder,
+) -> Option<(Bound<TelemetryCursor>, Bound<TelemetryCursor>)> {
+    let mut lower = start_time.map_or(Bound::Unbounded, |timestamp| {
+        Bound::Included(TelemetryCursor {
+            timestamp,
+            id: Uuid::nil(),
+        })
+    });
+    let mut upper = end_time.map_or(Bound::Unbounded, |timestamp| {
+        Bound::Included(TelemetryCursor {
+            timestamp,
+            id: Uuid::max(),
+        })
+    });
+
+    // The cursor tightens whichever end the query is walking away from
+    if let Some(cursor) = after {
+        match order {
+            SortOrder::Asc => match lower {
+                Bound::Included(key) if key > cursor => {}
+                _ => lower = Bound::Excluded(cursor),
+            },
+            SortOrder::Desc => match upper {
+                Bound::Included(key) if key < cursor => {}
+                _ => upper = Bound::Excluded(cursor),
+            },
+        }
+    }
+
+    let empty = match (&lower, &upper) {
+        (Bound::Included(l), Bound::Included(u)) => l > u,
+        (Bound::Included(l) | Bound::Excluded(l), Bound::Included(u) | Bound::Excluded(u)) => {
+            l >= u
+        }
+        _ => false,
+    };
+
+    (!empty).then_some((lower, upper))
+}
+
+#[async_trait]
+impl TelemetryRepository for TelemetryStore {
+    async fn add(&self, telemetry: TelemetryData) -> Result<Uuid, String> {
+        self.insert(telemetry, true)
+    }
+
+    async fn get_by_device(
         &self,
         device_id: &str,
-        start_time: Option<DateTime<Utc>>,
-        end_time: Option<DateTime<Utc>>,
+        filter: &TelemetryFilter,
+        after: Option<TelemetryCursor>,
+        order: SortOrder,
         limit: usize,
-    ) -> Vec<TelemetryData> {
-        match self.data.get(device_id) {
-            Some(data) => {
-                let filtered = data
-                    .iter()
-                    .filter(|t| {
-                        // Apply time range filters if provided
-                        let after_start = start_time.map(|st| t.timestamp >= st).unwrap_or(true);
+    ) -> Result<Vec<TelemetryData>, String> {
+        let Some(data) = self.data.get(device_id) else {
+            return Ok(Vec::new());
+        };
+        let Some(range) = key_range(filter.start_time, filter.end_time, after, order) else {
+            return Ok(Vec::new());
+        };
 
-                        let before_end = end_time.map(|et| t.timestamp <= et).unwrap_or(true);
+        // Records are keyed by device timestamp, so receive times are checked one by one
+        let records = data
+            .records
+            .range(range)
+            .map(|(_, t)| t)
+            .filter(|t| filter.accepts_received_at(t.received_at));
+        let page = match order {
+            SortOrder::Asc => records.take(limit).cloned().collect(),
+            SortOrder::Desc => records.rev().take(limit).cloned().collect(),
+        };
 
-                        after_start && before_end
-                    })
-                    .take(limit)
-                    .cloned()
-                    .collect();
+        Ok(page)
+    }
 
-                filtered
+    async fn received_after(
+        &self,
+        device_id: Option<&str>,
+        after: TelemetryCursor,
+        limit: usize,
+    ) -> Result<Vec<TelemetryData>, String> {
+        // Records are keyed by device timestamp, so every one has to be checked
+        let mut received = Vec::new();
+        let mut collect = |data: &DeviceRecords| {
+            received.extend(
+                data.records
+                    .values()
+                    .filter(|t| TelemetryCursor::received(t) > after)
+                    .cloned(),
+            );
+        };
+        match device_id {
+            Some(device_id) => {
+                if let Some(data) = self.data.get(device_id) {
+                    collect(&data);
+                }
             }
-            None => Vec::new(),
+            None => self.data.iter().for_each(|data| collect(&data)),
         }
+
+        received.sort_by_key(TelemetryCursor::received);
+        received.truncate(limit);
+        Ok(received)
     }
 
-    /// Delete telemetry records for a device older than the specified timestamp
-    pub async fn delete_old_records(&self, device_id: &str, older_than: DateTime<Utc>) -> usize {
+    async fn list_devices(
+        &self,
+        prefix: Option<&str>,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<DeviceSummary>, String> {
+        let mut device_ids: Vec<String> = self
+            .data
+            .iter()
+            .map(|entry| entry.key().clone())
+            .filter(|device_id| {
+                prefix.map(|p| device_id.starts_with(p)).unwrap_or(true)
+                    && after.map(|a| device_id.as_str() > a).unwrap_or(true)
+            })
+            .collect();
+        device_ids.sort();
+
+        // Summaries need a scan for the receive time, so only build the page's
+        let devices = device_ids
+            .into_iter()
+            .filter_map(|device_id| {
+                let data = self.data.get(&device_id)?;
+                // Devices whose records were all deleted have no summary
+                let (first, _) = data.records.first_key_value()?;
+                let (last, _) = data.records.last_key_value()?;
+                let last_seen = data.records.values().map(|t| t.received_at).max()?;
+                Some(DeviceSummary {
+                    record_count: data.records.len() as u64,
+                    first_timestamp: first.timestamp,
+                    last_timestamp: last.timestamp,
+                    last_seen,
+                    device_id,
+                })
+            })
+            .take(limit)
+            .collect();
+
+        Ok(devices)
+    }
+
+    async fn get_by_id(&self, id: Uuid) -> Result<Option<TelemetryData>, String> {
+        // Copy the location out so the index shard is not locked while reading data
+        let Some((device_id, key)) = self.index.get(&id).map(|entry| entry.clone()) else {
+            return Ok(None);
+        };
+
+        Ok(self
+            .data
+            .get(&device_id)
+            .and_then(|data| data.records.get(&key).cloned()))
+    }
+
+    async fn delete_old_records(
+        &self,
+        device_id: &str,
+        older_than: DateTime<Utc>,
+    ) -> Result<usize, String> {
         if let Some(mut data) = self.data.get_mut(device_id) {
-            let initial_count = data.len();
-            data.retain(|t| t.timestamp >= older_than);
+            // Everything before the first key at `older_than` is expired
+            let kept = data.records.split_off(&TelemetryCursor {
+                timestamp: older_than,
+                id: Uuid::nil(),
+            });
+            let deleted = std::mem::replace(&mut data.records, kept);
+            data.bytes -= deleted.values().map(approx_size).sum::<usize>();
+            drop(data);
 
-            initial_count - data.len()
+            self.forget(deleted.values());
+            Ok(deleted.len())
         } else {
-            0
+            Ok(0)
+        }
+    }
+
+    async fn rollups(
+        &self,
+        device_id: &str,
+        tier: TelemetryTier,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        metrics: &[String],
+    ) -> Result<Vec<BucketStats>, String> {
+        let (Some(width), Some(rollups)) = (tier.width(), self.rollups.get(device_id)) else {
+            return Ok(Vec::new());
+        };
+        let from = (
+            tier,
+            start_time.map_or(DateTime::<Utc>::MIN_UTC, |start| bucket_start(start, width)),
+        );
+        let to = (tier, end_time.unwrap_or(DateTime::<Utc>::MAX_UTC));
+        if from > to {
+            return Ok(Vec::new());
         }
+
+        let buckets = rollups
+            .range(from..=to)
+            .map(|((_, start), stats)| BucketStats {
+                bucket_start: *start,
+                metrics: stats
+                    .iter()
+                    .filter(|(name, _)| metrics.is_empty() || metrics.contains(name))
+                    .map(|(name, stats)| (name.clone(), stats.clone()))
+                    .collect(),
+            })
+            .filter(|bucket| !bucket.metrics.is_empty())
+            .collect();
+
+        Ok(buckets)
     }
 
-    /// Get telemetry data by its unique ID
-    pub async fn get_by_id(&self, id: Uuid) -> Option<TelemetryData> {
-        for device_data in self.data.iter() {
-            if let Some(telemetry) = device_data.iter().find(|t| t.id == id) {
-                return Some(telemetry.clone());
+    async fn delete_old_rollups(
+        &self,
+        tier: TelemetryTier,
+        older_than: DateTime<Utc>,
+    ) -> Result<usize, String> {
+        let mut deleted = 0;
+        for mut rollups in self.rollups.iter_mut() {
+            rollups.retain(|(bucket_tier, start), metrics| {
+                let expired = *bucket_tier == tier && *start < older_than;
+                if expired {
+                    deleted += metrics.len();
+                }
+                !expired
+            });
+        }
+        self.rollups.retain(|_, rollups| !rollups.is_empty());
+
+        Ok(deleted)
+    }
+
+    async fn memory_usage(&self, query: &MemoryUsageQuery) -> Result<Option<MemoryUsage>, String> {
+        let mut devices: Vec<DeviceMemoryUsage> = self
+            .data
+            .iter()
+            .filter(|entry| {
+                query
+                    .device_id
+                    .as_deref()
+                    .map(|d| entry.key() == d)
+                    .unwrap_or(true)
+            })
+            .map(|entry| DeviceMemoryUsage {
+                device_id: entry.key().clone(),
+                records: entry.records.len(),
+                approx_bytes: entry.bytes,
+                evicted: entry.evicted,
+            })
+            .collect();
+        devices.sort_by(|a, b| {
+            b.approx_bytes
+                .cmp(&a.approx_bytes)
+                .then_with(|| a.device_id.cmp(&b.device_id))
+        });
+        devices.truncate(query.limit);
+
+        Ok(Some(MemoryUsage {
+            limits: self.limits,
+            total_records: self.total_records.load(Ordering::Relaxed),
+            approx_bytes: self.total_bytes.load(Ordering::Relaxed),
+            evictions: EvictionCounts {
+                device_cap: self.device_cap_evictions.load(Ordering::Relaxed),
+                total_cap: self.total_cap_evictions.load(Ordering::Relaxed),
+                byte_cap: self.byte_cap_evictions.load(Ordering::Relaxed),
+            },
+            devices,
+        }))
+    }
+}
+
+/// In-memory device registry using DashMap for concurrent access
+#[derive(Default)]
+pub struct DeviceRegistryStore {
+    /// Maps device_id to its registration
+    devices: DashMap<String, Device>,
+}
+
+impl DeviceRegistryStore {
+    /// Create a new, empty device registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl DeviceRepository for DeviceRegistryStore {
+    async fn insert(&self, device: Device) -> Result<bool, String> {
+        match self.devices.entry(device.device_id.clone()) {
+            Entry::Occupied(_) => Ok(false),
+            Entry::Vacant(entry) => {
+                entry.insert(device);
+                Ok(true)
             }
         }
+    }
+
+    async fn get(&self, device_id: &str) -> Result<Option<Device>, String> {
+        Ok(self.devices.get(device_id).map(|device| device.clone()))
+    }
+
+    async fn list(
+        &self,
+        prefix: Option<&str>,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<Device>, String> {
+        let mut devices: Vec<Device> = self
+            .devices
+            .iter()
+            .filter(|entry| {
+                prefix.map(|p| entry.key().starts_with(p)).unwrap_or(true)
+                    && after.map(|a| entry.key().as_str() > a).unwrap_or(true)
+            })
+            .map(|entry| entry.value().clone())
+            .collect();
+        devices.sort_by(|a, b| a.device_id.cmp(&b.device_id));
+        devices.truncate(limit);
+        Ok(devices)
+    }
+
+    async fn update(&self, device: Device) -> Result<bool, String> {
+        match self.devices.get_mut(&device.device_id) {
+            Some(mut existing) => {
+                *existing = device;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn delete(&self, device_id: &str) -> Result<bool, String> {
+        Ok(self.devices.remove(device_id).is_some())
+    }
+}
+
+/// In-memory alert rule store using DashMap for concurrent access
+#[derive(Default)]
+pub struct AlertRuleStore {
+    rules: DashMap<Uuid, AlertRule>,
+}
+
+impl AlertRuleStore {
+    /// Create a new, empty alert rule store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl AlertRuleRepository for AlertRuleStore {
+    async fn insert(&self, rule: AlertRule) -> Result<(), String> {
+        self.rules.insert(rule.id, rule);
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<AlertRule>, String> {
+        let mut rules: Vec<AlertRule> = self.rules.iter().map(|r| r.value().clone()).collect();
+        rules.sort_by_key(|r| (r.created_at, r.id));
+        Ok(rules)
+    }
+
+    async fn update(&self, rule: AlertRule) -> Result<bool, String> {
+        match self.rules.get_mut(&rule.id) {
+            Some(mut existing) => {
+                *existing = rule;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<bool, String> {
+        Ok(self.rules.remove(&id).is_some())
+    }
+}
+
+/// In-memory webhook store using DashMap for concurrent access
+#[derive(Default)]
+pub struct WebhookStore {
+    webhooks: DashMap<Uuid, Webhook>,
+}
+
+impl WebhookStore {
+    /// Create a new, empty webhook store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl WebhookRepository for WebhookStore {
+    async fn insert(&self, webhook: Webhook) -> Result<(), String> {
+        self.webhooks.insert(webhook.id, webhook);
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<Webhook>, String> {
+        let mut webhooks: Vec<Webhook> = self.webhooks.iter().map(|w| w.value().clone()).collect();
+        webhooks.sort_by_key(|w| (w.created_at, w.id));
+        Ok(webhooks)
+    }
 
-        None
+    async fn delete(&self, id: Uuid) -> Result<bool, String> {
+        Ok(self.webhooks.remove(&id).is_some())
     }
 }
@@ -1,3 +1,265 @@
 mod in_memory;
+#[cfg(feature = "postgres")]
+mod postgres;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod wal;
 
-pub use in_memory::TelemetryStore;
+pub use in_memory::{AlertRuleStore, DeviceRegistryStore, TelemetryStore, WebhookStore};
+#[cfg(feature = "postgres")]
+pub use postgres::{
+    PostgresAlertRules, PostgresDeviceRegistry, PostgresTelemetryStore, PostgresWebhooks,
+};
+#[cfg(feature = "sqlite")]
+pub use sqlite::{SqliteAlertRules, SqliteDeviceRegistry, SqliteTelemetryStore, SqliteWebhooks};
+pub use wal::DurableTelemetryStore;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use crate::config::AppConfig;
+use crate::models::{
+    AlertRule, BucketStats, Device, DeviceSummary, MemoryUsage, MemoryUsageQuery, SortOrder,
+    TelemetryCursor, TelemetryData, TelemetryFilter, TelemetryTier, Webhook,
+};
+
+/// Storage backend for telemetry records
+///
+/// Implementations must be safe to share between worker threads, since a
+/// single repository instance backs every request handled by the server.
+/// Every record added is also folded into the rollups of each
+/// [`TelemetryTier::ROLLUPS`] tier, which deleting records leaves in place.
+#[async_trait]
+pub trait TelemetryRepository: Send + Sync {
+    /// Add a telemetry record to the store
+    async fn add(&self, telemetry: TelemetryData) -> Result<Uuid, String>;
+
+    /// Add several telemetry records in a single pass, returning each one's outcome in order
+    ///
+    /// The default implementation adds records one at a time, so some may be
+    /// stored while others fail; database backends override it to write
+    /// every record in a single transaction, so they succeed or fail together.
+    async fn add_batch(&self, telemetry: Vec<TelemetryData>) -> Vec<Result<Uuid, String>> {
+        let mut results = Vec::with_capacity(telemetry.len());
+        for record in telemetry {
+            results.push(self.add(record).await);
+        }
+        results
+    }
+
+    /// Get telemetry data for a specific device, optionally filtered by time range
+    ///
+    /// Records are ordered by timestamp and then ID, ascending or descending
+    /// according to `order`. When `after` is set only records positioned
+    /// strictly after that cursor in the requested order are returned.
+    async fn get_by_device(
+        &self,
+        device_id: &str,
+        filter: &TelemetryFilter,
+        after: Option<TelemetryCursor>,
+        order: SortOrder,
+        limit: usize,
+    ) -> Result<Vec<TelemetryData>, String>;
+
+    /// Get records received strictly after `after`, in the order they were received
+    ///
+    /// Records are ordered by receive time and then ID, as compared by
+    /// [`TelemetryCursor::received`]. Covers one device, or every device when
+    /// `device_id` is `None`.
+    async fn received_after(
+        &self,
+        device_id: Option<&str>,
+        after: TelemetryCursor,
+        limit: usize,
+    ) -> Result<Vec<TelemetryData>, String>;
+
+    /// Aggregate a device's telemetry into buckets of width `bucket`
+    ///
+    /// Buckets are aligned to the Unix epoch and returned in time order.
+    /// Only the named `metrics` are aggregated, or every metric when empty.
+    /// Returns `None` when the backend cannot aggregate natively, in which
+    /// case callers aggregate the raw records themselves.
+    async fn aggregate(
+        &self,
+        _device_id: &str,
+        _start_time: Option<DateTime<Utc>>,
+        _end_time: Option<DateTime<Utc>>,
+        _bucket: Duration,
+        _metrics: &[String],
+    ) -> Result<Option<Vec<BucketStats>>, String> {
+        Ok(None)
+    }
+
+    /// List devices that have stored telemetry, ordered by device ID
+    ///
+    /// Only devices whose ID starts with `prefix` and sorts strictly after
+    /// `after` are returned.
+    async fn list_devices(
+        &self,
+        prefix: Option<&str>,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<DeviceSummary>, String>;
+
+    /// Get telemetry data by its unique ID
+    async fn get_by_id(&self, id: Uuid) -> Result<Option<TelemetryData>, String>;
+
+    /// Delete telemetry records for a device older than the specified timestamp
+    async fn delete_old_records(
+        &self,
+        device_id: &str,
+        older_than: DateTime<Utc>,
+    ) -> Result<usize, String>;
+
+    /// Get a device's rollups in `tier`, one entry per tier bucket in time order
+    ///
+    /// Buckets from the one containing `start_time` up to `end_time` are
+    /// returned. Only the named `metrics` are included, or every metric when empty.
+    async fn rollups(
+        &self,
+        device_id: &str,
+        tier: TelemetryTier,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        metrics: &[String],
+    ) -> Result<Vec<BucketStats>, String>;
+
+    /// Delete every device's rollups in `tier` whose bucket starts before `older_than`
+    ///
+    /// Returns how many per-metric rollups were deleted.
+    async fn delete_old_rollups(
+        &self,
+        tier: TelemetryTier,
+        older_than: DateTime<Utc>,
+    ) -> Result<usize, String>;
+
+    /// Report the memory held for telemetry and the readings evicted to bound it
+    ///
+    /// Returns `None` for backends that do not keep telemetry in memory.
+    async fn memory_usage(&self, _query: &MemoryUsageQuery) -> Result<Option<MemoryUsage>, String> {
+        Ok(None)
+    }
+}
+
+/// Storage backend for the device registry
+#[async_trait]
+pub trait DeviceRepository: Send + Sync {
+    /// Register a device, returning `false` if its ID is already registered
+    async fn insert(&self, device: Device) -> Result<bool, String>;
+
+    /// Get a registered device by ID
+    async fn get(&self, device_id: &str) -> Result<Option<Device>, String>;
+
+    /// List registered devices ordered by device ID, starting after `after`
+    async fn list(
+        &self,
+        prefix: Option<&str>,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<Device>, String>;
+
+    /// Replace a registered device, returning `false` if it is not registered
+    async fn update(&self, device: Device) -> Result<bool, String>;
+
+    /// Remove a device from the registry, returning `false` if it was not registered
+    async fn delete(&self, device_id: &str) -> Result<bool, String>;
+}
+
+/// Storage backend for alert rules
+#[async_trait]
+pub trait AlertRuleRepository: Send + Sync {
+    /// Add a new alert rule
+    async fn insert(&self, rule: AlertRule) -> Result<(), String>;
+
+    /// List every alert rule, oldest first
+    async fn list(&self) -> Result<Vec<AlertRule>, String>;
+
+    /// Replace an alert rule, returning `false` if it does not exist
+    async fn update(&self, rule: AlertRule) -> Result<bool, String>;
+
+    /// Remove an alert rule, returning `false` if it did not exist
+    async fn delete(&self, id: Uuid) -> Result<bool, String>;
+}
+
+/// Storage backend for webhook subscriptions
+#[async_trait]
+pub trait WebhookRepository: Send + Sync {
+    /// Add a new webhook
+    async fn insert(&self, webhook: Webhook) -> Result<(), String>;
+
+    /// List every webhook, oldest first
+    async fn list(&self) -> Result<Vec<Webhook>, String>;
+
+    /// Remove a webhook, returning `false` if it did not exist
+    async fn delete(&self, id: Uuid) -> Result<bool, String>;
+}
+
+/// Storage backends selected at startup
+pub struct Storage {
+    pub telemetry: Arc<dyn TelemetryRepository>,
+    pub devices: Arc<dyn DeviceRepository>,
+    pub alert_rules: Arc<dyn AlertRuleRepository>,
+    pub webhooks: Arc<dyn WebhookRepository>,
+}
+
+/// Create the storage backends selected by the application configuration
+///
+/// Without a `database_url` the in-memory stores are used, with telemetry
+/// persisted to `persistence.data_dir` when one is set. A `sqlite:` URL
+/// selects the SQLite backend when the `sqlite` feature is enabled, and a
+/// `postgres:` URL the PostgreSQL backend when the `postgres` feature is.
+pub async fn create_storage(config: &AppConfig) -> Result<Storage, String> {
+    match config.database_url.as_deref() {
+        None => {
+            let store = TelemetryStore::new().with_limits(config.memory_limits);
+            let persistence = &config.persistence;
+            let telemetry: Arc<dyn TelemetryRepository> = match &persistence.data_dir {
+                Some(dir) => {
+                    let store =
+                        Arc::new(DurableTelemetryStore::open(store, dir, persistence.fsync).await?);
+                    tokio::spawn(store.clone().run(
+                        std::time::Duration::from_secs(persistence.snapshot_interval_secs.max(1)),
+                        std::time::Duration::from_millis(persistence.fsync_interval_ms.max(1)),
+                    ));
+                    store
+                }
+                None => Arc::new(store),
+            };
+
+            Ok(Storage {
+                telemetry,
+                devices: Arc::new(DeviceRegistryStore::new()),
+                alert_rules: Arc::new(AlertRuleStore::new()),
+                webhooks: Arc::new(WebhookStore::new()),
+            })
+        }
+        #[cfg(feature = "postgres")]
+        Some(url) if url.starts_with("postgres:") || url.starts_with("postgresql:") => {
+            let store =
+                PostgresTelemetryStore::connect(url, config.database_max_connections).await?;
+            Ok(Storage {
+                devices: Arc::new(store.device_registry()),
+                alert_rules: Arc::new(store.alert_rules()),
+                webhooks: Arc::new(store.webhooks()),
+                telemetry: Arc::new(store),
+            })
+        }
+        #[cfg(feature = "sqlite")]
+        Some(url) if url.starts_with("sqlite:") => {
+            let store = SqliteTelemetryStore::connect(url).await?;
+            Ok(Storage {
+                devices: Arc::new(store.device_registry()),
+                alert_rules: Arc::new(store.alert_rules()),
+                webhooks: Arc::new(store.webhooks()),
+                telemetry: Arc::new(store),
+            })
+        }
+        Some(url) => Err(format!(
+            "No storage backend available for DATABASE_URL '{}'",
+            url
+        )),
+    }
+}
@@ -0,0 +1,851 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::postgres::{PgConnection, PgPool, PgPoolOptions};
+use sqlx::types::Json;
+use sqlx::{FromRow, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use super::{AlertRuleRepository, DeviceRepository, TelemetryRepository, WebhookRepository};
+use crate::models::{
+    bucket_start, AlertRule, BucketStats, Comparator, Device, DeviceStatus, DeviceSummary,
+    MetricRollup, MetricStats, SortOrder, TelemetryCursor, TelemetryData, TelemetryFilter,
+    TelemetryTier, Webhook, WebhookEvent,
+};
+
+/// Maximum rows per multi-row INSERT, keeping bind parameters within limits
+const BATCH_CHUNK_SIZE: usize = 1000;
+
+/// PostgreSQL-backed telemetry store
+pub struct PostgresTelemetryStore {
+    pool: PgPool,
+}
+
+/// Row representation of a telemetry record in the `telemetry` table
+#[derive(FromRow)]
+struct TelemetryRow {
+    id: Uuid,
+    device_id: String,
+    metrics: Json<BTreeMap<String, f64>>,
+    timestamp: DateTime<Utc>,
+    received_at: DateTime<Utc>,
+}
+
+impl From<TelemetryRow> for TelemetryData {
+    fn from(row: TelemetryRow) -> Self {
+        Self {
+            id: row.id,
+            device_id: row.device_id,
+            metrics: row.metrics.0,
+            timestamp: row.timestamp,
+            received_at: row.received_at,
+        }
+    }
+}
+
+/// Row representation of one metric within one aggregation bucket
+#[derive(FromRow)]
+struct BucketRow {
+    bucket: DateTime<Utc>,
+    metric: String,
+    count: i64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    last: f64,
+    last_at: DateTime<Utc>,
+}
+
+/// Fold per-metric rows, ordered by bucket, into one entry per bucket
+fn group_buckets(rows: Vec<BucketRow>) -> Vec<BucketStats> {
+    let mut buckets: Vec<BucketStats> = Vec::new();
+    for row in rows {
+        if buckets.last().map(|b| b.bucket_start) != Some(row.bucket) {
+            buckets.push(BucketStats::new(row.bucket));
+        }
+        if let Some(bucket) = buckets.last_mut() {
+            bucket.metrics.insert(
+                row.metric,
+                MetricStats {
+                    count: row.count as u64,
+                    sum: row.sum,
+                    min: Some(row.min),
+                    max: Some(row.max),
+                    last: Some(row.last),
+                    last_at: Some(row.last_at),
+                },
+            );
+        }
+    }
+    buckets
+}
+
+/// Row representation of one device in a device listing
+#[derive(FromRow)]
+struct DeviceRow {
+    device_id: String,
+    record_count: i64,
+    first_timestamp: DateTime<Utc>,
+    last_timestamp: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+}
+
+impl From<DeviceRow> for DeviceSummary {
+    fn from(row: DeviceRow) -> Self {
+        Self {
+            device_id: row.device_id,
+            record_count: row.record_count as u64,
+            first_timestamp: row.first_timestamp,
+            last_timestamp: row.last_timestamp,
+            last_seen: row.last_seen,
+        }
+    }
+}
+
+/// Row representation of a registered device in the `devices` table
+#[derive(FromRow)]
+struct RegisteredDeviceRow {
+    device_id: String,
+    name: String,
+    device_type: String,
+    location: Option<String>,
+    tags: Vec<String>,
+    status: String,
+    expected_interval_secs: Option<i64>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl TryFrom<RegisteredDeviceRow> for Device {
+    type Error = String;
+
+    fn try_from(row: RegisteredDeviceRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            device_id: row.device_id,
+            name: row.name,
+            device_type: row.device_type,
+            location: row.location,
+            tags: row.tags,
+            status: row.status.parse::<DeviceStatus>()?,
+            expected_interval_secs: row.expected_interval_secs,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+/// Row representation of an alert rule in the `alert_rules` table
+#[derive(FromRow)]
+struct AlertRuleRow {
+    id: Uuid,
+    name: String,
+    device: String,
+    metric: String,
+    op: String,
+    threshold: f64,
+    duration_secs: i64,
+    hysteresis: f64,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl TryFrom<AlertRuleRow> for AlertRule {
+    type Error = String;
+
+    fn try_from(row: AlertRuleRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.id,
+            name: row.name,
+            device: row.device,
+            metric: row.metric,
+            op: row.op.parse::<Comparator>()?,
+            threshold: row.threshold,
+            duration_secs: row.duration_secs,
+            hysteresis: row.hysteresis,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+/// Row representation of a webhook in the `webhooks` table
+#[derive(FromRow)]
+struct WebhookRow {
+    id: Uuid,
+    url: String,
+    secret: String,
+    device: String,
+    events: Vec<String>,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<WebhookRow> for Webhook {
+    type Error = String;
+
+    fn try_from(row: WebhookRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.id,
+            url: row.url,
+            secret: row.secret,
+            device: row.device,
+            events: row
+                .events
+                .iter()
+                .map(|e| e.parse::<WebhookEvent>())
+                .collect::<Result<_, _>>()?,
+            created_at: row.created_at,
+        })
+    }
+}
+
+/// Escape LIKE wildcards so `prefix` only matches literally
+fn like_prefix(prefix: &str) -> String {
+    let mut pattern = String::with_capacity(prefix.len() + 1);
+    for c in prefix.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+/// Fold `rollups` into the stored rollups
+async fn upsert_rollups(conn: &mut PgConnection, rollups: &[MetricRollup]) -> Result<(), String> {
+    for chunk in rollups.chunks(BATCH_CHUNK_SIZE) {
+        let mut query: QueryBuilder<Postgres> = QueryBuilder::new(
+            "INSERT INTO telemetry_rollups \
+             (device_id, tier, bucket_start, metric, count, sum, min, max, last, last_at) ",
+        );
+        query.push_values(chunk, |mut row, r| {
+            row.push_bind(r.device_id.clone())
+                .push_bind(r.tier.as_str())
+                .push_bind(r.bucket_start)
+                .push_bind(r.metric.clone())
+                .push_bind(r.stats.count as i64)
+                .push_bind(r.stats.sum)
+                .push_bind(r.stats.min.unwrap_or_default())
+                .push_bind(r.stats.max.unwrap_or_default())
+                .push_bind(r.stats.last.unwrap_or_default())
+                .push_bind(r.stats.last_at.unwrap_or(r.bucket_start));
+        });
+        query.push(
+            " ON CONFLICT (device_id, tier, bucket_start, metric) DO UPDATE SET \
+             count = telemetry_rollups.count + EXCLUDED.count, \
+             sum = telemetry_rollups.sum + EXCLUDED.sum, \
+             min = LEAST(telemetry_rollups.min, EXCLUDED.min), \
+             max = GREATEST(telemetry_rollups.max, EXCLUDED.max), \
+             last = CASE WHEN EXCLUDED.last_at >= telemetry_rollups.last_at \
+                    THEN EXCLUDED.last ELSE telemetry_rollups.last END, \
+             last_at = GREATEST(telemetry_rollups.last_at, EXCLUDED.last_at)",
+        );
+        query
+            .build()
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Insert every record and fold it into the rollups in a single transaction
+async fn insert_batch(pool: &PgPool, telemetry: Vec<TelemetryData>) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    for chunk in telemetry.chunks(BATCH_CHUNK_SIZE) {
+        let mut query: QueryBuilder<Postgres> = QueryBuilder::new(
+            "INSERT INTO telemetry (id, device_id, metrics, timestamp, received_at) ",
+        );
+        query.push_values(chunk, |mut row, t| {
+            row.push_bind(t.id)
+                .push_bind(t.device_id.clone())
+                .push_bind(Json(t.metrics.clone()))
+                .push_bind(t.timestamp)
+                .push_bind(t.received_at);
+        });
+        query
+            .build()
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    upsert_rollups(&mut tx, &MetricRollup::fold(&telemetry)).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+impl PostgresTelemetryStore {
+    /// Connect to the database at `url` and apply any pending migrations
+    pub async fn connect(url: &str, max_connections: u32) -> Result<Self, String> {
+        let pool = PgPoolOptions::new()
+            .max_connections(max_connections)
+            .connect(url)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Self::from_pool(pool).await
+    }
+
+    /// Create a store from an existing pool, applying any pending migrations
+    pub async fn from_pool(pool: PgPool) -> Result<Self, String> {
+        sqlx::migrate!("./migrations/postgres")
+            .run(&pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self { pool })
+    }
+
+    /// Device registry sharing this store's connection pool
+    pub fn device_registry(&self) -> PostgresDeviceRegistry {
+        PostgresDeviceRegistry {
+            pool: self.pool.clone(),
+        }
+    }
+
+    /// Alert rule store sharing this store's connection pool
+    pub fn alert_rules(&self) -> PostgresAlertRules {
+        PostgresAlertRules {
+            pool: self.pool.clone(),
+        }
+    }
+
+    /// Webhook store sharing this store's connection pool
+    pub fn webhooks(&self) -> PostgresWebhooks {
+        PostgresWebhooks {
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+/// PostgreSQL-backed device registry
+pub struct PostgresDeviceRegistry {
+    pool: PgPool,
+}
+
+/// PostgreSQL-backed alert rule store
+pub struct PostgresAlertRules {
+    pool: PgPool,
+}
+
+/// PostgreSQL-backed webhook store
+pub struct PostgresWebhooks {
+    pool: PgPool,
+}
+
+#[async_trait]
+impl TelemetryRepository for PostgresTelemetryStore {
+    async fn add(&self, telemetry: TelemetryData) -> Result<Uuid, String> {
+        let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
+        sqlx::query(
+            "INSERT INTO telemetry (id, device_id, metrics, timestamp, received_at) \
+             VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(telemetry.id)
+        .bind(&telemetry.device_id)
+        .bind(Json(&telemetry.metrics))
+        .bind(telemetry.timestamp)
+        .bind(telemetry.received_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+        upsert_rollups(&mut tx, &MetricRollup::fold([&telemetry])).await?;
+        tx.commit().await.map_err(|e| e.to_string())?;
+
+        Ok(telemetry.id)
+    }
+
+    async fn add_batch(&self, telemetry: Vec<TelemetryData>) -> Vec<Result<Uuid, String>> {
+        let ids: Vec<Uuid> = telemetry.iter().map(|t| t.id).collect();
+        match insert_batch(&self.pool, telemetry).await {
+            Ok(()) => ids.into_iter().map(Ok).collect(),
+            Err(e) => ids.iter().map(|_| Err(e.clone())).collect(),
+        }
+    }
+
+    async fn get_by_device(
+        &self,
+        device_id: &str,
+        filter: &TelemetryFilter,
+        after: Option<TelemetryCursor>,
+        order: SortOrder,
+        limit: usize,
+    ) -> Result<Vec<TelemetryData>, String> {
+        let (comparison, direction) = match order {
+            SortOrder::Asc => (">", "ASC"),
+            SortOrder::Desc => ("<", "DESC"),
+        };
+
+        // Only emit the range predicates that are actually needed, so the
+        // planner can use the (device_id, timestamp) index for every query
+        let mut query: QueryBuilder<Postgres> = QueryBuilder::new(
+            "SELECT id, device_id, metrics, timestamp, received_at FROM telemetry \
+             WHERE device_id = ",
+        );
+        query.push_bind(device_id);
+        if let Some(start) = filter.start_time {
+            query.push(" AND timestamp >= ").push_bind(start);
+        }
+        if let Some(end) = filter.end_time {
+            query.push(" AND timestamp <= ").push_bind(end);
+        }
+        if let Some(start) = filter.received_start_time {
+            query.push(" AND received_at >= ").push_bind(start);
+        }
+        if let Some(end) = filter.received_end_time {
+            query.push(" AND received_at <= ").push_bind(end);
+        }
+        if let Some(cursor) = after {
+            query
+                .push(format_args!(" AND (timestamp, id) {} (", comparison))
+                .push_bind(cursor.timestamp)
+                .push(", ")
+                .push_bind(cursor.id)
+                .push(")");
+        }
+        query
+            .push(format_args!(
+                " ORDER BY timestamp {0}, id {0} LIMIT ",
+                direction
+            ))
+            .push_bind(i64::try_from(limit).unwrap_or(i64::MAX));
+
+        let rows: Vec<TelemetryRow> = query
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(rows.into_iter().map(TelemetryData::from).collect())
+    }
+
+    async fn received_after(
+        &self,
+        device_id: Option<&str>,
+        after: TelemetryCursor,
+        limit: usize,
+    ) -> Result<Vec<TelemetryData>, String> {
+        let mut query: QueryBuilder<Postgres> = QueryBuilder::new(
+            "SELECT id, device_id, metrics, timestamp, received_at FROM telemetry \
+             WHERE (received_at, id) > (",
+        );
+        query
+            .push_bind(after.timestamp)
+            .push(", ")
+            .push_bind(after.id)
+            .push(")");
+        if let Some(device_id) = device_id {
+            query.push(" AND device_id = ").push_bind(device_id);
+        }
+        query
+            .push(" ORDER BY received_at, id LIMIT ")
+            .push_bind(i64::try_from(limit).unwrap_or(i64::MAX));
+
+        let rows: Vec<TelemetryRow> = query
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(rows.into_iter().map(TelemetryData::from).collect())
+    }
+
+    async fn aggregate(
+        &self,
+        device_id: &str,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        bucket: Duration,
+        metrics: &[String],
+    ) -> Result<Option<Vec<BucketStats>>, String> {
+        let width = bucket.num_seconds() as f64;
+
+        // The newest value per bucket and metric is ranked first by the window
+        let mut query: QueryBuilder<Postgres> = QueryBuilder::new(
+            "SELECT bucket, metric, COUNT(*) AS count, SUM(value) AS sum, \
+                    MIN(value) AS min, MAX(value) AS max, \
+                    MAX(CASE WHEN latest = 1 THEN value END) AS last, MAX(timestamp) AS last_at \
+             FROM (SELECT bucket, metric, value, timestamp, ROW_NUMBER() OVER ( \
+                       PARTITION BY bucket, metric ORDER BY timestamp DESC, id DESC) AS latest \
+                   FROM (SELECT to_timestamp(floor(extract(epoch FROM t.timestamp)::float8 / ",
+        );
+        query.push_bind(width).push(") * ").push_bind(width).push(
+            ") AS bucket, m.key AS metric, m.value::float8 AS value, t.timestamp, t.id \
+             FROM telemetry AS t CROSS JOIN LATERAL jsonb_each_text(t.metrics) AS m \
+             WHERE t.device_id = ",
+        );
+        query.push_bind(device_id);
+        if let Some(start) = start_time {
+            query.push(" AND t.timestamp >= ").push_bind(start);
+        }
+        if let Some(end) = end_time {
+            query.push(" AND t.timestamp <= ").push_bind(end);
+        }
+        if !metrics.is_empty() {
+            query.push(" AND m.key = ANY(").push_bind(metrics).push(")");
+        }
+        query.push(") AS readings) AS ranked GROUP BY 1, 2 ORDER BY 1, 2");
+
+        let rows: Vec<BucketRow> = query
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(Some(group_buckets(rows)))
+    }
+
+    async fn list_devices(
+        &self,
+        prefix: Option<&str>,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<DeviceSummary>, String> {
+        let mut query: QueryBuilder<Postgres> = QueryBuilder::new(
+            "SELECT device_id, COUNT(*) AS record_count, \
+                    MIN(timestamp) AS first_timestamp, MAX(timestamp) AS last_timestamp, \
+                    MAX(received_at) AS last_seen \
+             FROM telemetry WHERE TRUE",
+        );
+        if let Some(prefix) = prefix {
+            query
+                .push(" AND device_id LIKE ")
+                .push_bind(like_prefix(prefix))
+                .push(" ESCAPE '\\'");
+        }
+        // Byte-wise collation keeps paging order identical to the other backends
+        if let Some(after) = after {
+            query
+                .push(" AND device_id COLLATE \"C\" > ")
+                .push_bind(after);
+        }
+        query
+            .push(" GROUP BY device_id ORDER BY device_id COLLATE \"C\" LIMIT ")
+            .push_bind(i64::try_from(limit).unwrap_or(i64::MAX));
+
+        let rows: Vec<DeviceRow> = query
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(rows.into_iter().map(DeviceSummary::from).collect())
+    }
+
+    async fn get_by_id(&self, id: Uuid) -> Result<Option<TelemetryData>, String> {
+        let row: Option<TelemetryRow> = sqlx::query_as(
+            "SELECT id, device_id, metrics, timestamp, received_at FROM telemetry WHERE id = $1",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(row.map(TelemetryData::from))
+    }
+
+    async fn delete_old_records(
+        &self,
+        device_id: &str,
+        older_than: DateTime<Utc>,
+    ) -> Result<usize, String> {
+        // A single set-based DELETE removes every matching row in one round trip
+        let result = sqlx::query("DELETE FROM telemetry WHERE device_id = $1 AND timestamp < $2")
+            .bind(device_id)
+            .bind(older_than)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() as usize)
+    }
+
+    async fn rollups(
+        &self,
+        device_id: &str,
+        tier: TelemetryTier,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        metrics: &[String],
+    ) -> Result<Vec<BucketStats>, String> {
+        let Some(width) = tier.width() else {
+            return Ok(Vec::new());
+        };
+
+        let mut query: QueryBuilder<Postgres> = QueryBuilder::new(
+            "SELECT bucket_start AS bucket, metric, count, sum, min, max, last, last_at \
+             FROM telemetry_rollups WHERE device_id = ",
+        );
+        query
+            .push_bind(device_id)
+            .push(" AND tier = ")
+            .push_bind(tier.as_str());
+        if let Some(start) = start_time {
+            query
+                .push(" AND bucket_start >= ")
+                .push_bind(bucket_start(start, width));
+        }
+        if let Some(end) = end_time {
+            query.push(" AND bucket_start <= ").push_bind(end);
+        }
+        if !metrics.is_empty() {
+            query
+                .push(" AND metric = ANY(")
+                .push_bind(metrics)
+                .push(")");
+        }
+        query.push(" ORDER BY bucket_start, metric");
+
+        let rows: Vec<BucketRow> = query
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(group_buckets(rows))
+    }
+
+    async fn delete_old_rollups(
+        &self,
+        tier: TelemetryTier,
+        older_than: DateTime<Utc>,
+    ) -> Result<usize, String> {
+        let result =
+            sqlx::query("DELETE FROM telemetry_rollups WHERE tier = $1 AND bucket_start < $2")
+                .bind(tier.as_str())
+                .bind(older_than)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() as usize)
+    }
+}
+
+#[async_trait]
+impl DeviceRepository for PostgresDeviceRegistry {
+    async fn insert(&self, device: Device) -> Result<bool, String> {
+        let result = sqlx::query(
+            "INSERT INTO devices \
+                 (device_id, name, device_type, location, tags, status, \
+                  expected_interval_secs, created_at, updated_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) \
+             ON CONFLICT (device_id) DO NOTHING",
+        )
+        .bind(&device.device_id)
+        .bind(&device.name)
+        .bind(&device.device_type)
+        .bind(&device.location)
+        .bind(&device.tags)
+        .bind(device.status.as_str())
+        .bind(device.expected_interval_secs)
+        .bind(device.created_at)
+        .bind(device.updated_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get(&self, device_id: &str) -> Result<Option<Device>, String> {
+        let row: Option<RegisteredDeviceRow> = sqlx::query_as(
+            "SELECT device_id, name, device_type, location, tags, status, \
+                 expected_interval_secs, created_at, updated_at \
+             FROM devices WHERE device_id = $1",
+        )
+        .bind(device_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        row.map(Device::try_from).transpose()
+    }
+
+    async fn list(
+        &self,
+        prefix: Option<&str>,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<Device>, String> {
+        let mut query: QueryBuilder<Postgres> = QueryBuilder::new(
+            "SELECT device_id, name, device_type, location, tags, status, \
+                 expected_interval_secs, created_at, updated_at \
+             FROM devices WHERE TRUE",
+        );
+        if let Some(prefix) = prefix {
+            query
+                .push(" AND device_id LIKE ")
+                .push_bind(like_prefix(prefix))
+                .push(" ESCAPE '\\'");
+        }
+        // Byte-wise collation keeps paging order identical to the other backends
+        if let Some(after) = after {
+            query
+                .push(" AND device_id COLLATE \"C\" > ")
+                .push_bind(after);
+        }
+        query
+            .push(" ORDER BY device_id COLLATE \"C\" LIMIT ")
+            .push_bind(i64::try_from(limit).unwrap_or(i64::MAX));
+
+        let rows: Vec<RegisteredDeviceRow> = query
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        rows.into_iter().map(Device::try_from).collect()
+    }
+
+    async fn update(&self, device: Device) -> Result<bool, String> {
+        let result = sqlx::query(
+            "UPDATE devices \
+             SET name = $1, device_type = $2, location = $3, tags = $4, status = $5, \
+                 expected_interval_secs = $6, updated_at = $7 \
+             WHERE device_id = $8",
+        )
+        .bind(&device.name)
+        .bind(&device.device_type)
+        .bind(&device.location)
+        .bind(&device.tags)
+        .bind(device.status.as_str())
+        .bind(device.expected_interval_secs)
+        .bind(device.updated_at)
+        .bind(&device.device_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete(&self, device_id: &str) -> Result<bool, String> {
+        let result = sqlx::query("DELETE FROM devices WHERE device_id = $1")
+            .bind(device_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[async_trait]
+impl AlertRuleRepository for PostgresAlertRules {
+    async fn insert(&self, rule: AlertRule) -> Result<(), String> {
+        sqlx::query(
+            "INSERT INTO alert_rules \
+                 (id, name, device, metric, op, threshold, duration_secs, hysteresis, \
+                  created_at, updated_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+        )
+        .bind(rule.id)
+        .bind(&rule.name)
+        .bind(&rule.device)
+        .bind(&rule.metric)
+        .bind(rule.op.as_str())
+        .bind(rule.threshold)
+        .bind(rule.duration_secs)
+        .bind(rule.hysteresis)
+        .bind(rule.created_at)
+        .bind(rule.updated_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<AlertRule>, String> {
+        let rows: Vec<AlertRuleRow> = sqlx::query_as(
+            "SELECT id, name, device, metric, op, threshold, duration_secs, hysteresis, \
+                    created_at, updated_at \
+             FROM alert_rules ORDER BY created_at, id",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        rows.into_iter().map(AlertRule::try_from).collect()
+    }
+
+    async fn update(&self, rule: AlertRule) -> Result<bool, String> {
+        let result = sqlx::query(
+            "UPDATE alert_rules \
+             SET name = $1, device = $2, metric = $3, op = $4, threshold = $5, \
+                 duration_secs = $6, hysteresis = $7, updated_at = $8 \
+             WHERE id = $9",
+        )
+        .bind(&rule.name)
+        .bind(&rule.device)
+        .bind(&rule.metric)
+        .bind(rule.op.as_str())
+        .bind(rule.threshold)
+        .bind(rule.duration_secs)
+        .bind(rule.hysteresis)
+        .bind(rule.updated_at)
+        .bind(rule.id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query("DELETE FROM alert_rules WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[async_trait]
+impl WebhookRepository for PostgresWebhooks {
+    async fn insert(&self, webhook: Webhook) -> Result<(), String> {
+        let events: Vec<&str> = webhook.events.iter().map(|e| e.as_str()).collect();
+        sqlx::query(
+            "INSERT INTO webhooks (id, url, secret, device, events, created_at) \
+             VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(webhook.id)
+        .bind(&webhook.url)
+        .bind(&webhook.secret)
+        .bind(&webhook.device)
+        .bind(&events)
+        .bind(webhook.created_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<Webhook>, String> {
+        let rows: Vec<WebhookRow> = sqlx::query_as(
+            "SELECT id, url, secret, device, events, created_at \
+             FROM webhooks ORDER BY created_at, id",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        rows.into_iter().map(Webhook::try_from).collect()
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query("DELETE FROM webhooks WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,899 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::types::Json;
+use sqlx::{FromRow, QueryBuilder, Sqlite, SqliteConnection};
+use uuid::Uuid;
+
+use super::{AlertRuleRepository, DeviceRepository, TelemetryRepository, WebhookRepository};
+use crate::models::{
+    bucket_start, AlertRule, BucketStats, Comparator, Device, DeviceStatus, DeviceSummary,
+    MetricRollup, MetricStats, SortOrder, TelemetryCursor, TelemetryData, TelemetryFilter,
+    TelemetryTier, Webhook, WebhookEvent,
+};
+
+/// Maximum rows per multi-row INSERT, keeping bind parameters within limits
+const BATCH_CHUNK_SIZE: usize = 1000;
+
+/// SQLite-backed telemetry store
+pub struct SqliteTelemetryStore {
+    pool: SqlitePool,
+}
+
+/// Row representation of a telemetry record in the `telemetry` table
+#[derive(FromRow)]
+struct TelemetryRow {
+    id: Uuid,
+    device_id: String,
+    metrics: Json<BTreeMap<String, f64>>,
+    timestamp: i64,
+    received_at: i64,
+}
+
+impl From<TelemetryRow> for TelemetryData {
+    fn from(row: TelemetryRow) -> Self {
+        Self {
+            id: row.id,
+            device_id: row.device_id,
+            metrics: row.metrics.0,
+            timestamp: DateTime::from_timestamp_nanos(row.timestamp),
+            received_at: DateTime::from_timestamp_nanos(row.received_at),
+        }
+    }
+}
+
+/// Row representation of one metric within one aggregation bucket
+#[derive(FromRow)]
+struct BucketRow {
+    bucket: i64,
+    metric: String,
+    count: i64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    last: f64,
+    last_at: i64,
+}
+
+/// Fold per-metric rows, ordered by bucket, into one entry per bucket
+fn group_buckets(rows: Vec<BucketRow>) -> Vec<BucketStats> {
+    let mut buckets: Vec<BucketStats> = Vec::new();
+    for row in rows {
+        let start = DateTime::from_timestamp_nanos(row.bucket);
+        if buckets.last().map(|b| b.bucket_start) != Some(start) {
+            buckets.push(BucketStats::new(start));
+        }
+        if let Some(bucket) = buckets.last_mut() {
+            bucket.metrics.insert(
+                row.metric,
+                MetricStats {
+                    count: row.count as u64,
+                    sum: row.sum,
+                    min: Some(row.min),
+                    max: Some(row.max),
+                    last: Some(row.last),
+                    last_at: Some(DateTime::from_timestamp_nanos(row.last_at)),
+                },
+            );
+        }
+    }
+    buckets
+}
+
+/// Row representation of one device in a device listing
+#[derive(FromRow)]
+struct DeviceRow {
+    device_id: String,
+    record_count: i64,
+    first_timestamp: i64,
+    last_timestamp: i64,
+    last_seen: i64,
+}
+
+impl From<DeviceRow> for DeviceSummary {
+    fn from(row: DeviceRow) -> Self {
+        Self {
+            device_id: row.device_id,
+            record_count: row.record_count as u64,
+            first_timestamp: DateTime::from_timestamp_nanos(row.first_timestamp),
+            last_timestamp: DateTime::from_timestamp_nanos(row.last_timestamp),
+            last_seen: DateTime::from_timestamp_nanos(row.last_seen),
+        }
+    }
+}
+
+/// Row representation of a registered device in the `devices` table
+#[derive(FromRow)]
+struct RegisteredDeviceRow {
+    device_id: String,
+    name: String,
+    device_type: String,
+    location: Option<String>,
+    tags: String,
+    status: String,
+    expected_interval_secs: Option<i64>,
+    created_at: i64,
+    updated_at: i64,
+}
+
+impl TryFrom<RegisteredDeviceRow> for Device {
+    type Error = String;
+
+    fn try_from(row: RegisteredDeviceRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            device_id: row.device_id,
+            name: row.name,
+            device_type: row.device_type,
+            location: row.location,
+            tags: serde_json::from_str(&row.tags).map_err(|e| e.to_string())?,
+            status: row.status.parse::<DeviceStatus>()?,
+            expected_interval_secs: row.expected_interval_secs,
+            created_at: DateTime::from_timestamp_nanos(row.created_at),
+            updated_at: DateTime::from_timestamp_nanos(row.updated_at),
+        })
+    }
+}
+
+/// Row representation of an alert rule in the `alert_rules` table
+#[derive(FromRow)]
+struct AlertRuleRow {
+    id: Uuid,
+    name: String,
+    device: String,
+    metric: String,
+    op: String,
+    threshold: f64,
+    duration_secs: i64,
+    hysteresis: f64,
+    created_at: i64,
+    updated_at: i64,
+}
+
+impl TryFrom<AlertRuleRow> for AlertRule {
+    type Error = String;
+
+    fn try_from(row: AlertRuleRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.id,
+            name: row.name,
+            device: row.device,
+            metric: row.metric,
+            op: row.op.parse::<Comparator>()?,
+            threshold: row.threshold,
+            duration_secs: row.duration_secs,
+            hysteresis: row.hysteresis,
+            created_at: DateTime::from_timestamp_nanos(row.created_at),
+            updated_at: DateTime::from_timestamp_nanos(row.updated_at),
+        })
+    }
+}
+
+/// Row representation of a webhook in the `webhooks` table
+#[derive(FromRow)]
+struct WebhookRow {
+    id: Uuid,
+    url: String,
+    secret: String,
+    device: String,
+    events: String,
+    created_at: i64,
+}
+
+impl TryFrom<WebhookRow> for Webhook {
+    type Error = String;
+
+    fn try_from(row: WebhookRow) -> Result<Self, Self::Error> {
+        let events: Vec<String> = serde_json::from_str(&row.events).map_err(|e| e.to_string())?;
+        Ok(Self {
+            id: row.id,
+            url: row.url,
+            secret: row.secret,
+            device: row.device,
+            events: events
+                .iter()
+                .map(|e| e.parse::<WebhookEvent>())
+                .collect::<Result<_, _>>()?,
+            created_at: DateTime::from_timestamp_nanos(row.created_at),
+        })
+    }
+}
+
+/// Convert a timestamp to the integer representation stored in SQLite
+fn to_nanos(timestamp: DateTime<Utc>) -> Result<i64, String> {
+    timestamp
+        .timestamp_nanos_opt()
+        .ok_or_else(|| format!("Timestamp {} is out of range for storage", timestamp))
+}
+
+/// Fold `rollups` into the stored rollups
+async fn upsert_rollups(
+    conn: &mut SqliteConnection,
+    rollups: &[MetricRollup],
+) -> Result<(), String> {
+    let rows = rollups
+        .iter()
+        .map(|r| {
+            let last_at = r.stats.last_at.unwrap_or(r.bucket_start);
+            Ok((to_nanos(r.bucket_start)?, to_nanos(last_at)?, r))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    for chunk in rows.chunks(BATCH_CHUNK_SIZE) {
+        let mut query: QueryBuilder<Sqlite> = QueryBuilder::new(
+            "INSERT INTO telemetry_rollups \
+             (device_id, tier, bucket_start, metric, count, sum, min, max, last, last_at) ",
+        );
+        query.push_values(chunk, |mut row, (bucket_start, last_at, r)| {
+            row.push_bind(r.device_id.clone())
+                .push_bind(r.tier.as_str())
+                .push_bind(*bucket_start)
+                .push_bind(r.metric.clone())
+                .push_bind(r.stats.count as i64)
+                .push_bind(r.stats.sum)
+                .push_bind(r.stats.min.unwrap_or_default())
+                .push_bind(r.stats.max.unwrap_or_default())
+                .push_bind(r.stats.last.unwrap_or_default())
+                .push_bind(*last_at);
+        });
+        query.push(
+            " ON CONFLICT (device_id, tier, bucket_start, metric) DO UPDATE SET \
+             count = telemetry_rollups.count + excluded.count, \
+             sum = telemetry_rollups.sum + excluded.sum, \
+             min = min(telemetry_rollups.min, excluded.min), \
+             max = max(telemetry_rollups.max, excluded.max), \
+             last = CASE WHEN excluded.last_at >= telemetry_rollups.last_at \
+                    THEN excluded.last ELSE telemetry_rollups.last END, \
+             last_at = max(telemetry_rollups.last_at, excluded.last_at)",
+        );
+        query
+            .build()
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Insert every record and fold it into the rollups in a single transaction
+async fn insert_batch(pool: &SqlitePool, telemetry: Vec<TelemetryData>) -> Result<(), String> {
+    let rollups = MetricRollup::fold(&telemetry);
+    let rows = telemetry
+        .into_iter()
+        .map(|t| Ok((to_nanos(t.timestamp)?, to_nanos(t.received_at)?, t)))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    for chunk in rows.chunks(BATCH_CHUNK_SIZE) {
+        let mut query: QueryBuilder<Sqlite> = QueryBuilder::new(
+            "INSERT INTO telemetry (id, device_id, metrics, timestamp, received_at) ",
+        );
+        query.push_values(chunk, |mut row, (timestamp, received_at, t)| {
+            row.push_bind(t.id)
+                .push_bind(t.device_id.clone())
+                .push_bind(Json(t.metrics.clone()))
+                .push_bind(*timestamp)
+                .push_bind(*received_at);
+        });
+        query
+            .build()
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    upsert_rollups(&mut tx, &rollups).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+impl SqliteTelemetryStore {
+    /// Connect to the database at `url` and apply any pending migrations
+    pub async fn connect(url: &str) -> Result<Self, String> {
+        let options = SqliteConnectOptions::from_str(url)
+            .map_err(|e| e.to_string())?
+            .create_if_missing(true);
+
+        // Every connection to an in-memory database opens a fresh, empty one,
+        // so keep a single connection alive for the lifetime of the pool
+        let pool_options = if url.contains(":memory:") {
+            SqlitePoolOptions::new()
+                .max_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None)
+        } else {
+            SqlitePoolOptions::new()
+        };
+
+        let pool = pool_options
+            .connect_with(options)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Self::from_pool(pool).await
+    }
+
+    /// Create a store from an existing pool, applying any pending migrations
+    pub async fn from_pool(pool: SqlitePool) -> Result<Self, String> {
+        sqlx::migrate!("./migrations/sqlite")
+            .run(&pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self { pool })
+    }
+
+    /// Device registry sharing this store's connection pool
+    pub fn device_registry(&self) -> SqliteDeviceRegistry {
+        SqliteDeviceRegistry {
+            pool: self.pool.clone(),
+        }
+    }
+
+    /// Alert rule store sharing this store's connection pool
+    pub fn alert_rules(&self) -> SqliteAlertRules {
+        SqliteAlertRules {
+            pool: self.pool.clone(),
+        }
+    }
+
+    /// Webhook store sharing this store's connection pool
+    pub fn webhooks(&self) -> SqliteWebhooks {
+        SqliteWebhooks {
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+/// SQLite-backed device registry
+pub struct SqliteDeviceRegistry {
+    pool: SqlitePool,
+}
+
+/// SQLite-backed alert rule store
+pub struct SqliteAlertRules {
+    pool: SqlitePool,
+}
+
+/// SQLite-backed webhook store
+pub struct SqliteWebhooks {
+    pool: SqlitePool,
+}
+
+#[async_trait]
+impl TelemetryRepository for SqliteTelemetryStore {
+    async fn add(&self, telemetry: TelemetryData) -> Result<Uuid, String> {
+        let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
+        sqlx::query(
+            "INSERT INTO telemetry (id, device_id, metrics, timestamp, received_at) \
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(telemetry.id)
+        .bind(&telemetry.device_id)
+        .bind(Json(&telemetry.metrics))
+        .bind(to_nanos(telemetry.timestamp)?)
+        .bind(to_nanos(telemetry.received_at)?)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+        upsert_rollups(&mut tx, &MetricRollup::fold([&telemetry])).await?;
+        tx.commit().await.map_err(|e| e.to_string())?;
+
+        Ok(telemetry.id)
+    }
+
+    async fn add_batch(&self, telemetry: Vec<TelemetryData>) -> Vec<Result<Uuid, String>> {
+        let ids: Vec<Uuid> = telemetry.iter().map(|t| t.id).collect();
+        match insert_batch(&self.pool, telemetry).await {
+            Ok(()) => ids.into_iter().map(Ok).collect(),
+            Err(e) => ids.iter().map(|_| Err(e.clone())).collect(),
+        }
+    }
+
+    async fn get_by_device(
+        &self,
+        device_id: &str,
+        filter: &TelemetryFilter,
+        after: Option<TelemetryCursor>,
+        order: SortOrder,
+        limit: usize,
+    ) -> Result<Vec<TelemetryData>, String> {
+        let (comparison, direction) = match order {
+            SortOrder::Asc => (">", "ASC"),
+            SortOrder::Desc => ("<", "DESC"),
+        };
+
+        let mut query: QueryBuilder<Sqlite> = QueryBuilder::new(
+            "SELECT id, device_id, metrics, timestamp, received_at FROM telemetry \
+             WHERE device_id = ",
+        );
+        query.push_bind(device_id);
+        if let Some(start) = filter.start_time {
+            query.push(" AND timestamp >= ").push_bind(to_nanos(start)?);
+        }
+        if let Some(end) = filter.end_time {
+            query.push(" AND timestamp <= ").push_bind(to_nanos(end)?);
+        }
+        if let Some(start) = filter.received_start_time {
+            query
+                .push(" AND received_at >= ")
+                .push_bind(to_nanos(start)?);
+        }
+        if let Some(end) = filter.received_end_time {
+            query.push(" AND received_at <= ").push_bind(to_nanos(end)?);
+        }
+        if let Some(cursor) = after {
+            query
+                .push(format_args!(" AND (timestamp, id) {} (", comparison))
+                .push_bind(to_nanos(cursor.timestamp)?)
+                .push(", ")
+                .push_bind(cursor.id)
+                .push(")");
+        }
+        query
+            .push(format_args!(
+                " ORDER BY timestamp {0}, id {0} LIMIT ",
+                direction
+            ))
+            .push_bind(i64::try_from(limit).unwrap_or(i64::MAX));
+
+        let rows: Vec<TelemetryRow> = query
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(rows.into_iter().map(TelemetryData::from).collect())
+    }
+
+    async fn received_after(
+        &self,
+        device_id: Option<&str>,
+        after: TelemetryCursor,
+        limit: usize,
+    ) -> Result<Vec<TelemetryData>, String> {
+        let mut query: QueryBuilder<Sqlite> = QueryBuilder::new(
+            "SELECT id, device_id, metrics, timestamp, received_at FROM telemetry \
+             WHERE (received_at, id) > (",
+        );
+        query
+            .push_bind(to_nanos(after.timestamp)?)
+            .push(", ")
+            .push_bind(after.id)
+            .push(")");
+        if let Some(device_id) = device_id {
+            query.push(" AND device_id = ").push_bind(device_id);
+        }
+        query
+            .push(" ORDER BY received_at, id LIMIT ")
+            .push_bind(i64::try_from(limit).unwrap_or(i64::MAX));
+
+        let rows: Vec<TelemetryRow> = query
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(rows.into_iter().map(TelemetryData::from).collect())
+    }
+
+    async fn aggregate(
+        &self,
+        device_id: &str,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        bucket: Duration,
+        metrics: &[String],
+    ) -> Result<Option<Vec<BucketStats>>, String> {
+        let width = bucket
+            .num_nanoseconds()
+            .ok_or_else(|| "Bucket width is out of range".to_string())?;
+
+        // The newest value per bucket and metric is ranked first by the window.
+        // The double modulo floors pre-epoch timestamps the same way as post-epoch ones
+        let mut query: QueryBuilder<Sqlite> = QueryBuilder::new(
+            "SELECT bucket, metric, COUNT(*) AS count, SUM(value) AS sum, \
+                    MIN(value) AS min, MAX(value) AS max, \
+                    MAX(CASE WHEN latest = 1 THEN value END) AS last, MAX(timestamp) AS last_at \
+             FROM (SELECT bucket, metric, value, timestamp, ROW_NUMBER() OVER ( \
+                       PARTITION BY bucket, metric ORDER BY timestamp DESC, id DESC) AS latest \
+                   FROM (SELECT t.timestamp - (((t.timestamp % ",
+        );
+        query
+            .push_bind(width)
+            .push(") + ")
+            .push_bind(width)
+            .push(") % ")
+            .push_bind(width)
+            .push(
+                ") AS bucket, m.key AS metric, CAST(m.value AS REAL) AS value, \
+                 t.timestamp, t.id \
+                 FROM telemetry AS t, json_each(t.metrics) AS m \
+                 WHERE t.device_id = ",
+            )
+            .push_bind(device_id);
+        if let Some(start) = start_time {
+            query
+                .push(" AND t.timestamp >= ")
+                .push_bind(to_nanos(start)?);
+        }
+        if let Some(end) = end_time {
+            query.push(" AND t.timestamp <= ").push_bind(to_nanos(end)?);
+        }
+        if !metrics.is_empty() {
+            query.push(" AND m.key IN (");
+            let mut names = query.separated(", ");
+            for name in metrics {
+                names.push_bind(name.as_str());
+            }
+            query.push(")");
+        }
+        query.push(")) GROUP BY bucket, metric ORDER BY bucket, metric");
+
+        let rows: Vec<BucketRow> = query
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(Some(group_buckets(rows)))
+    }
+
+    async fn list_devices(
+        &self,
+        prefix: Option<&str>,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<DeviceSummary>, String> {
+        let mut query: QueryBuilder<Sqlite> = QueryBuilder::new(
+            "SELECT device_id, COUNT(*) AS record_count, \
+                    MIN(timestamp) AS first_timestamp, MAX(timestamp) AS last_timestamp, \
+                    MAX(received_at) AS last_seen \
+             FROM telemetry WHERE 1 = 1",
+        );
+        // LIKE is case-insensitive in SQLite, so compare the prefix exactly
+        if let Some(prefix) = prefix {
+            query
+                .push(" AND substr(device_id, 1, length(")
+                .push_bind(prefix)
+                .push(")) = ")
+                .push_bind(prefix);
+        }
+        if let Some(after) = after {
+            query.push(" AND device_id > ").push_bind(after);
+        }
+        query
+            .push(" GROUP BY device_id ORDER BY device_id LIMIT ")
+            .push_bind(i64::try_from(limit).unwrap_or(i64::MAX));
+
+        let rows: Vec<DeviceRow> = query
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(rows.into_iter().map(DeviceSummary::from).collect())
+    }
+
+    async fn get_by_id(&self, id: Uuid) -> Result<Option<TelemetryData>, String> {
+        let row: Option<TelemetryRow> = sqlx::query_as(
+            "SELECT id, device_id, metrics, timestamp, received_at FROM telemetry WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(row.map(TelemetryData::from))
+    }
+
+    async fn delete_old_records(
+        &self,
+        device_id: &str,
+        older_than: DateTime<Utc>,
+    ) -> Result<usize, String> {
+        let result = sqlx::query("DELETE FROM telemetry WHERE device_id = ? AND timestamp < ?")
+            .bind(device_id)
+            .bind(to_nanos(older_than)?)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() as usize)
+    }
+
+    async fn rollups(
+        &self,
+        device_id: &str,
+        tier: TelemetryTier,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        metrics: &[String],
+    ) -> Result<Vec<BucketStats>, String> {
+        let Some(width) = tier.width() else {
+            return Ok(Vec::new());
+        };
+
+        let mut query: QueryBuilder<Sqlite> = QueryBuilder::new(
+            "SELECT bucket_start AS bucket, metric, count, sum, min, max, last, last_at \
+             FROM telemetry_rollups WHERE device_id = ",
+        );
+        query
+            .push_bind(device_id)
+            .push(" AND tier = ")
+            .push_bind(tier.as_str());
+        if let Some(start) = start_time {
+            query
+                .push(" AND bucket_start >= ")
+                .push_bind(to_nanos(bucket_start(start, width))?);
+        }
+        if let Some(end) = end_time {
+            query
+                .push(" AND bucket_start <= ")
+                .push_bind(to_nanos(end)?);
+        }
+        if !metrics.is_empty() {
+            query.push(" AND metric IN (");
+            let mut names = query.separated(", ");
+            for name in metrics {
+                names.push_bind(name.as_str());
+            }
+            query.push(")");
+        }
+        query.push(" ORDER BY bucket_start, metric");
+
+        let rows: Vec<BucketRow> = query
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(group_buckets(rows))
+    }
+
+    async fn delete_old_rollups(
+        &self,
+        tier: TelemetryTier,
+        older_than: DateTime<Utc>,
+    ) -> Result<usize, String> {
+        let result =
+            sqlx::query("DELETE FROM telemetry_rollups WHERE tier = ? AND bucket_start < ?")
+                .bind(tier.as_str())
+                .bind(to_nanos(older_than)?)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() as usize)
+    }
+}
+
+#[async_trait]
+impl DeviceRepository for SqliteDeviceRegistry {
+    async fn insert(&self, device: Device) -> Result<bool, String> {
+        let tags = serde_json::to_string(&device.tags).map_err(|e| e.to_string())?;
+        let result = sqlx::query(
+            "INSERT INTO devices \
+                 (device_id, name, device_type, location, tags, status, \
+                  expected_interval_secs, created_at, updated_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?) \
+             ON CONFLICT (device_id) DO NOTHING",
+        )
+        .bind(&device.device_id)
+        .bind(&device.name)
+        .bind(&device.device_type)
+        .bind(&device.location)
+        .bind(tags)
+        .bind(device.status.as_str())
+        .bind(device.expected_interval_secs)
+        .bind(to_nanos(device.created_at)?)
+        .bind(to_nanos(device.updated_at)?)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get(&self, device_id: &str) -> Result<Option<Device>, String> {
+        let row: Option<RegisteredDeviceRow> = sqlx::query_as(
+            "SELECT device_id, name, device_type, location, tags, status, \
+                 expected_interval_secs, created_at, updated_at \
+             FROM devices WHERE device_id = ?",
+        )
+        .bind(device_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        row.map(Device::try_from).transpose()
+    }
+
+    async fn list(
+        &self,
+        prefix: Option<&str>,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<Device>, String> {
+        let mut query: QueryBuilder<Sqlite> = QueryBuilder::new(
+            "SELECT device_id, name, device_type, location, tags, status, \
+                 expected_interval_secs, created_at, updated_at \
+             FROM devices WHERE 1 = 1",
+        );
+        // LIKE is case-insensitive in SQLite, so compare the prefix exactly
+        if let Some(prefix) = prefix {
+            query
+                .push(" AND substr(device_id, 1, length(")
+                .push_bind(prefix)
+                .push(")) = ")
+                .push_bind(prefix);
+        }
+        if let Some(after) = after {
+            query.push(" AND device_id > ").push_bind(after);
+        }
+        query
+            .push(" ORDER BY device_id LIMIT ")
+            .push_bind(i64::try_from(limit).unwrap_or(i64::MAX));
+
+        let rows: Vec<RegisteredDeviceRow> = query
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        rows.into_iter().map(Device::try_from).collect()
+    }
+
+    async fn update(&self, device: Device) -> Result<bool, String> {
+        let tags = serde_json::to_string(&device.tags).map_err(|e| e.to_string())?;
+        let result = sqlx::query(
+            "UPDATE devices \
+             SET name = ?, device_type = ?, location = ?, tags = ?, status = ?, \
+                 expected_interval_secs = ?, updated_at = ? \
+             WHERE device_id = ?",
+        )
+        .bind(&device.name)
+        .bind(&device.device_type)
+        .bind(&device.location)
+        .bind(tags)
+        .bind(device.status.as_str())
+        .bind(device.expected_interval_secs)
+        .bind(to_nanos(device.updated_at)?)
+        .bind(&device.device_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete(&self, device_id: &str) -> Result<bool, String> {
+        let result = sqlx::query("DELETE FROM devices WHERE device_id = ?")
+            .bind(device_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[async_trait]
+impl AlertRuleRepository for SqliteAlertRules {
+    async fn insert(&self, rule: AlertRule) -> Result<(), String> {
+        sqlx::query(
+            "INSERT INTO alert_rules \
+                 (id, name, device, metric, op, threshold, duration_secs, hysteresis, \
+                  created_at, updated_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(rule.id)
+        .bind(&rule.name)
+        .bind(&rule.device)
+        .bind(&rule.metric)
+        .bind(rule.op.as_str())
+        .bind(rule.threshold)
+        .bind(rule.duration_secs)
+        .bind(rule.hysteresis)
+        .bind(to_nanos(rule.created_at)?)
+        .bind(to_nanos(rule.updated_at)?)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<AlertRule>, String> {
+        let rows: Vec<AlertRuleRow> = sqlx::query_as(
+            "SELECT id, name, device, metric, op, threshold, duration_secs, hysteresis, \
+                    created_at, updated_at \
+             FROM alert_rules ORDER BY created_at, id",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        rows.into_iter().map(AlertRule::try_from).collect()
+    }
+
+    async fn update(&self, rule: AlertRule) -> Result<bool, String> {
+        let result = sqlx::query(
+            "UPDATE alert_rules \
+             SET name = ?, device = ?, metric = ?, op = ?, threshold = ?, \
+                 duration_secs = ?, hysteresis = ?, updated_at = ? \
+             WHERE id = ?",
+        )
+        .bind(&rule.name)
+        .bind(&rule.device)
+        .bind(&rule.metric)
+        .bind(rule.op.as_str())
+        .bind(rule.threshold)
+        .bind(rule.duration_secs)
+        .bind(rule.hysteresis)
+        .bind(to_nanos(rule.updated_at)?)
+        .bind(rule.id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query("DELETE FROM alert_rules WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[async_trait]
+impl WebhookRepository for SqliteWebhooks {
+    async fn insert(&self, webhook: Webhook) -> Result<(), String> {
+        let events: Vec<&str> = webhook.events.iter().map(|e| e.as_str()).collect();
+        let events = serde_json::to_string(&events).map_err(|e| e.to_string())?;
+        sqlx::query(
+            "INSERT INTO webhooks (id, url, secret, device, events, created_at) \
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(webhook.id)
+        .bind(&webhook.url)
+        .bind(&webhook.secret)
+        .bind(&webhook.device)
+        .bind(events)
+        .bind(to_nanos(webhook.created_at)?)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<Webhook>, String> {
+        let rows: Vec<WebhookRow> = sqlx::query_as(
+            "SELECT id, url, secret, device, events, created_at \
+             FROM webhooks ORDER BY created_at, id",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        rows.into_iter().map(Webhook::try_from).collect()
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<bool, String> {
+        let result = sqlx::query("DELETE FROM webhooks WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
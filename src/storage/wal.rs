@@ -0,0 +1,588 @@
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::runtime::Handle;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use tokio::time::{interval_at, Instant};
+use uuid::Uuid;
+
+use super::{TelemetryRepository, TelemetryStore};
+use crate::config::{FsyncPolicy, MemoryLimits};
+use crate::models::{
+    BucketStats, DeviceSummary, MemoryUsage, MemoryUsageQuery, MetricRollup, SortOrder,
+    TelemetryCursor, TelemetryData, TelemetryFilter, TelemetryTier,
+};
+
+/// Every record held as of the last compaction
+const SNAPSHOT_FILE: &str = "telemetry.snapshot";
+
+/// Snapshot being written, renamed over [`SNAPSHOT_FILE`] once complete
+const SNAPSHOT_TMP_FILE: &str = "telemetry.snapshot.tmp";
+
+/// Changes since the last compaction
+const WAL_FILE: &str = "telemetry.wal";
+
+/// Log being compacted; only left behind by a crash mid-compaction
+const COMPACTING_WAL_FILE: &str = "telemetry.wal.old";
+
+/// Log being started, renamed over [`WAL_FILE`] once the old one is rotated out
+const NEXT_WAL_FILE: &str = "telemetry.wal.next";
+
+/// One change to the store, written to the log as a line of JSON
+///
+/// Logs and snapshots start with their generation. A snapshot holds every
+/// record and rollup as of the end of the log of the same generation.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum WalEntry<'a> {
+    Log {
+        generation: u64,
+    },
+    Snapshot {
+        generation: u64,
+    },
+    Add {
+        telemetry: Cow<'a, TelemetryData>,
+    },
+    Delete {
+        device_id: Cow<'a, str>,
+        older_than: DateTime<Utc>,
+    },
+    DeleteRollups {
+        tier: TelemetryTier,
+        older_than: DateTime<Utc>,
+    },
+    /// A record in a snapshot, whose rollups are held separately
+    Record {
+        telemetry: Cow<'a, TelemetryData>,
+    },
+    /// A rollup in a snapshot
+    Rollup {
+        rollup: Cow<'a, MetricRollup>,
+    },
+}
+
+/// The open write-ahead log
+struct Wal {
+    path: PathBuf,
+    file: File,
+    fsync: FsyncPolicy,
+    generation: u64,
+
+    /// Length of the file up to the last complete entry
+    len: u64,
+
+    /// Whether entries have been written since the last fsync
+    unsynced: bool,
+
+    /// Entries written since the log was created
+    entries: u64,
+}
+
+impl Wal {
+    /// Create an empty log of `generation` at `path`, replacing any file there
+    fn create(path: PathBuf, generation: u64, fsync: FsyncPolicy) -> Result<Self, String> {
+        let file = File::create(&path).map_err(|e| io_error(&path, e))?;
+        let mut wal = Self {
+            path,
+            file,
+            fsync,
+            generation,
+            len: 0,
+            unsynced: false,
+            entries: 0,
+        };
+        wal.append(&encode(&[WalEntry::Log { generation }])?, 0)?;
+        wal.sync()?;
+        Ok(wal)
+    }
+
+    /// Append `entries` encoded entries in one write; they are only durable
+    /// once [`Wal::commit`] returns
+    fn append(&mut self, lines: &[u8], entries: u64) -> Result<(), String> {
+        if let Err(e) = self.file.write_all(lines) {
+            // Drop the torn entries so later ones are not appended after them
+            let _ = self.file.set_len(self.len);
+            let _ = self.file.seek(SeekFrom::Start(self.len));
+            return Err(io_error(&self.path, e));
+        }
+        self.len += lines.len() as u64;
+        self.unsynced = true;
+        self.entries += entries;
+        Ok(())
+    }
+
+    /// Make written entries durable as far as the fsync policy requires
+    fn commit(&mut self) -> Result<(), String> {
+        match self.fsync {
+            FsyncPolicy::Always => self.sync(),
+            FsyncPolicy::Interval | FsyncPolicy::Never => Ok(()),
+        }
+    }
+
+    /// Flush written entries to disk
+    fn sync(&mut self) -> Result<(), String> {
+        if self.unsynced {
+            self.file.sync_data().map_err(|e| io_error(&self.path, e))?;
+            self.unsynced = false;
+        }
+        Ok(())
+    }
+}
+
+/// In-memory telemetry store persisted to a write-ahead log and snapshots
+///
+/// Every write is appended to the log before it is applied to the wrapped
+/// [`TelemetryStore`], and reads are served from memory. The log is
+/// periodically compacted into a snapshot of every record and rollup held. On
+/// open the snapshot and logs are replayed, so the store comes back as it was
+/// before a restart or crash, less any writes the fsync policy had not yet
+/// flushed to disk.
+pub struct DurableTelemetryStore {
+    store: TelemetryStore,
+    dir: PathBuf,
+
+    /// Held while writing so the log records changes in the order they are applied
+    wal: Arc<Mutex<Wal>>,
+
+    /// Held for the duration of a compaction so compactions never overlap
+    compacting: Mutex<()>,
+}
+
+impl DurableTelemetryStore {
+    /// Recover the telemetry persisted in `dir` into `store` and log further changes there
+    ///
+    /// Everything recovered is compacted into a fresh snapshot before the
+    /// store is returned.
+    pub async fn open(
+        store: TelemetryStore,
+        dir: impl Into<PathBuf>,
+        fsync: FsyncPolicy,
+    ) -> Result<Self, String> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(|e| io_error(&dir, e))?;
+
+        let (generation, replayed) =
+            recover(&store, &dir, &[COMPACTING_WAL_FILE, WAL_FILE]).await?;
+
+        // The snapshot must hold everything replayed before any log is dropped
+        let records = store.all_records();
+        write_snapshot(&dir, generation, &records, &store.all_rollups())?;
+        remove_if_exists(&dir.join(COMPACTING_WAL_FILE))?;
+        remove_if_exists(&dir.join(NEXT_WAL_FILE))?;
+        let wal = Wal::create(dir.join(WAL_FILE), generation + 1, fsync)?;
+        sync_dir(&dir)?;
+
+        tracing::info!(
+            "Recovered {} telemetry records from {} entries in {}",
+            records.len(),
+            replayed,
+            dir.display()
+        );
+        Ok(Self {
+            store,
+            dir,
+            wal: Arc::new(Mutex::new(wal)),
+            compacting: Mutex::new(()),
+        })
+    }
+
+    /// Compact the log into a snapshot, returning how many records it holds
+    ///
+    /// Writes only wait while the log is rotated. The snapshot is then rebuilt
+    /// from the previous one and the rotated log rather than copied from the
+    /// live store, so it matches the end of that log exactly while writes
+    /// carry on. A log left behind by a failed compaction is compacted instead
+    /// of rotating another.
+    pub async fn snapshot(&self) -> Result<usize, String> {
+        let _compacting = self.compacting.lock().await;
+
+        let mut wal = self.wal.clone().lock_owned().await;
+        let dir = self.dir.clone();
+        let limits = self.store.limits();
+        blocking(move || {
+            let compacting = dir.join(COMPACTING_WAL_FILE);
+            if !compacting.exists() {
+                // Start the next log first, so a failure leaves the live one in place
+                let mut next = Wal::create(dir.join(NEXT_WAL_FILE), wal.generation + 1, wal.fsync)?;
+                wal.sync()?;
+                fs::rename(&wal.path, &compacting).map_err(|e| io_error(&compacting, e))?;
+                next.path = dir.join(WAL_FILE);
+                if let Err(e) = fs::rename(dir.join(NEXT_WAL_FILE), &next.path) {
+                    fs::rename(&compacting, &wal.path).map_err(|e| io_error(&wal.path, e))?;
+                    return Err(io_error(&next.path, e));
+                }
+                *wal = next;
+                sync_dir(&dir)?;
+            }
+            drop(wal);
+            compact(&dir, limits)
+        })
+        .await
+    }
+
+    /// Append `entries` to the log and commit them, doing the file I/O off the async runtime
+    ///
+    /// The log stays locked until the returned guard is dropped, so the
+    /// caller applies the change before any later one is logged.
+    async fn log(&self, entries: &[WalEntry<'_>]) -> Result<OwnedMutexGuard<Wal>, String> {
+        let lines = encode(entries)?;
+        let count = entries.len() as u64;
+        let mut wal = self.wal.clone().lock_owned().await;
+        blocking(move || {
+            wal.append(&lines, count)?;
+            wal.commit()?;
+            Ok(wal)
+        })
+        .await
+    }
+
+    /// Compact every `snapshot_period` and, under [`FsyncPolicy::Interval`],
+    /// flush the log every `fsync_period`, forever
+    pub async fn run(
+        self: Arc<Self>,
+        snapshot_period: std::time::Duration,
+        fsync_period: std::time::Duration,
+    ) {
+        let mut snapshots = interval_at(Instant::now() + snapshot_period, snapshot_period);
+        let mut syncs = interval_at(Instant::now() + fsync_period, fsync_period);
+        let interval_fsync = self.wal.lock().await.fsync == FsyncPolicy::Interval;
+
+        loop {
+            tokio::select! {
+                _ = snapshots.tick() => {
+                    if self.wal.lock().await.entries == 0 {
+                        continue;
+                    }
+                    match self.snapshot().await {
+                        Ok(count) => tracing::debug!("Snapshot written with {} telemetry records", count),
+                        Err(e) => tracing::warn!("Telemetry snapshot failed: {}", e),
+                    }
+                }
+                _ = syncs.tick(), if interval_fsync => {
+                    let mut wal = self.wal.clone().lock_owned().await;
+                    if let Err(e) = blocking(move || wal.sync()).await {
+                        tracing::warn!("Telemetry log fsync failed: {}", e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl TelemetryRepository for DurableTelemetryStore {
+    async fn add(&self, telemetry: TelemetryData) -> Result<Uuid, String> {
+        let _wal = self
+            .log(&[WalEntry::Add {
+                telemetry: Cow::Borrowed(&telemetry),
+            }])
+            .await?;
+        self.store.add(telemetry).await
+    }
+
+    async fn add_batch(&self, telemetry: Vec<TelemetryData>) -> Vec<Result<Uuid, String>> {
+        // Records are logged and committed together, then applied; one the
+        // store rejects is rejected the same way when the log is replayed
+        let entries: Vec<WalEntry> = telemetry
+            .iter()
+            .map(|record| WalEntry::Add {
+                telemetry: Cow::Borrowed(record),
+            })
+            .collect();
+        let logged = self.log(&entries).await;
+        drop(entries);
+
+        let _wal = match logged {
+            Ok(wal) => wal,
+            Err(e) => return telemetry.iter().map(|_| Err(e.clone())).collect(),
+        };
+        let mut results = Vec::with_capacity(telemetry.len());
+        for record in telemetry {
+            results.push(self.store.add(record).await);
+        }
+        results
+    }
+
+    async fn get_by_device(
+        &self,
+        device_id: &str,
+        filter: &TelemetryFilter,
+        after: Option<TelemetryCursor>,
+        order: SortOrder,
+        limit: usize,
+    ) -> Result<Vec<TelemetryData>, String> {
+        self.store
+            .get_by_device(device_id, filter, after, order, limit)
+            .await
+    }
+
+    async fn received_after(
+        &self,
+        device_id: Option<&str>,
+        after: TelemetryCursor,
+        limit: usize,
+    ) -> Result<Vec<TelemetryData>, String> {
+        self.store.received_after(device_id, after, limit).await
+    }
+
+    async fn aggregate(
+        &self,
+        device_id: &str,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        bucket: Duration,
+        metrics: &[String],
+    ) -> Result<Option<Vec<BucketStats>>, String> {
+        self.store
+            .aggregate(device_id, start_time, end_time, bucket, metrics)
+            .await
+    }
+
+    async fn list_devices(
+        &self,
+        prefix: Option<&str>,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<DeviceSummary>, String> {
+        self.store.list_devices(prefix, after, limit).await
+    }
+
+    async fn get_by_id(&self, id: Uuid) -> Result<Option<TelemetryData>, String> {
+        self.store.get_by_id(id).await
+    }
+
+    async fn delete_old_records(
+        &self,
+        device_id: &str,
+        older_than: DateTime<Utc>,
+    ) -> Result<usize, String> {
+        let _wal = self
+            .log(&[WalEntry::Delete {
+                device_id: Cow::Borrowed(device_id),
+                older_than,
+            }])
+            .await?;
+        self.store.delete_old_records(device_id, older_than).await
+    }
+
+    async fn rollups(
+        &self,
+        device_id: &str,
+        tier: TelemetryTier,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        metrics: &[String],
+    ) -> Result<Vec<BucketStats>, String> {
+        self.store
+            .rollups(device_id, tier, start_time, end_time, metrics)
+            .await
+    }
+
+    async fn delete_old_rollups(
+        &self,
+        tier: TelemetryTier,
+        older_than: DateTime<Utc>,
+    ) -> Result<usize, String> {
+        let _wal = self
+            .log(&[WalEntry::DeleteRollups { tier, older_than }])
+            .await?;
+        self.store.delete_old_rollups(tier, older_than).await
+    }
+
+    async fn memory_usage(&self, query: &MemoryUsageQuery) -> Result<Option<MemoryUsage>, String> {
+        self.store.memory_usage(query).await
+    }
+}
+
+/// Replay the snapshot in `dir` and then each of its `logs` into `store`
+///
+/// Returns the generation reached and how many entries were applied.
+async fn recover(
+    store: &TelemetryStore,
+    dir: &Path,
+    logs: &[&str],
+) -> Result<(u64, usize), String> {
+    let (snapshot_generation, mut replayed) = replay(store, &dir.join(SNAPSHOT_FILE), 0).await?;
+    let mut generation = snapshot_generation;
+    for log in logs {
+        let (log_generation, applied) = replay(store, &dir.join(log), snapshot_generation).await?;
+        generation = generation.max(log_generation);
+        replayed += applied;
+    }
+    Ok((generation, replayed))
+}
+
+/// Fold the log being compacted into the snapshot in `dir`, returning how many records it holds
+///
+/// Runs on a blocking thread, since it replays both files into a scratch
+/// store bound by the same `limits` as the live one.
+fn compact(dir: &Path, limits: MemoryLimits) -> Result<usize, String> {
+    let store = TelemetryStore::new().with_limits(limits);
+    let (generation, _) =
+        Handle::current().block_on(recover(&store, dir, &[COMPACTING_WAL_FILE]))?;
+
+    let records = store.all_records();
+    write_snapshot(dir, generation, &records, &store.all_rollups())?;
+    remove_if_exists(&dir.join(COMPACTING_WAL_FILE))?;
+    Ok(records.len())
+}
+
+/// Apply the entries of the log or snapshot at `path` to `store`
+///
+/// A log whose generation is not after `snapshot_generation` was already
+/// folded into the snapshot and is skipped. Returns the file's generation,
+/// or 0 when there is no file or it was torn before its first entry, and
+/// how many entries were applied.
+async fn replay(
+    store: &TelemetryStore,
+    path: &Path,
+    snapshot_generation: u64,
+) -> Result<(u64, usize), String> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((0, 0)),
+        Err(e) => return Err(io_error(path, e)),
+    };
+
+    let mut lines = BufReader::new(file).split(b'\n').enumerate().peekable();
+    let mut replayed = 0;
+    let mut generation = None;
+    while let Some((number, line)) = lines.next() {
+        let line = line.map_err(|e| io_error(path, e))?;
+        let entry = match serde_json::from_slice::<WalEntry>(&line) {
+            Ok(entry) => entry,
+            // A crash mid-write can only tear the final entry
+            Err(_) if lines.peek().is_none() => {
+                tracing::warn!("Ignoring incomplete final entry of {}", path.display());
+                break;
+            }
+            Err(e) => return Err(corrupt(path, number, e)),
+        };
+
+        // Every file starts with its generation, and only there
+        if generation.is_none() {
+            match entry {
+                WalEntry::Log { generation: log } if log <= snapshot_generation => {
+                    return Ok((log, 0));
+                }
+                WalEntry::Log { generation: g } | WalEntry::Snapshot { generation: g } => {
+                    generation = Some(g);
+                    continue;
+                }
+                _ => return Err(corrupt(path, number, "missing generation")),
+            }
+        }
+
+        match entry {
+            WalEntry::Log { .. } | WalEntry::Snapshot { .. } => {
+                return Err(corrupt(path, number, "unexpected generation"));
+            }
+            // Adding a record already held fails, which is expected when replaying
+            WalEntry::Add { telemetry } => {
+                let _ = store.add(telemetry.into_owned()).await;
+            }
+            WalEntry::Delete {
+                device_id,
+                older_than,
+            } => {
+                store.delete_old_records(&device_id, older_than).await?;
+            }
+            WalEntry::DeleteRollups { tier, older_than } => {
+                store.delete_old_rollups(tier, older_than).await?;
+            }
+            WalEntry::Record { telemetry } => {
+                let _ = store.restore(telemetry.into_owned());
+            }
+            WalEntry::Rollup { rollup } => store.restore_rollup(rollup.into_owned()),
+        }
+        replayed += 1;
+    }
+    Ok((generation.unwrap_or(0), replayed))
+}
+
+/// Atomically replace the snapshot in `dir` with one of `generation` holding `records` and `rollups`
+fn write_snapshot(
+    dir: &Path,
+    generation: u64,
+    records: &[TelemetryData],
+    rollups: &[MetricRollup],
+) -> Result<(), String> {
+    let tmp = dir.join(SNAPSHOT_TMP_FILE);
+    let write = || -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(&tmp)?);
+        let records = records.iter().map(|telemetry| WalEntry::Record {
+            telemetry: Cow::Borrowed(telemetry),
+        });
+        let rollups = rollups.iter().map(|rollup| WalEntry::Rollup {
+            rollup: Cow::Borrowed(rollup),
+        });
+        for entry in std::iter::once(WalEntry::Snapshot { generation })
+            .chain(records)
+            .chain(rollups)
+        {
+            serde_json::to_writer(&mut file, &entry)?;
+            file.write_all(b"\n")?;
+        }
+        file.into_inner()?.sync_all()
+    };
+    write().map_err(|e| io_error(&tmp, e))?;
+
+    let snapshot = dir.join(SNAPSHOT_FILE);
+    fs::rename(&tmp, &snapshot).map_err(|e| io_error(&snapshot, e))?;
+    sync_dir(dir)
+}
+
+/// Encode entries as the lines of JSON they are logged as
+fn encode(entries: &[WalEntry]) -> Result<Vec<u8>, String> {
+    let mut lines = Vec::new();
+    for entry in entries {
+        serde_json::to_writer(&mut lines, entry).map_err(|e| e.to_string())?;
+        lines.push(b'\n');
+    }
+    Ok(lines)
+}
+
+/// Run file I/O on the blocking thread pool rather than an async worker
+async fn blocking<T: Send + 'static>(
+    io: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    tokio::task::spawn_blocking(io)
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn corrupt(path: &Path, line: usize, e: impl std::fmt::Display) -> String {
+    format!("{} is corrupt at line {}: {}", path.display(), line + 1, e)
+}
+
+fn remove_if_exists(path: &Path) -> Result<(), String> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(io_error(path, e)),
+        _ => Ok(()),
+    }
+}
+
+/// Make renames and new files in `dir` durable
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> Result<(), String> {
+    File::open(dir)
+        .and_then(|dir| dir.sync_all())
+        .map_err(|e| io_error(dir, e))
+}
+
+/// Directory entries cannot be synced here; renames are durable once the OS flushes them
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+fn io_error(path: &Path, e: io::Error) -> String {
+    format!("{}: {}", path.display(), e)
+}
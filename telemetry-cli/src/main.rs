@@ -3,6 +3,7 @@ use clap::{Parser, Subcommand};
 use rand::Rng;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::time::Duration;
 use tokio::time;
@@ -40,6 +41,10 @@ enum Commands {
         /// Pressure value (hPa)
         #[clap(short, long)]
         pressure: Option<f32>,
+
+        /// Additional metric as NAME=VALUE, e.g. co2=415 (repeatable)
+        #[clap(short, long = "metric", value_parser = parse_metric)]
+        metrics: Vec<(String, f64)>,
     },
 
     /// Simulate a device sending telemetry data continuously
@@ -80,6 +85,8 @@ struct TelemetryPayload {
     temperature: f32,
     humidity: Option<f32>,
     pressure: Option<f32>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    metrics: BTreeMap<String, f64>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -99,6 +106,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
             temperature,
             humidity,
             pressure,
+            metrics,
         } => {
             // Generate random temperature if not provided
             let temp = temperature.unwrap_or_else(|| {
@@ -111,6 +119,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 temperature: temp,
                 humidity,
                 pressure,
+                metrics: metrics.into_iter().collect(),
             };
 
             let response = send_telemetry(&client, &url, payload).await?;
@@ -144,6 +153,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                     temperature: temp,
                     humidity: Some(humidity),
                     pressure: Some(pressure),
+                    metrics: BTreeMap::new(),
                 };
 
                 match send_telemetry(&client, &url, payload).await {
@@ -181,6 +191,17 @@ async fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+/// Parse a `NAME=VALUE` metric argument
+fn parse_metric(arg: &str) -> Result<(String, f64), String> {
+    let (name, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=VALUE, got '{}'", arg))?;
+    let value = value
+        .parse()
+        .map_err(|_| format!("invalid value for metric '{}'", name))?;
+    Ok((name.to_string(), value))
+}
+
 async fn send_telemetry(
     client: &Client,
     base_url: &str,
@@ -1,10 +1,26 @@
+use actix_web::body::MessageBody;
+use actix_web::http::StatusCode;
 use actix_web::{test, web, App};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use rustegrate::api::routes;
-use rustegrate::models::CreateTelemetryRequest;
-use rustegrate::services::TelemetryService;
-use rustegrate::storage::TelemetryStore;
+use rustegrate::config::{
+    DeviceRetention, MemoryLimits, MetricRange, RetentionConfig, ValidationConfig,
+};
+use rustegrate::models::{
+    BucketStats, CreateTelemetryRequest, DeviceSummary, SortOrder, TelemetryCursor, TelemetryData,
+    TelemetryFilter, TelemetryTier,
+};
+use rustegrate::services::{
+    AlertService, DeviceService, HeartbeatService, RetentionService, TelemetryService,
+    WebhookService, SIGNATURE_HEADER,
+};
+use rustegrate::storage::{
+    AlertRuleStore, DeviceRegistryStore, TelemetryRepository, TelemetryStore, WebhookStore,
+};
 use serde_json::json;
+use std::collections::BTreeMap;
+use std::pin::Pin;
+use std::sync::Arc;
 
 #[actix_web::test]
 async fn test_create_telemetry() {
@@ -21,7 +37,9 @@ async fn test_create_telemetry() {
     // Create test payload
     let payload = CreateTelemetryRequest {
         device_id: "test-device-001".to_string(),
-        temperature: 23.5,
+        message_id: None,
+        metrics: BTreeMap::new(),
+        temperature: Some(23.5),
         humidity: Some(45.0),
         pressure: Some(1013.0),
         timestamp: Utc::now(),
@@ -53,7 +71,9 @@ async fn test_get_device_telemetry() {
     // Create a test telemetry entry
     let payload = CreateTelemetryRequest {
         device_id: "test-device-002".to_string(),
-        temperature: 22.5,
+        message_id: None,
+        metrics: BTreeMap::new(),
+        temperature: Some(22.5),
         humidity: Some(40.0),
         pressure: Some(1010.0),
         timestamp: Utc::now(),
@@ -81,9 +101,1981 @@ async fn test_get_device_telemetry() {
 
     // Parse response
     let body = test::read_body(resp).await;
-    let response: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+    let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let data = response["data"].as_array().unwrap();
 
     // Verify we got at least one telemetry record
-    assert!(!response.is_empty());
-    assert_eq!(response[0]["device_id"], json!("test-device-002"));
+    assert!(!data.is_empty());
+    assert_eq!(data[0]["device_id"], json!("test-device-002"));
+}
+
+#[actix_web::test]
+async fn test_create_telemetry_batch_partial_success() {
+    // Setup
+    let store = TelemetryStore::new();
+    let service = TelemetryService::new(store);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .configure(routes::configure),
+    )
+    .await;
+
+    // Second item is missing its temperature, third has an empty device ID
+    let payload = json!([
+        { "device_id": "batch-device-001", "temperature": 21.0 },
+        { "device_id": "batch-device-001" },
+        { "device_id": "", "temperature": 22.0 },
+        { "device_id": "batch-device-001", "temperature": 23.0, "humidity": 50.0 }
+    ]);
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/telemetry/batch")
+        .set_json(&payload)
+        .to_request();
+
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::MULTI_STATUS);
+
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(response["accepted"], json!(2));
+    assert_eq!(response["rejected"], json!(2));
+
+    let results = response["results"].as_array().unwrap();
+    assert_eq!(results.len(), 4);
+    assert!(results[0]["id"].is_string());
+    assert!(results[1]["error"].is_string());
+    assert!(results[2]["error"].is_string());
+    assert_eq!(results[3]["index"], json!(3));
+
+    // Only the valid readings were stored
+    let req = test::TestRequest::get()
+        .uri("/api/v1/devices/batch-device-001/telemetry")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body = test::read_body(resp).await;
+    let stored: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(stored["data"].as_array().unwrap().len(), 2);
+}
+
+/// Store that fails to add readings from devices whose ID starts with "broken"
+struct FailingStore(TelemetryStore);
+
+#[async_trait::async_trait]
+impl TelemetryRepository for FailingStore {
+    async fn add(&self, telemetry: TelemetryData) -> Result<uuid::Uuid, String> {
+        if telemetry.device_id.starts_with("broken") {
+            return Err("disk full".to_string());
+        }
+        self.0.add(telemetry).await
+    }
+
+    async fn get_by_device(
+        &self,
+        device_id: &str,
+        filter: &TelemetryFilter,
+        after: Option<TelemetryCursor>,
+        order: SortOrder,
+        limit: usize,
+    ) -> Result<Vec<TelemetryData>, String> {
+        self.0
+            .get_by_device(device_id, filter, after, order, limit)
+            .await
+    }
+
+    async fn received_after(
+        &self,
+        device_id: Option<&str>,
+        after: TelemetryCursor,
+        limit: usize,
+    ) -> Result<Vec<TelemetryData>, String> {
+        self.0.received_after(device_id, after, limit).await
+    }
+
+    async fn list_devices(
+        &self,
+        prefix: Option<&str>,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<DeviceSummary>, String> {
+        self.0.list_devices(prefix, after, limit).await
+    }
+
+    async fn get_by_id(&self, id: uuid::Uuid) -> Result<Option<TelemetryData>, String> {
+        self.0.get_by_id(id).await
+    }
+
+    async fn delete_old_records(
+        &self,
+        device_id: &str,
+        older_than: DateTime<Utc>,
+    ) -> Result<usize, String> {
+        self.0.delete_old_records(device_id, older_than).await
+    }
+
+    async fn rollups(
+        &self,
+        device_id: &str,
+        tier: TelemetryTier,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        metrics: &[String],
+    ) -> Result<Vec<BucketStats>, String> {
+        self.0
+            .rollups(device_id, tier, start_time, end_time, metrics)
+            .await
+    }
+
+    async fn delete_old_rollups(
+        &self,
+        tier: TelemetryTier,
+        older_than: DateTime<Utc>,
+    ) -> Result<usize, String> {
+        self.0.delete_old_rollups(tier, older_than).await
+    }
+}
+
+#[actix_web::test]
+async fn test_create_telemetry_batch_storage_failure() {
+    let service = TelemetryService::new(FailingStore(TelemetryStore::new()));
+    let mut events = service.subscribe();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .configure(routes::configure),
+    )
+    .await;
+
+    let payload = json!([
+        { "device_id": "dock-1", "message_id": "m1", "temperature": 21.0 },
+        { "device_id": "broken-1", "message_id": "m2", "temperature": 22.0 },
+        { "device_id": "dock-1", "message_id": "m3", "temperature": 23.0 }
+    ]);
+    let batch = || {
+        test::TestRequest::post()
+            .uri("/api/v1/telemetry/batch")
+            .set_json(&payload)
+            .to_request()
+    };
+
+    // Readings the store kept are accepted even though another one failed
+    let resp = test::call_service(&app, batch()).await;
+    assert_eq!(resp.status(), StatusCode::MULTI_STATUS);
+    let first: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(first["accepted"], 2);
+    assert_eq!(first["rejected"], 1);
+    assert!(first["results"][1]["error"].is_string());
+    for _ in 0..2 {
+        assert_eq!(events.try_recv().unwrap().device_id, "dock-1");
+    }
+    assert!(events.try_recv().is_err());
+
+    // Retrying the batch stores nothing twice, while the failed reading is tried again
+    let second: serde_json::Value = test::call_and_read_body_json(&app, batch()).await;
+    assert_eq!(second["results"][0]["id"], first["results"][0]["id"]);
+    assert_eq!(second["results"][2]["id"], first["results"][2]["id"]);
+    assert!(second["results"][1]["error"].is_string());
+    assert!(events.try_recv().is_err());
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/devices/dock-1/telemetry")
+        .to_request();
+    let stored: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(stored["data"].as_array().unwrap().len(), 2);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/devices/dock-1/latest")
+        .to_request();
+    let latest: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(latest["id"], first["results"][2]["id"]);
+}
+
+#[actix_web::test]
+async fn test_import_telemetry_ndjson() {
+    // Setup
+    let store = TelemetryStore::new();
+    let service = TelemetryService::new(store);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .configure(routes::configure),
+    )
+    .await;
+
+    // Line 3 is malformed and line 4 is blank; the last line has no newline
+    let body = concat!(
+        "{\"device_id\":\"ndjson-device-001\",\"temperature\":20.5}\n",
+        "{\"device_id\":\"ndjson-device-001\",\"temperature\":21.5}\r\n",
+        "{\"device_id\":\"ndjson-device-001\",\"temperature\":\n",
+        "\n",
+        "{\"device_id\":\"ndjson-device-001\",\"temperature\":22.5}",
+    );
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/telemetry/import")
+        .insert_header(("content-type", "application/x-ndjson"))
+        .set_payload(body)
+        .to_request();
+
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(response["accepted"], json!(3));
+    assert_eq!(response["rejected"], json!(1));
+    assert_eq!(response["errors"][0]["line"], json!(3));
+}
+
+#[actix_web::test]
+async fn test_aggregate_device_telemetry() {
+    // Setup
+    let store = TelemetryStore::new();
+    for temperature in [20.0, 30.0] {
+        let payload = CreateTelemetryRequest {
+            device_id: "aggregate-device-001".to_string(),
+            message_id: None,
+            metrics: BTreeMap::new(),
+            temperature: Some(temperature),
+            humidity: None,
+            pressure: None,
+            timestamp: "2024-01-01T00:01:00Z".parse().unwrap(),
+        };
+        store
+            .add(rustegrate::models::TelemetryData::from(payload))
+            .await
+            .unwrap();
+    }
+
+    let service = TelemetryService::new(store);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .configure(routes::configure),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/devices/aggregate-device-001/telemetry/aggregate?bucket=5m&fn=avg,count")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let body = test::read_body(resp).await;
+    let response: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(response.len(), 1);
+    assert_eq!(response[0]["bucket_start"], json!("2024-01-01T00:00:00Z"));
+    assert_eq!(response[0]["metrics"]["temperature"]["avg"], json!(25.0));
+    assert_eq!(response[0]["metrics"]["temperature"]["count"], json!(2));
+    assert!(response[0]["metrics"]["temperature"].get("min").is_none());
+
+    // Unknown bucket units are rejected
+    let req = test::TestRequest::get()
+        .uri("/api/v1/devices/aggregate-device-001/telemetry/aggregate?bucket=5w")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_aggregate_rollup_tiers() {
+    // Setup: two readings in the first hour and two in the second
+    let store = TelemetryStore::new();
+    for (timestamp, temperature) in [
+        ("2024-01-01T00:01:00Z", 20.0),
+        ("2024-01-01T00:02:00Z", 30.0),
+        ("2024-01-01T01:00:00Z", 40.0),
+        ("2024-01-01T01:30:00Z", 10.0),
+    ] {
+        let payload = CreateTelemetryRequest {
+            device_id: "rollup-device-001".to_string(),
+            message_id: None,
+            metrics: BTreeMap::new(),
+            temperature: Some(temperature),
+            humidity: None,
+            pressure: None,
+            timestamp: timestamp.parse().unwrap(),
+        };
+        store
+            .add(rustegrate::models::TelemetryData::from(payload))
+            .await
+            .unwrap();
+    }
+
+    let service = TelemetryService::new(store);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .configure(routes::configure),
+    )
+    .await;
+
+    // The coarsest tier fitting the bucket answers, and is reported in a header
+    for (query, tier) in [
+        ("bucket=1h", "1h"),
+        ("bucket=30m", "1m"),
+        ("bucket=5m", "1m"),
+        ("bucket=150s", "raw"),
+        ("bucket=1h&tier=raw", "raw"),
+    ] {
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/api/v1/devices/rollup-device-001/telemetry/aggregate?{}&fn=avg,count,last",
+                query
+            ))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success(), "{}", query);
+        assert_eq!(
+            resp.headers().get("X-Telemetry-Tier").unwrap(),
+            tier,
+            "{}",
+            query
+        );
+
+        let response: Vec<serde_json::Value> = test::read_body_json(resp).await;
+        let temperature = &response[0]["metrics"]["temperature"];
+        assert_eq!(temperature["last"], json!(30.0), "{}", query);
+        assert_eq!(temperature["avg"], json!(25.0), "{}", query);
+    }
+
+    // Rollups are only chosen when the range falls on their boundaries, so
+    // the result always matches the raw readings
+    for (range, tier) in [
+        (
+            "start_time=2024-01-01T00:00:00Z&end_time=2024-01-01T01:00:00Z",
+            "1h",
+        ),
+        (
+            "start_time=2024-01-01T00:00:00Z&end_time=2024-01-01T01:30:00Z",
+            "1m",
+        ),
+        (
+            "start_time=2024-01-01T00:01:30Z&end_time=2024-01-01T01:00:00Z",
+            "raw",
+        ),
+        (
+            "start_time=2024-01-01T00:00:00Z&end_time=2024-01-01T00:01:00.5Z",
+            "raw",
+        ),
+    ] {
+        let uri = format!(
+            "/api/v1/devices/rollup-device-001/telemetry/aggregate?bucket=1h&fn=avg,min,max,count,last&{}",
+            range
+        );
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get("X-Telemetry-Tier").unwrap(),
+            tier,
+            "{}",
+            range
+        );
+        let chosen: serde_json::Value = test::read_body_json(resp).await;
+
+        let req = test::TestRequest::get()
+            .uri(&format!("{}&tier=raw", uri))
+            .to_request();
+        let raw: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(chosen, raw, "{}", range);
+    }
+
+    // A tier too coarse for the bucket, or an unknown one, is rejected
+    for query in ["bucket=5m&tier=1h", "bucket=1h&tier=1w"] {
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/api/v1/devices/rollup-device-001/telemetry/aggregate?{}",
+                query
+            ))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", query);
+    }
+}
+
+#[actix_web::test]
+async fn test_device_telemetry_cursor_pagination() {
+    // Setup: five readings, two of them sharing a timestamp
+    let store = TelemetryStore::new();
+    let timestamps = [
+        "2024-01-01T00:00:03Z",
+        "2024-01-01T00:00:01Z",
+        "2024-01-01T00:00:02Z",
+        "2024-01-01T00:00:02Z",
+        "2024-01-01T00:00:00Z",
+    ];
+    for timestamp in timestamps {
+        let payload = CreateTelemetryRequest {
+            device_id: "paged-device-001".to_string(),
+            message_id: None,
+            metrics: BTreeMap::new(),
+            temperature: Some(20.0),
+            humidity: None,
+            pressure: None,
+            timestamp: timestamp.parse().unwrap(),
+        };
+        store
+            .add(rustegrate::models::TelemetryData::from(payload))
+            .await
+            .unwrap();
+    }
+
+    let service = TelemetryService::new(store);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .configure(routes::configure),
+    )
+    .await;
+
+    // Walk every page, two records at a time
+    let mut seen = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let uri = match &cursor {
+            Some(c) => format!(
+                "/api/v1/devices/paged-device-001/telemetry?limit=2&cursor={}",
+                c
+            ),
+            None => "/api/v1/devices/paged-device-001/telemetry?limit=2".to_string(),
+        };
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let body = test::read_body(resp).await;
+        let page: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        for record in page["data"].as_array().unwrap() {
+            seen.push((
+                record["timestamp"].as_str().unwrap().to_string(),
+                record["id"].as_str().unwrap().to_string(),
+            ));
+        }
+
+        match page.get("next_cursor") {
+            Some(next) => cursor = Some(next.as_str().unwrap().to_string()),
+            None => break,
+        }
+    }
+
+    // Every record appears exactly once, in timestamp order
+    assert_eq!(seen.len(), 5);
+    let mut sorted = seen.clone();
+    sorted.sort();
+    sorted.dedup();
+    assert_eq!(sorted.len(), 5);
+    let timestamps: Vec<_> = seen.iter().map(|(t, _)| t.clone()).collect();
+    let mut ordered = timestamps.clone();
+    ordered.sort();
+    assert_eq!(timestamps, ordered);
+
+    // Garbage cursors are rejected
+    let req = test::TestRequest::get()
+        .uri("/api/v1/devices/paged-device-001/telemetry?cursor=not-a-cursor")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    // An empty page would look like the end of the data, so it cannot be asked for
+    for uri in [
+        "/api/v1/devices/paged-device-001/telemetry?limit=0",
+        "/api/v1/devices?limit=0",
+    ] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", uri);
+    }
+}
+
+#[actix_web::test]
+async fn test_get_latest_telemetry() {
+    // Setup
+    let store = TelemetryStore::new();
+    let service = TelemetryService::new(store);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .configure(routes::configure),
+    )
+    .await;
+
+    // Nothing reported yet
+    let req = test::TestRequest::get()
+        .uri("/api/v1/devices/latest-device-001/latest")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    // The late reading arrives last but is older, so it must not win
+    for (temperature, timestamp) in [
+        (21.0, "2024-01-01T00:05:00Z"),
+        (19.0, "2024-01-01T00:00:00Z"),
+    ] {
+        let payload = json!({
+            "device_id": "latest-device-001",
+            "temperature": temperature,
+            "timestamp": timestamp,
+        });
+        let req = test::TestRequest::post()
+            .uri("/api/v1/telemetry")
+            .set_json(&payload)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/devices/latest-device-001/latest")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(response["metrics"]["temperature"], json!(21.0));
+    assert_eq!(response["timestamp"], json!("2024-01-01T00:05:00Z"));
+    assert!(response["received_at"].is_string());
+}
+
+#[actix_web::test]
+async fn test_list_devices() {
+    // Setup
+    let store = TelemetryStore::new();
+    let service = TelemetryService::new(store);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .configure(routes::configure),
+    )
+    .await;
+
+    for device_id in ["fleet-a-001", "fleet-a-002", "fleet-b-001"] {
+        let payload = json!({ "device_id": device_id, "temperature": 20.0 });
+        let req = test::TestRequest::post()
+            .uri("/api/v1/telemetry")
+            .set_json(&payload)
+            .to_request();
+        test::call_service(&app, req).await;
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/devices?prefix=fleet-a&limit=1")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let body = test::read_body(resp).await;
+    let page: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(page["data"][0]["device_id"], json!("fleet-a-001"));
+    assert_eq!(page["data"][0]["record_count"], json!(1));
+    assert!(page["data"][0]["last_seen"].is_string());
+
+    let cursor = page["next_cursor"].as_str().unwrap();
+    let req = test::TestRequest::get()
+        .uri(&format!(
+            "/api/v1/devices?prefix=fleet-a&limit=1&cursor={}",
+            cursor
+        ))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body = test::read_body(resp).await;
+    let page: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(page["data"].as_array().unwrap().len(), 1);
+    assert_eq!(page["data"][0]["device_id"], json!("fleet-a-002"));
+    assert!(page.get("next_cursor").is_none());
+}
+
+#[actix_web::test]
+async fn test_device_registry_crud() {
+    let service = TelemetryService::new(TelemetryStore::new());
+    let devices = DeviceService::new(DeviceRegistryStore::new());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .app_data(web::Data::new(devices))
+            .configure(routes::configure),
+    )
+    .await;
+
+    let payload = json!({
+        "device_id": "freezer-001",
+        "name": "Walk-in freezer",
+        "type": "freezer-sensor",
+        "location": "Kitchen",
+        "tags": ["cold-chain"]
+    });
+    let req = test::TestRequest::post()
+        .uri("/api/v1/devices")
+        .set_json(&payload)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    let body = test::read_body(resp).await;
+    let device: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(device["status"], json!("active"));
+    let created_at = device["created_at"].clone();
+
+    // Registering the same ID again conflicts
+    let req = test::TestRequest::post()
+        .uri("/api/v1/devices")
+        .set_json(&payload)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+    // Device IDs follow the same rules as in telemetry
+    let req = test::TestRequest::post()
+        .uri("/api/v1/devices")
+        .set_json(json!({ "device_id": "freezer 002", "name": "Freezer", "type": "sensor" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body = test::read_body(resp).await;
+    let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(error["details"][0]["field"], json!("device_id"));
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/devices")
+        .set_json(json!({ "device_id": "fridge-001", "name": "Fridge", "type": "sensor" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    // The registry lists devices that have never reported telemetry, unlike the default listing
+    let req = test::TestRequest::get().uri("/api/v1/devices").to_request();
+    let page: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(page["data"].as_array().unwrap().is_empty());
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/devices?registered=true&limit=1")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    let body = test::read_body(resp).await;
+    let page: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(page["data"].as_array().unwrap().len(), 1);
+    assert_eq!(page["data"][0]["device_id"], json!("freezer-001"));
+    let cursor = page["next_cursor"].as_str().unwrap().to_string();
+
+    let req = test::TestRequest::get()
+        .uri(&format!(
+            "/api/v1/devices?registered=true&limit=1&cursor={}",
+            cursor
+        ))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body = test::read_body(resp).await;
+    let page: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(page["data"][0]["device_id"], json!("fridge-001"));
+    assert!(page.get("next_cursor").is_none());
+
+    let update = json!({
+        "name": "Walk-in freezer",
+        "type": "freezer-sensor",
+        "location": "Back kitchen",
+        "status": "maintenance"
+    });
+    let req = test::TestRequest::put()
+        .uri("/api/v1/devices/freezer-001")
+        .set_json(&update)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/devices/freezer-001")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body = test::read_body(resp).await;
+    let device: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(device["location"], json!("Back kitchen"));
+    assert_eq!(device["status"], json!("maintenance"));
+    assert_eq!(device["created_at"], created_at);
+
+    let req = test::TestRequest::delete()
+        .uri("/api/v1/devices/freezer-001")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/devices/freezer-001")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn test_unregistered_devices_rejected_when_required() {
+    let registry = Arc::new(DeviceRegistryStore::new());
+    let service =
+        TelemetryService::new(TelemetryStore::new()).require_registered_devices(registry.clone());
+    let devices = DeviceService::with_repository(registry);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .app_data(web::Data::new(devices))
+            .configure(routes::configure),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/devices")
+        .set_json(json!({ "device_id": "known-001", "name": "Known", "type": "sensor" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    for (device_id, status) in [
+        ("retired-001", "decommissioned"),
+        ("serviced-001", "maintenance"),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/devices")
+            .set_json(
+                json!({ "device_id": device_id, "name": "Sensor", "type": "sensor",
+                              "status": status }),
+            )
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/telemetry")
+        .set_json(json!({ "device_id": "rogue-001", "temperature": 20.0 }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    // Registered devices must also be in a status that reports
+    let req = test::TestRequest::post()
+        .uri("/api/v1/telemetry")
+        .set_json(json!({ "device_id": "retired-001", "temperature": 20.0 }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/telemetry")
+        .set_json(json!({ "device_id": "serviced-001", "temperature": 20.0 }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    let batch = json!([
+        { "device_id": "known-001", "temperature": 20.0 },
+        { "device_id": "rogue-001", "temperature": 21.0 },
+        { "device_id": "retired-001", "temperature": 21.0 }
+    ]);
+    let req = test::TestRequest::post()
+        .uri("/api/v1/telemetry/batch")
+        .set_json(&batch)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::MULTI_STATUS);
+
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(response["accepted"], json!(1));
+    assert!(response["results"][0]["id"].is_string());
+    assert!(response["results"][1]["error"].is_string());
+    assert!(response["results"][2]["error"].is_string());
+}
+
+#[actix_web::test]
+async fn test_arbitrary_metrics() {
+    let service = TelemetryService::new(TelemetryStore::new());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .configure(routes::configure),
+    )
+    .await;
+
+    let payload = json!({
+        "device_id": "air-001",
+        "metrics": { "co2": 415.0, "battery": 3.7 },
+        "temperature": 21.0
+    });
+    let req = test::TestRequest::post()
+        .uri("/api/v1/telemetry")
+        .set_json(&payload)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/devices/air-001/latest")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        response["metrics"],
+        json!({ "battery": 3.7, "co2": 415.0, "temperature": 21.0 })
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/devices/air-001/telemetry/aggregate?bucket=1h&metrics=co2")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(response[0]["metrics"]["co2"]["avg"], json!(415.0));
+    assert!(response[0]["metrics"].get("temperature").is_none());
+
+    // A reading needs at least one metric, and a legacy field may not repeat one
+    for payload in [
+        json!({ "device_id": "air-001" }),
+        json!({ "device_id": "air-001", "temperature": 20.0, "metrics": { "temperature": 21.0 } }),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/telemetry")
+            .set_json(&payload)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+}
+
+#[actix_web::test]
+async fn test_validation_reports_every_failing_field() {
+    let mut validation = ValidationConfig::default();
+    validation.metric_ranges.insert(
+        "co2".to_string(),
+        MetricRange {
+            min: 0.0,
+            max: 10000.0,
+        },
+    );
+    let service = TelemetryService::new(TelemetryStore::new()).with_validation(validation);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .configure(routes::configure),
+    )
+    .await;
+
+    let payload = json!({
+        "device_id": "bad device!",
+        "humidity": 400.0,
+        "metrics": { "co2": -5.0 },
+        "timestamp": "3000-01-01T00:00:00Z"
+    });
+    let req = test::TestRequest::post()
+        .uri("/api/v1/telemetry")
+        .set_json(&payload)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let mut fields: Vec<&str> = response["details"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| d["field"].as_str().unwrap())
+        .collect();
+    fields.sort();
+    assert_eq!(
+        fields,
+        vec!["device_id", "humidity", "metrics.co2", "timestamp"]
+    );
+
+    // Readings inside every range are accepted
+    let payload = json!({
+        "device_id": "good-device:01",
+        "humidity": 40.0,
+        "metrics": { "co2": 415.0 }
+    });
+    let req = test::TestRequest::post()
+        .uri("/api/v1/telemetry")
+        .set_json(&payload)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+}
+
+#[cfg(feature = "sqlite")]
+#[actix_web::test]
+async fn test_unstorable_timestamps_rejected() {
+    let store = rustegrate::storage::SqliteTelemetryStore::connect("sqlite::memory:")
+        .await
+        .unwrap();
+    let validation = ValidationConfig {
+        max_clock_skew_secs: i64::MAX,
+        ..ValidationConfig::default()
+    };
+    let service = TelemetryService::new(store).with_validation(validation);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .configure(routes::configure),
+    )
+    .await;
+
+    // Outside the range SQLite stores, so rejected as invalid rather than failing to store
+    for timestamp in [
+        "1500-01-01T00:00:00Z",
+        "1969-12-31T23:59:59Z",
+        "2300-01-01T00:00:00Z",
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/telemetry")
+            .set_json(
+                json!({ "device_id": "old-001", "temperature": 20.0, "timestamp": timestamp }),
+            )
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", timestamp);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["details"][0]["field"], "timestamp");
+    }
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/telemetry")
+        .set_json(json!({ "device_id": "old-001", "temperature": 20.0,
+                          "timestamp": "1970-01-01T00:00:00Z" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+}
+
+#[actix_web::test]
+async fn test_idempotent_ingestion() {
+    let service = TelemetryService::new(TelemetryStore::new());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .configure(routes::configure),
+    )
+    .await;
+
+    let payload = json!({ "device_id": "flaky-001", "temperature": 20.0 });
+    let mut ids = Vec::new();
+    for _ in 0..2 {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/telemetry")
+            .insert_header(("Idempotency-Key", "msg-1"))
+            .set_json(&payload)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let body = test::read_body(resp).await;
+        let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        ids.push(response["id"].clone());
+    }
+    assert_eq!(ids[0], ids[1]);
+
+    // The body field is equivalent to the header, and retries in a batch are deduplicated too
+    let batch = json!([
+        { "device_id": "flaky-001", "message_id": "msg-1", "temperature": 20.0 },
+        { "device_id": "flaky-001", "message_id": "msg-2", "temperature": 21.0 },
+        { "device_id": "flaky-001", "message_id": "msg-2", "temperature": 21.0 }
+    ]);
+    let req = test::TestRequest::post()
+        .uri("/api/v1/telemetry/batch")
+        .set_json(&batch)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(response["results"][0]["id"], ids[0]);
+    assert_eq!(response["results"][1]["id"], response["results"][2]["id"]);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/devices/flaky-001/telemetry")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body = test::read_body(resp).await;
+    let page: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(page["data"].as_array().unwrap().len(), 2);
+
+    // The same message ID from another device is a different message
+    let req = test::TestRequest::post()
+        .uri("/api/v1/telemetry")
+        .insert_header(("Idempotency-Key", "msg-1"))
+        .set_json(json!({ "device_id": "flaky-002", "temperature": 20.0 }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_ne!(response["id"], ids[0]);
+}
+
+#[actix_web::test]
+async fn test_idempotency_window_disabled() {
+    let service = TelemetryService::new(TelemetryStore::new())
+        .with_idempotency_window(chrono::Duration::zero());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .configure(routes::configure),
+    )
+    .await;
+
+    let payload = json!({ "device_id": "flaky-003", "message_id": "msg-1", "temperature": 20.0 });
+    let mut ids = Vec::new();
+    for _ in 0..2 {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/telemetry")
+            .set_json(&payload)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let body = test::read_body(resp).await;
+        let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        ids.push(response["id"].clone());
+    }
+    assert_ne!(ids[0], ids[1]);
+}
+
+#[actix_web::test]
+async fn test_filter_on_receive_time() {
+    let service = TelemetryService::new(TelemetryStore::new());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .configure(routes::configure),
+    )
+    .await;
+
+    // A reading taken yesterday but only uploaded now
+    let before = Utc::now();
+    let taken = before - chrono::Duration::days(1);
+    let req = test::TestRequest::post()
+        .uri("/api/v1/telemetry")
+        .set_json(json!({ "device_id": "late-001", "temperature": 20.0, "timestamp": taken }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    let since = before.to_rfc3339_opts(chrono::SecondsFormat::Micros, true);
+    let uri = format!(
+        "/api/v1/devices/late-001/telemetry?received_start_time={}",
+        since
+    );
+    let req = test::TestRequest::get().uri(&uri).to_request();
+    let resp = test::call_service(&app, req).await;
+    let body = test::read_body(resp).await;
+    let page: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(page["data"].as_array().unwrap().len(), 1);
+    assert!(page["data"][0]["received_at"].is_string());
+
+    // The same bound on the device timestamp excludes it
+    let uri = format!("/api/v1/devices/late-001/telemetry?start_time={}", since);
+    let req = test::TestRequest::get().uri(&uri).to_request();
+    let resp = test::call_service(&app, req).await;
+    let body = test::read_body(resp).await;
+    let page: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(page["data"].as_array().unwrap().is_empty());
+}
+
+/// Read the next `telemetry` event from an SSE body, returning its ID and data
+async fn next_event<B: MessageBody>(body: &mut Pin<Box<B>>) -> (String, serde_json::Value) {
+    loop {
+        let chunk = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            std::future::poll_fn(|cx| body.as_mut().poll_next(cx)),
+        )
+        .await
+        .expect("timed out waiting for an event")
+        .expect("stream ended")
+        .ok()
+        .unwrap();
+        let text = String::from_utf8(chunk.to_vec()).unwrap();
+        if !text.contains("event: telemetry") {
+            continue;
+        }
+
+        let field = |name: &str| {
+            text.lines()
+                .find_map(|line| line.strip_prefix(name))
+                .unwrap()
+                .to_string()
+        };
+        let data = serde_json::from_str(&field("data: ")).unwrap();
+        return (field("id: "), data);
+    }
+}
+
+#[actix_web::test]
+async fn test_stream_live_telemetry() {
+    let service = TelemetryService::new(TelemetryStore::new());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .configure(routes::configure),
+    )
+    .await;
+
+    let open = |uri: &str, last_event_id: Option<&str>| {
+        let mut req = test::TestRequest::get().uri(uri);
+        if let Some(id) = last_event_id {
+            req = req.insert_header(("Last-Event-ID", id));
+        }
+        req.to_request()
+    };
+
+    let resp = test::call_service(&app, open("/api/v1/devices/sse-001/stream", None)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get("content-type").unwrap(),
+        "text/event-stream"
+    );
+    let mut device_stream = Box::pin(resp.into_body());
+    let resp = test::call_service(&app, open("/api/v1/telemetry/stream", None)).await;
+    let mut fleet_stream = Box::pin(resp.into_body());
+
+    for (device_id, temperature) in [("sse-002", 10.0), ("sse-001", 20.0), ("sse-001", 21.0)] {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/telemetry")
+            .set_json(json!({ "device_id": device_id, "temperature": temperature }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+
+    // The device stream skips other devices' readings
+    let (first_id, first) = next_event(&mut device_stream).await;
+    assert_eq!(first["device_id"], "sse-001");
+    assert_eq!(first["metrics"]["temperature"], 20.0);
+    let (_, second) = next_event(&mut device_stream).await;
+    assert_eq!(second["metrics"]["temperature"], 21.0);
+
+    let (_, fleet_first) = next_event(&mut fleet_stream).await;
+    assert_eq!(fleet_first["device_id"], "sse-002");
+
+    // Reconnecting with the last seen event ID replays what came after it
+    let resp = test::call_service(
+        &app,
+        open("/api/v1/devices/sse-001/stream", Some(&first_id)),
+    )
+    .await;
+    let mut resumed = Box::pin(resp.into_body());
+    let (_, replayed) = next_event(&mut resumed).await;
+    assert_eq!(replayed["id"], second["id"]);
+
+    let resp = test::call_service(
+        &app,
+        open("/api/v1/devices/sse-001/stream", Some("not-a-cursor")),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_stream_replay_order_and_overflow() {
+    let service = web::Data::new(TelemetryService::new(TelemetryStore::new()));
+    let app = test::init_service(
+        App::new()
+            .app_data(service.clone())
+            .configure(routes::configure),
+    )
+    .await;
+
+    let add = |device_id: &str, sequence: f64, hours_ago: i64| {
+        let request = serde_json::from_value(json!({
+            "device_id": device_id,
+            "timestamp": Utc::now() - chrono::Duration::hours(hours_ago),
+            "metrics": { "sequence": sequence }
+        }))
+        .unwrap();
+        let service = service.clone();
+        async move { service.create_telemetry(request).await.unwrap() }
+    };
+    let open = |uri: &str, last_event_id: &str| {
+        test::TestRequest::get()
+            .uri(uri)
+            .insert_header(("Last-Event-ID", last_event_id.to_string()))
+            .to_request()
+    };
+
+    let first = add("replay-001", 1.0, 0).await;
+    let first = service.get_telemetry_by_id(first).await.unwrap();
+    let position = TelemetryCursor::received(&first).encode();
+
+    // Readings are replayed in the order received, not by device timestamp
+    add("replay-001", 2.0, 1).await;
+    add("replay-002", 3.0, 3).await;
+    add("replay-001", 4.0, 2).await;
+    let resp = test::call_service(&app, open("/api/v1/telemetry/stream", &position)).await;
+    let mut fleet = Box::pin(resp.into_body());
+    for expected in [2.0, 3.0, 4.0] {
+        let (_, reading) = next_event(&mut fleet).await;
+        assert_eq!(reading["metrics"]["sequence"], expected);
+    }
+
+    // Past the replay limit the client is told it overflowed and the stream ends
+    for i in 0..1000 {
+        add("replay-001", i as f64, 0).await;
+    }
+    let resp = test::call_service(&app, open("/api/v1/devices/replay-001/stream", &position)).await;
+    let body = tokio::time::timeout(std::time::Duration::from_secs(5), test::read_body(resp))
+        .await
+        .expect("stream did not end");
+    let text = String::from_utf8(body.to_vec()).unwrap();
+    let events: Vec<&str> = text.split("\n\n").filter(|e| !e.is_empty()).collect();
+    assert_eq!(events.len(), 1001);
+    assert!(events[..1000]
+        .iter()
+        .all(|e| e.contains("event: telemetry")));
+    assert!(events[0].contains("\"sequence\":2.0"));
+    assert!(events[1].contains("\"sequence\":4.0"));
+    assert_eq!(
+        events[1000],
+        "event: overflow\ndata: {\"reason\":\"replay_limit\"}"
+    );
+
+    // Resuming from the last replayed reading picks up the rest
+    let last_id = events[999]
+        .lines()
+        .find_map(|line| line.strip_prefix("id: "))
+        .unwrap();
+    let resp = test::call_service(&app, open("/api/v1/devices/replay-001/stream", last_id)).await;
+    let mut resumed = Box::pin(resp.into_body());
+    for expected in [998.0, 999.0] {
+        let (_, reading) = next_event(&mut resumed).await;
+        assert_eq!(reading["metrics"]["sequence"], expected);
+    }
+}
+
+#[actix_web::test]
+async fn test_websocket_subscriptions() {
+    use futures_util::SinkExt;
+    use tokio_tungstenite::tungstenite::Message;
+
+    let service = web::Data::new(TelemetryService::new(TelemetryStore::new()));
+    let app_service = service.clone();
+    let server = actix_web::HttpServer::new(move || {
+        App::new()
+            .app_data(app_service.clone())
+            .configure(routes::configure)
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+    let addr = server.addrs()[0];
+    actix_web::rt::spawn(server.run());
+
+    let (mut socket, _) =
+        tokio_tungstenite::connect_async(format!("ws://{}/api/v1/telemetry/ws", addr))
+            .await
+            .unwrap();
+
+    let subscribe = json!({
+        "action": "subscribe",
+        "id": "hot-labs",
+        "devices": ["lab-*"],
+        "thresholds": [{ "metric": "temperature", "op": ">", "value": 30.0 }]
+    });
+    socket
+        .send(Message::Text(subscribe.to_string()))
+        .await
+        .unwrap();
+    let reply = read_json(&mut socket).await;
+    assert_eq!(reply, json!({ "type": "subscribed", "id": "hot-labs" }));
+
+    // Only the hot reading from a matching device is delivered
+    for (device_id, temperature) in [("lab-1", 20.0), ("office-1", 35.0), ("lab-2", 31.5)] {
+        service
+            .create_telemetry(
+                serde_json::from_value(
+                    json!({ "device_id": device_id, "temperature": temperature }),
+                )
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+    let reading = read_json(&mut socket).await;
+    assert_eq!(reading["type"], "telemetry");
+    assert_eq!(reading["subscriptions"], json!(["hot-labs"]));
+    assert_eq!(reading["data"]["device_id"], "lab-2");
+
+    socket
+        .send(Message::Text(
+            json!({ "action": "unsubscribe", "id": "missing" }).to_string(),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(read_json(&mut socket).await["type"], "error");
+
+    socket
+        .send(Message::Text(
+            json!({ "action": "unsubscribe", "id": "hot-labs" }).to_string(),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(read_json(&mut socket).await["type"], "unsubscribed");
+}
+
+/// Read the next text message from a WebSocket as JSON
+async fn read_json<S>(socket: &mut S) -> serde_json::Value
+where
+    S: futures_util::Stream<
+            Item = Result<
+                tokio_tungstenite::tungstenite::Message,
+                tokio_tungstenite::tungstenite::Error,
+            >,
+        > + Unpin,
+{
+    use futures_util::StreamExt;
+
+    let message = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next())
+        .await
+        .expect("timed out waiting for a message")
+        .expect("socket closed")
+        .unwrap();
+    serde_json::from_str(message.to_text().unwrap()).unwrap()
+}
+
+#[actix_web::test]
+async fn test_alert_rules_fire_and_resolve() {
+    let alerts = Arc::new(AlertService::new(AlertRuleStore::new()));
+    let service = TelemetryService::new(TelemetryStore::new()).with_alerts(alerts.clone());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .app_data(web::Data::from(alerts))
+            .configure(routes::configure),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/alerts/rules")
+        .set_json(json!({
+            "name": "",
+            "device": "freezer-*",
+            "metric": "temperature",
+            "op": "gt",
+            "threshold": -15.0,
+            "duration_secs": -1
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["details"].as_array().unwrap().len(), 2);
+
+    // A duration too long to represent is rejected rather than breaking evaluation
+    let req = test::TestRequest::post()
+        .uri("/api/v1/alerts/rules")
+        .set_json(json!({
+            "name": "Freezer too warm",
+            "device": "freezer-*",
+            "metric": "temperature",
+            "op": "gt",
+            "threshold": -15.0,
+            "duration_secs": i64::MAX
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["details"][0]["field"], "duration_secs");
+
+    // Fires once above -15 for a minute, resolves once back below -17
+    let req = test::TestRequest::post()
+        .uri("/api/v1/alerts/rules")
+        .set_json(json!({
+            "name": "Freezer too warm",
+            "device": "freezer-*",
+            "metric": "temperature",
+            "op": "gt",
+            "threshold": -15.0,
+            "duration_secs": 60,
+            "hysteresis": 2.0
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let rule: serde_json::Value = test::read_body_json(resp).await;
+    let rule_uri = format!("/api/v1/alerts/rules/{}", rule["id"].as_str().unwrap());
+
+    let start = Utc::now() - chrono::Duration::minutes(10);
+    let send = |device_id: &'static str, temperature: f64, offset_secs: i64| {
+        let timestamp = start + chrono::Duration::seconds(offset_secs);
+        test::TestRequest::post()
+            .uri("/api/v1/telemetry")
+            .set_json(json!({
+                "device_id": device_id,
+                "temperature": temperature,
+                "timestamp": timestamp
+            }))
+            .to_request()
+    };
+    let list = |query: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/alerts{}", query))
+            .to_request()
+    };
+
+    for (device_id, temperature, offset) in [
+        ("freezer-1", -10.0, 0),
+        ("freezer-1", -9.0, 30),
+        ("fridge-1", 5.0, 30),
+    ] {
+        test::call_service(&app, send(device_id, temperature, offset)).await;
+    }
+    let alerts: serde_json::Value =
+        test::read_body_json(test::call_service(&app, list("?state=firing")).await).await;
+    assert!(alerts.as_array().unwrap().is_empty());
+
+    test::call_service(&app, send("freezer-1", -8.0, 90)).await;
+    let alerts: serde_json::Value =
+        test::read_body_json(test::call_service(&app, list("?state=firing")).await).await;
+    assert_eq!(alerts[0]["device_id"], "freezer-1");
+    assert_eq!(alerts[0]["value"], -8.0);
+    assert_eq!(alerts.as_array().unwrap().len(), 1);
+
+    // Dropping below the threshold but within the hysteresis keeps it firing
+    test::call_service(&app, send("freezer-1", -16.0, 120)).await;
+    test::call_service(&app, send("freezer-1", -18.0, 150)).await;
+    let alerts: serde_json::Value =
+        test::read_body_json(test::call_service(&app, list("?device_id=freezer-1")).await).await;
+    assert_eq!(alerts[0]["state"], "resolved");
+    assert_eq!(alerts[0]["last_value"], -18.0);
+    assert!(alerts[0]["resolved_at"].is_string());
+
+    let req = test::TestRequest::put()
+        .uri(&rule_uri)
+        .set_json(json!({
+            "name": "Freezer far too warm",
+            "device": "freezer-*",
+            "metric": "temperature",
+            "op": "gte",
+            "threshold": -5.0
+        }))
+        .to_request();
+    let updated: serde_json::Value =
+        test::read_body_json(test::call_service(&app, req).await).await;
+    assert_eq!(updated["created_at"], rule["created_at"]);
+    assert_eq!(updated["op"], "gte");
+
+    let req = test::TestRequest::delete().uri(&rule_uri).to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NO_CONTENT
+    );
+    let req = test::TestRequest::get().uri(&rule_uri).to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NOT_FOUND
+    );
+}
+
+/// Request received by the webhook stand-in: signature header and body
+type ReceivedHook = (String, serde_json::Value);
+
+/// Poll `check` until it returns a value, failing after five seconds
+async fn eventually<T, F, Fut>(mut check: F) -> T
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Option<T>>,
+{
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(5);
+    loop {
+        if let Some(value) = check().await {
+            return value;
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "condition not met in time"
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+}
+
+#[actix_web::test]
+async fn test_webhook_deliveries() {
+    use hmac::{Hmac, Mac};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    // Local stand-in for a downstream endpoint that fails its first request
+    let received: Arc<Mutex<Vec<ReceivedHook>>> = Arc::default();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let (log, counter) = (received.clone(), calls.clone());
+    let server = actix_web::HttpServer::new(move || {
+        let (log, counter) = (log.clone(), counter.clone());
+        App::new().route(
+            "/hook",
+            web::post().to(move |req: actix_web::HttpRequest, body: web::Bytes| {
+                let (log, counter) = (log.clone(), counter.clone());
+                async move {
+                    if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                        return actix_web::HttpResponse::InternalServerError().finish();
+                    }
+                    let signature = req
+                        .headers()
+                        .get(SIGNATURE_HEADER)
+                        .unwrap()
+                        .to_str()
+                        .unwrap();
+                    let signed = Hmac::<sha2::Sha256>::new_from_slice(b"s3cret")
+                        .unwrap()
+                        .chain_update(&body)
+                        .finalize()
+                        .into_bytes();
+                    assert_eq!(signature, format!("sha256={}", hex::encode(signed)));
+                    let payload = serde_json::from_slice(&body).unwrap();
+                    log.lock().unwrap().push((signature.to_string(), payload));
+                    actix_web::HttpResponse::NoContent().finish()
+                }
+            }),
+        )
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+    let hook_url = format!("http://{}/hook", server.addrs()[0]);
+    actix_web::rt::spawn(server.run());
+
+    let webhooks = Arc::new(
+        WebhookService::new(WebhookStore::new())
+            .with_retry(2, std::time::Duration::from_millis(10)),
+    );
+    let service = TelemetryService::new(TelemetryStore::new()).with_webhooks(webhooks.clone());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .app_data(web::Data::from(webhooks))
+            .configure(routes::configure),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/webhooks")
+        .set_json(json!({
+            "url": hook_url,
+            "secret": "s3cret",
+            "device": "dock-*",
+            "events": ["reading-accepted", "reading-rejected"]
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let webhook: serde_json::Value = test::read_body_json(resp).await;
+    assert!(webhook.get("secret").is_none());
+
+    // Nothing listens on port 1, so every delivery to this one fails
+    let req = test::TestRequest::post()
+        .uri("/api/v1/webhooks")
+        .set_json(json!({
+            "url": "http://127.0.0.1:1/hook",
+            "secret": "other",
+            "events": ["records-deleted"]
+        }))
+        .to_request();
+    let unreachable: serde_json::Value =
+        test::read_body_json(test::call_service(&app, req).await).await;
+
+    for payload in [
+        json!({ "device_id": "dock-1", "temperature": 4.0 }),
+        json!({ "device_id": "dock-1", "humidity": 150.0 }),
+        json!({ "device_id": "yard-1", "temperature": 9.0 }),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/telemetry")
+            .set_json(payload)
+            .to_request();
+        test::call_service(&app, req).await;
+    }
+    let req = test::TestRequest::delete()
+        .uri("/api/v1/devices/dock-1/telemetry")
+        .set_json(json!({ "older_than": Utc::now() + chrono::Duration::days(1) }))
+        .to_request();
+    test::call_service(&app, req).await;
+
+    // The failed first attempt is retried; the other device is filtered out
+    let mut hooks = eventually(|| {
+        let received = received.clone();
+        async move {
+            let hooks = received.lock().unwrap().clone();
+            (hooks.len() == 2).then_some(hooks)
+        }
+    })
+    .await;
+    hooks.sort_by_key(|(_, payload)| payload["event"].as_str().unwrap().to_string());
+    assert_eq!(hooks[0].1["event"], "reading-accepted");
+    assert_eq!(hooks[0].1["data"]["device_id"], "dock-1");
+    assert_eq!(hooks[1].1["event"], "reading-rejected");
+    assert_eq!(hooks[1].1["data"]["details"][0]["field"], "humidity");
+
+    let uri = format!(
+        "/api/v1/webhooks/{}/deliveries",
+        webhook["id"].as_str().unwrap()
+    );
+    // The stand-in logs a request before the service records its outcome
+    let deliveries = eventually(|| {
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let app = &app;
+        async move {
+            let deliveries: serde_json::Value =
+                test::read_body_json(test::call_service(app, req).await).await;
+            let deliveries = deliveries.as_array().unwrap().clone();
+            let delivered =
+                deliveries.len() == 2 && deliveries.iter().all(|d| d["status"] == "delivered");
+            delivered.then_some(deliveries)
+        }
+    })
+    .await;
+    let attempts: u64 = deliveries
+        .iter()
+        .map(|d| d["attempts"].as_u64().unwrap())
+        .sum();
+    assert_eq!(attempts, 3);
+
+    let dead = eventually(|| {
+        let req = test::TestRequest::get()
+            .uri("/api/v1/webhooks/dead-letters")
+            .to_request();
+        let app = &app;
+        async move {
+            let dead: serde_json::Value =
+                test::read_body_json(test::call_service(app, req).await).await;
+            (!dead.as_array().unwrap().is_empty()).then_some(dead)
+        }
+    })
+    .await;
+    assert_eq!(dead[0]["webhook_id"], unreachable["id"]);
+    assert_eq!(dead[0]["event"], "records-deleted");
+    assert_eq!(dead[0]["attempts"], 2);
+    assert_eq!(dead[0]["payload"]["data"]["deleted"], 1);
+}
+
+#[actix_web::test]
+async fn test_webhook_deliveries_are_bounded() {
+    // Retries are an hour apart, so a failed delivery stays under way
+    let webhooks = Arc::new(
+        WebhookService::new(WebhookStore::new())
+            .with_retry(5, std::time::Duration::from_secs(3600))
+            .with_max_in_flight(2),
+    );
+    let service = TelemetryService::new(TelemetryStore::new()).with_webhooks(webhooks.clone());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .app_data(web::Data::from(webhooks))
+            .configure(routes::configure),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/webhooks")
+        .set_json(json!({
+            "url": "http://127.0.0.1:1/hook",
+            "secret": "s3cret",
+            "events": ["reading-accepted"]
+        }))
+        .to_request();
+    let webhook: serde_json::Value =
+        test::read_body_json(test::call_service(&app, req).await).await;
+
+    for temperature in 0..5 {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/telemetry")
+            .set_json(json!({ "device_id": "dock-1", "temperature": temperature }))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::CREATED
+        );
+    }
+
+    // Only two deliveries are ever under way; the rest are dead-lettered at once
+    let req = test::TestRequest::get()
+        .uri(&format!(
+            "/api/v1/webhooks/{}/deliveries?status=pending",
+            webhook["id"].as_str().unwrap()
+        ))
+        .to_request();
+    let pending: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(pending.as_array().unwrap().len(), 2);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/webhooks/dead-letters")
+        .to_request();
+    let dead: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let dead = dead.as_array().unwrap();
+    assert_eq!(dead.len(), 3);
+    assert!(dead.iter().all(|d| d["attempts"] == 0));
+    assert_eq!(
+        dead[0]["last_error"],
+        "Too many deliveries under way to this webhook"
+    );
+}
+
+#[actix_web::test]
+async fn test_device_heartbeat_status() {
+    let registry = Arc::new(DeviceRegistryStore::new());
+    let heartbeats = Arc::new(
+        HeartbeatService::new()
+            .with_default_interval(chrono::Duration::milliseconds(200))
+            .with_offline_factor(5)
+            .with_registry(registry.clone()),
+    );
+    let devices = DeviceService::with_repository(registry).with_heartbeats(heartbeats.clone());
+    let service = TelemetryService::new(TelemetryStore::new()).with_heartbeats(heartbeats.clone());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .app_data(web::Data::new(devices))
+            .app_data(web::Data::from(heartbeats.clone()))
+            .configure(routes::configure),
+    )
+    .await;
+
+    let status = |device_id: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/devices/{}/status", device_id))
+            .to_request()
+    };
+    let post_reading = |device_id: &str| {
+        test::TestRequest::post()
+            .uri("/api/v1/telemetry")
+            .set_json(json!({ "device_id": device_id, "temperature": 21.0 }))
+            .to_request()
+    };
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/devices")
+        .set_json(
+            json!({ "device_id": "pump-1", "name": "Pump", "type": "pump",
+                          "expected_interval_secs": 0 }),
+        )
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+    // Intervals too long to track are rejected before they reach the registry
+    let req = test::TestRequest::post()
+        .uri("/api/v1/devices")
+        .set_json(
+            json!({ "device_id": "pump-1", "name": "Pump", "type": "pump",
+                          "expected_interval_secs": i64::MAX }),
+        )
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+    for (device_id, interval) in [("pump-1", json!(3600)), ("pump-2", json!(null))] {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/devices")
+            .set_json(
+                json!({ "device_id": device_id, "name": "Pump", "type": "pump",
+                              "expected_interval_secs": interval }),
+            )
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::CREATED
+        );
+    }
+
+    // Unknown devices have no status; registered ones that never reported are offline
+    let resp = test::call_service(&app, status("ghost")).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let pump: serde_json::Value = test::call_and_read_body_json(&app, status("pump-2")).await;
+    assert_eq!(pump["status"], "offline");
+    assert!(pump.get("last_seen").is_none());
+
+    for device_id in ["pump-1", "sensor-1"] {
+        test::call_service(&app, post_reading(device_id)).await;
+    }
+    let sensor: serde_json::Value = test::call_and_read_body_json(&app, status("sensor-1")).await;
+    assert_eq!(sensor["status"], "online");
+    assert_eq!(sensor["expected_interval_secs"], 0);
+
+    // Intervals too long to represent or to multiply are tracked without panicking
+    for interval in [i64::MAX, i64::MAX / 1000] {
+        heartbeats.set_interval("pump-1", Some(interval));
+        heartbeats.check();
+    }
+    heartbeats.set_interval("pump-1", Some(3600));
+
+    // Overdue after one interval, offline after five; the pump reports hourly
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    let detected = heartbeats.check();
+    assert_eq!(detected.len(), 1);
+    let sensor: serde_json::Value = test::call_and_read_body_json(&app, status("sensor-1")).await;
+    assert_eq!(sensor["status"], "stale");
+
+    tokio::time::sleep(std::time::Duration::from_millis(800)).await;
+    heartbeats.check();
+    let pump: serde_json::Value = test::call_and_read_body_json(&app, status("pump-1")).await;
+    assert_eq!(pump["status"], "online");
+    assert_eq!(pump["expected_interval_secs"], 3600);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/fleet/status?status=offline")
+        .to_request();
+    let fleet: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(fleet["total"], 2);
+    assert_eq!(fleet["online"], 1);
+    assert_eq!(fleet["offline"], 1);
+    assert_eq!(fleet["devices"].as_array().unwrap().len(), 1);
+    assert_eq!(fleet["devices"][0]["device_id"], "sensor-1");
+
+    // A new reading brings the device back
+    test::call_service(&app, post_reading("sensor-1")).await;
+    let req = test::TestRequest::get()
+        .uri("/api/v1/devices/sensor-1/status/history")
+        .to_request();
+    let history: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let steps: Vec<(&str, &str)> = history
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| (t["from"].as_str().unwrap(), t["to"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        steps,
+        [
+            ("offline", "online"),
+            ("stale", "offline"),
+            ("online", "stale")
+        ]
+    );
+}
+
+#[actix_web::test]
+async fn test_retention_sweep() {
+    let service = Arc::new(TelemetryService::new(TelemetryStore::new()));
+    let policy = RetentionConfig {
+        default_days: Some(7),
+        devices: vec![
+            DeviceRetention {
+                device: "freezer-*".to_string(),
+                days: 20,
+            },
+            DeviceRetention {
+                device: "test-1".to_string(),
+                days: 1,
+            },
+        ],
+        tiers: [(TelemetryTier::Day, 365)].into_iter().collect(),
+        sweep_interval_secs: 3600,
+    };
+    let retention = RetentionService::new(service.clone(), policy);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::from(service))
+            .app_data(web::Data::new(retention))
+            .configure(routes::configure),
+    )
+    .await;
+
+    for (device_id, days_old) in [
+        ("dev-a", 10),
+        ("dev-a", 1),
+        ("freezer-1", 40),
+        ("freezer-1", 10),
+        ("test-1", 2),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/telemetry")
+            .set_json(json!({
+                "device_id": device_id,
+                "temperature": 4.0,
+                "timestamp": Utc::now() - chrono::Duration::days(days_old)
+            }))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::CREATED
+        );
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/admin/retention")
+        .to_request();
+    let status: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(status["runs"], 0);
+    assert_eq!(status["policy"]["default_days"], 7);
+    assert_eq!(status["policy"]["tiers"]["1d"], 365);
+    assert!(status.get("last_run").is_none());
+
+    // Device overrides beat the default
+    let req = test::TestRequest::post()
+        .uri("/api/v1/admin/retention/run")
+        .to_request();
+    let run: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(run["devices_checked"], 3);
+    assert_eq!(run["devices_pruned"], 3);
+    assert_eq!(run["records_deleted"], 3);
+    assert_eq!(run["rollups_deleted"], 0);
+    assert_eq!(run["failures"], 0);
+
+    for (device_id, remaining) in [("dev-a", 1), ("freezer-1", 1), ("test-1", 0)] {
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/v1/devices/{}/telemetry", device_id))
+            .to_request();
+        let page: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            page["data"].as_array().unwrap().len(),
+            remaining,
+            "{}",
+            device_id
+        );
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/admin/retention")
+        .to_request();
+    let status: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(status["runs"], 1);
+    assert_eq!(status["total_deleted"], 3);
+    assert_eq!(status["running"], false);
+    assert_eq!(status["last_run"]["records_deleted"], 3);
+}
+
+#[actix_web::test]
+async fn test_memory_usage() {
+    let store = TelemetryStore::new().with_limits(MemoryLimits {
+        max_records_per_device: Some(2),
+        ..MemoryLimits::default()
+    });
+    let service = TelemetryService::new(store);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .configure(routes::configure),
+    )
+    .await;
+
+    for device_id in ["chatty", "chatty", "chatty", "quiet"] {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/telemetry")
+            .set_json(json!({ "device_id": device_id, "temperature": 20.0 }))
+            .to_request();
+        test::call_service(&app, req).await;
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/admin/memory")
+        .to_request();
+    let usage: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(usage["limits"]["max_records_per_device"], 2);
+    assert_eq!(usage["total_records"], 3);
+    assert_eq!(usage["evictions"]["device_cap"], 1);
+    // Largest devices come first
+    assert_eq!(usage["devices"][0]["device_id"], "chatty");
+    assert_eq!(usage["devices"][0]["records"], 2);
+    assert_eq!(usage["devices"][0]["evicted"], 1);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/admin/memory?device_id=quiet")
+        .to_request();
+    let usage: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let devices = usage["devices"].as_array().unwrap();
+    assert_eq!(devices.len(), 1);
+    assert_eq!(devices[0]["records"], 1);
+    assert!(devices[0]["approx_bytes"].as_u64().unwrap() > 0);
 }
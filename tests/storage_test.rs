@@ -0,0 +1,1342 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use rustegrate::config::{FsyncPolicy, MemoryLimits};
+use rustegrate::models::{
+    AggregateFunction, AlertRule, AlertRuleRequest, Comparator, CreateDeviceRequest,
+    CreateTelemetryRequest, Device, DeviceStatus, MemoryUsage, MemoryUsageQuery, SortOrder,
+    TelemetryCursor, TelemetryData, TelemetryFilter, TelemetryTier, Webhook, WebhookEvent,
+    WebhookRequest,
+};
+use rustegrate::services::TelemetryService;
+use rustegrate::storage::{
+    AlertRuleRepository, DeviceRepository, DurableTelemetryStore, Storage, TelemetryRepository,
+    TelemetryStore, WebhookRepository,
+};
+use uuid::Uuid;
+
+/// Device IDs are made unique per test so suites can share a database
+fn unique_device(prefix: &str) -> String {
+    format!("{}-{}", prefix, Uuid::new_v4())
+}
+
+async fn memory_usage(store: &TelemetryStore) -> MemoryUsage {
+    let query = MemoryUsageQuery {
+        device_id: None,
+        limit: 100,
+    };
+    store.memory_usage(&query).await.unwrap().unwrap()
+}
+
+#[tokio::test]
+async fn in_memory_per_device_cap_evicts_oldest() {
+    let store = TelemetryStore::new().with_limits(MemoryLimits {
+        max_records_per_device: Some(3),
+        ..MemoryLimits::default()
+    });
+    let base = Utc::now();
+
+    let mut ids = Vec::new();
+    for minutes in [4, 2, 0, 3, 1] {
+        let telemetry = reading_at("chatty", 20.0, base - Duration::minutes(minutes));
+        ids.push(store.add(telemetry).await.unwrap());
+    }
+    store
+        .add(reading_at("quiet", 20.0, base - Duration::hours(1)))
+        .await
+        .unwrap();
+
+    // The readings 4 and 3 minutes old are gone; the others are untouched
+    let kept = store
+        .get_by_device(
+            "chatty",
+            &TelemetryFilter::default(),
+            None,
+            SortOrder::Asc,
+            10,
+        )
+        .await
+        .unwrap();
+    let ages: Vec<i64> = kept
+        .iter()
+        .map(|t| (base - t.timestamp).num_minutes())
+        .collect();
+    assert_eq!(ages, [2, 1, 0]);
+    assert!(store.get_by_id(ids[0]).await.unwrap().is_none());
+    assert!(store.get_by_id(ids[3]).await.unwrap().is_none());
+
+    // A backfilled reading older than everything kept is evicted at once
+    store
+        .add(reading_at("chatty", 20.0, base - Duration::hours(2)))
+        .await
+        .unwrap();
+
+    let usage = memory_usage(&store).await;
+    assert_eq!(usage.total_records, 4);
+    assert_eq!(usage.evictions.device_cap, 3);
+    assert_eq!(usage.evictions.total_cap, 0);
+    let chatty = usage
+        .devices
+        .iter()
+        .find(|d| d.device_id == "chatty")
+        .unwrap();
+    assert_eq!((chatty.records, chatty.evicted), (3, 3));
+    let quiet = usage
+        .devices
+        .iter()
+        .find(|d| d.device_id == "quiet")
+        .unwrap();
+    assert_eq!((quiet.records, quiet.evicted), (1, 0));
+    assert_eq!(usage.approx_bytes, chatty.approx_bytes + quiet.approx_bytes);
+}
+
+#[tokio::test]
+async fn in_memory_global_caps_evict_oldest_across_devices() {
+    let store = TelemetryStore::new().with_limits(MemoryLimits {
+        max_total_records: Some(3),
+        ..MemoryLimits::default()
+    });
+    let base = Utc::now();
+
+    for (device_id, minutes) in [("dev-a", 5), ("dev-b", 4), ("dev-a", 3), ("dev-b", 2)] {
+        store
+            .add(reading_at(
+                device_id,
+                20.0,
+                base - Duration::minutes(minutes),
+            ))
+            .await
+            .unwrap();
+    }
+    let usage = memory_usage(&store).await;
+    assert_eq!(usage.total_records, 3);
+    assert_eq!(usage.evictions.total_cap, 1);
+    let dev_a = usage
+        .devices
+        .iter()
+        .find(|d| d.device_id == "dev-a")
+        .unwrap();
+    assert_eq!((dev_a.records, dev_a.evicted), (1, 1));
+
+    // Deleted records free their share of the caps
+    assert_eq!(
+        store
+            .delete_old_records("dev-b", base - Duration::minutes(3))
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(memory_usage(&store).await.total_records, 2);
+
+    // Every reading below has the same size, so the byte cap holds exactly two
+    let sized = TelemetryStore::new();
+    sized.add(reading_at("dev-c", 20.0, base)).await.unwrap();
+    let record_bytes = memory_usage(&sized).await.approx_bytes;
+
+    let store = TelemetryStore::new().with_limits(MemoryLimits {
+        max_bytes: Some(record_bytes * 5 / 2),
+        ..MemoryLimits::default()
+    });
+    for minutes in [3, 1, 2] {
+        store
+            .add(reading_at("dev-c", 20.0, base - Duration::minutes(minutes)))
+            .await
+            .unwrap();
+    }
+    let usage = memory_usage(&store).await;
+    assert_eq!(usage.total_records, 2);
+    assert_eq!(usage.approx_bytes, 2 * record_bytes);
+    assert_eq!(usage.evictions.byte_cap, 1);
+    let kept = store
+        .get_by_device(
+            "dev-c",
+            &TelemetryFilter::default(),
+            None,
+            SortOrder::Asc,
+            10,
+        )
+        .await
+        .unwrap();
+    assert_eq!(kept[0].timestamp, base - Duration::minutes(2));
+}
+
+/// Empty directory of its own for a persisted store
+fn data_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("rustegrate-test-{}", Uuid::new_v4()))
+}
+
+/// IDs of every record a store holds for `device_id`, oldest first
+async fn stored_ids(store: &dyn TelemetryRepository, device_id: &str) -> Vec<Uuid> {
+    store
+        .get_by_device(
+            device_id,
+            &TelemetryFilter::default(),
+            None,
+            SortOrder::Asc,
+            100,
+        )
+        .await
+        .unwrap()
+        .iter()
+        .map(|t| t.id)
+        .collect()
+}
+
+/// Temperature readings counted in each of a device's rollups in `tier`, oldest first
+async fn rollup_counts(
+    store: &dyn TelemetryRepository,
+    device_id: &str,
+    tier: TelemetryTier,
+) -> Vec<u64> {
+    store
+        .rollups(device_id, tier, None, None, &[])
+        .await
+        .unwrap()
+        .iter()
+        .map(|bucket| bucket.metrics["temperature"].count)
+        .collect()
+}
+
+#[tokio::test]
+async fn durable_store_recovers_after_restart() {
+    let dir = data_dir();
+    let open =
+        || DurableTelemetryStore::open(TelemetryStore::new(), dir.clone(), FsyncPolicy::Always);
+    let base = Utc::now();
+
+    let store = open().await.unwrap();
+    for minutes in [3, 2, 1] {
+        store
+            .add(reading_at("dev-a", 20.0, base - Duration::minutes(minutes)))
+            .await
+            .unwrap();
+    }
+    store
+        .add_batch(vec![
+            reading_at("dev-b", 21.0, base - Duration::minutes(2)),
+            reading_at("dev-b", 22.0, base),
+        ])
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    store
+        .delete_old_records("dev-a", base - Duration::minutes(2))
+        .await
+        .unwrap();
+    let dev_a = stored_ids(&store, "dev-a").await;
+    let dev_b = stored_ids(&store, "dev-b").await;
+    assert_eq!((dev_a.len(), dev_b.len()), (2, 2));
+    drop(store);
+
+    // A crash mid-compaction leaves the old log behind; replaying it again is harmless
+    std::fs::copy(dir.join("telemetry.wal"), dir.join("telemetry.wal.old")).unwrap();
+    let store = open().await.unwrap();
+    assert_eq!(stored_ids(&store, "dev-a").await, dev_a);
+    assert_eq!(stored_ids(&store, "dev-b").await, dev_b);
+    assert!(!dir.join("telemetry.wal.old").exists());
+
+    // Writes after a compaction are recovered on top of the snapshot
+    assert_eq!(store.snapshot().await.unwrap(), 4);
+    let late = store.add(reading_at("dev-a", 23.0, base)).await.unwrap();
+    store
+        .delete_old_records("dev-b", base - Duration::minutes(1))
+        .await
+        .unwrap();
+    drop(store);
+
+    let store = open().await.unwrap();
+    assert_eq!(
+        stored_ids(&store, "dev-a").await,
+        [&dev_a[..], &[late]].concat()
+    );
+    assert_eq!(stored_ids(&store, "dev-b").await, dev_b[1..]);
+    drop(store);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn durable_store_recovers_from_torn_log() {
+    let dir = data_dir();
+    let store = DurableTelemetryStore::open(TelemetryStore::new(), dir.clone(), FsyncPolicy::Never)
+        .await
+        .unwrap();
+    let id = store
+        .add(reading("dev-a", 20.0, Duration::zero()))
+        .await
+        .unwrap();
+    drop(store);
+
+    // An entry cut short by a crash is dropped
+    let wal = dir.join("telemetry.wal");
+    let mut log = std::fs::read(&wal).unwrap();
+    log.extend_from_slice(br#"{"op":"add","telemetry":{"id":"#);
+    std::fs::write(&wal, &log).unwrap();
+
+    let store = DurableTelemetryStore::open(TelemetryStore::new(), dir.clone(), FsyncPolicy::Never)
+        .await
+        .unwrap();
+    assert_eq!(stored_ids(&store, "dev-a").await, [id]);
+    drop(store);
+
+    // Anything unreadable before the final entry is corruption, not a torn write
+    let log = concat!(
+        "not json\n",
+        r#"{"op":"delete","device_id":"dev-a","older_than":"2020-01-01T00:00:00Z"}"#,
+        "\n"
+    );
+    std::fs::write(&wal, log).unwrap();
+    let error = DurableTelemetryStore::open(TelemetryStore::new(), dir.clone(), FsyncPolicy::Never)
+        .await
+        .err()
+        .unwrap();
+    assert!(error.contains("corrupt at line 1"), "{}", error);
+
+    // So is a log that does not start with its generation
+    let delete = r#"{"op":"delete","device_id":"dev-a","older_than":"2020-01-01T00:00:00Z"}"#;
+    std::fs::write(&wal, format!("{}\n{}\n", delete, delete)).unwrap();
+    let error = DurableTelemetryStore::open(TelemetryStore::new(), dir.clone(), FsyncPolicy::Never)
+        .await
+        .err()
+        .unwrap();
+    assert!(error.contains("line 1: missing generation"), "{}", error);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn durable_store_keeps_logging_when_rotation_fails() {
+    let dir = data_dir();
+    let open =
+        || DurableTelemetryStore::open(TelemetryStore::new(), dir.clone(), FsyncPolicy::Always);
+
+    let store = open().await.unwrap();
+    let first = store
+        .add(reading("dev-a", 20.0, Duration::minutes(2)))
+        .await
+        .unwrap();
+
+    // A directory in the way of the next log makes starting it fail
+    let next = dir.join("telemetry.wal.next");
+    std::fs::create_dir(&next).unwrap();
+    assert!(store.snapshot().await.is_err());
+    assert!(!dir.join("telemetry.wal.old").exists());
+
+    // Later writes still go to the live log, and the next compaction succeeds
+    let second = store
+        .add(reading("dev-a", 21.0, Duration::minutes(1)))
+        .await
+        .unwrap();
+    std::fs::remove_dir(&next).unwrap();
+    assert_eq!(store.snapshot().await.unwrap(), 2);
+    let third = store
+        .add(reading("dev-a", 22.0, Duration::zero()))
+        .await
+        .unwrap();
+    drop(store);
+
+    let store = open().await.unwrap();
+    assert_eq!(stored_ids(&store, "dev-a").await, [first, second, third]);
+    drop(store);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn durable_store_recovers_rollups() {
+    let dir = data_dir();
+    let open =
+        || DurableTelemetryStore::open(TelemetryStore::new(), dir.clone(), FsyncPolicy::Always);
+    let base = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+
+    let store = open().await.unwrap();
+    for minutes in [0, 1, 61] {
+        store
+            .add(reading_at("dev-a", 20.0, base + Duration::minutes(minutes)))
+            .await
+            .unwrap();
+    }
+    drop(store);
+
+    // Logs already folded into the snapshot are not replayed a second time
+    std::fs::copy(dir.join("telemetry.wal"), dir.join("telemetry.wal.old")).unwrap();
+    let store = open().await.unwrap();
+    assert_eq!(
+        rollup_counts(&store, "dev-a", TelemetryTier::Hour).await,
+        [2, 1]
+    );
+    drop(store);
+
+    let store = open().await.unwrap();
+    assert_eq!(
+        rollup_counts(&store, "dev-a", TelemetryTier::Hour).await,
+        [2, 1]
+    );
+    store
+        .delete_old_records("dev-a", base + Duration::days(1))
+        .await
+        .unwrap();
+    store
+        .delete_old_rollups(TelemetryTier::Minute, base + Duration::hours(1))
+        .await
+        .unwrap();
+    drop(store);
+
+    let store = open().await.unwrap();
+    assert!(stored_ids(&store, "dev-a").await.is_empty());
+    assert_eq!(
+        rollup_counts(&store, "dev-a", TelemetryTier::Minute).await,
+        [1]
+    );
+    assert_eq!(
+        rollup_counts(&store, "dev-a", TelemetryTier::Day).await,
+        [3]
+    );
+    drop(store);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn durable_store_compacts_while_writing() {
+    let dir = data_dir();
+    let open =
+        || DurableTelemetryStore::open(TelemetryStore::new(), dir.clone(), FsyncPolicy::Never);
+    let base = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    let store = Arc::new(open().await.unwrap());
+
+    let writer = {
+        let store = store.clone();
+        tokio::spawn(async move {
+            for minutes in 0..200 {
+                let at = base + Duration::minutes(minutes);
+                store.add(reading_at("dev-a", 20.0, at)).await.unwrap();
+                if minutes % 50 == 49 {
+                    store.delete_old_records("dev-a", at).await.unwrap();
+                }
+            }
+        })
+    };
+    for _ in 0..5 {
+        store.snapshot().await.unwrap();
+        tokio::task::yield_now().await;
+    }
+    writer.await.unwrap();
+    store.snapshot().await.unwrap();
+
+    // Readings added and deleted while compactions ran are each counted once
+    let held = stored_ids(&*store, "dev-a").await;
+    assert_eq!(held.len(), 1);
+    assert_eq!(
+        rollup_counts(&*store, "dev-a", TelemetryTier::Day).await,
+        [200]
+    );
+    drop(store);
+
+    let store = open().await.unwrap();
+    assert_eq!(stored_ids(&store, "dev-a").await, held);
+    assert_eq!(
+        rollup_counts(&store, "dev-a", TelemetryTier::Day).await,
+        [200]
+    );
+    drop(store);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+fn reading(device_id: &str, temperature: f64, age: Duration) -> TelemetryData {
+    reading_at(device_id, temperature, Utc::now() - age)
+}
+
+fn reading_at(device_id: &str, temperature: f64, timestamp: DateTime<Utc>) -> TelemetryData {
+    TelemetryData::from(CreateTelemetryRequest {
+        device_id: device_id.to_string(),
+        message_id: None,
+        metrics: BTreeMap::new(),
+        temperature: Some(temperature),
+        humidity: Some(40.0),
+        pressure: None,
+        timestamp,
+    })
+}
+
+async fn check_add_and_get_by_id(repo: Arc<dyn TelemetryRepository>) {
+    let device_id = unique_device("storage-device-001");
+    let mut telemetry = reading(&device_id, 21.5, Duration::zero());
+    telemetry.metrics.insert("co2".to_string(), 415.25);
+    let id = repo.add(telemetry.clone()).await.unwrap();
+    assert_eq!(id, telemetry.id);
+
+    let stored = repo.get_by_id(id).await.unwrap().expect("record exists");
+    assert_eq!(stored.device_id, device_id);
+    assert_eq!(stored.metrics.get("temperature"), Some(&21.5));
+    assert_eq!(stored.metrics.get("humidity"), Some(&40.0));
+    assert_eq!(stored.metrics.get("co2"), Some(&415.25));
+    assert_eq!(stored.metrics.get("pressure"), None);
+
+    let missing = repo.get_by_id(Uuid::new_v4()).await.unwrap();
+    assert!(missing.is_none());
+
+    // IDs are unique across the store
+    assert!(repo.add(telemetry).await.is_err());
+}
+
+async fn check_add_batch(repo: Arc<dyn TelemetryRepository>) {
+    let device_id = unique_device("storage-device-004");
+    let batch: Vec<TelemetryData> = (0..5)
+        .map(|i| reading(&device_id, i as f64, Duration::minutes(i)))
+        .collect();
+    let expected: Vec<_> = batch.iter().map(|t| t.id).collect();
+    let duplicate = batch[0].clone();
+
+    let ids = repo
+        .add_batch(batch)
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(ids, expected);
+
+    let stored = |repo: Arc<dyn TelemetryRepository>, device_id: String| async move {
+        repo.get_by_device(
+            &device_id,
+            &TelemetryFilter::default(),
+            None,
+            SortOrder::Asc,
+            100,
+        )
+        .await
+        .unwrap()
+        .len()
+    };
+    assert_eq!(stored(repo.clone(), device_id.clone()).await, 5);
+
+    // A record that cannot be stored is reported on its own, and exactly the
+    // records reported as added are stored
+    let other_device = unique_device("storage-device-004");
+    let batch = vec![
+        reading(&other_device, 1.0, Duration::minutes(2)),
+        duplicate,
+        reading(&other_device, 2.0, Duration::minutes(1)),
+    ];
+    let results = repo.add_batch(batch).await;
+    assert_eq!(results.len(), 3);
+    assert!(results[1].is_err());
+    let added = results.iter().filter(|r| r.is_ok()).count();
+    assert_eq!(stored(repo.clone(), other_device).await, added);
+    assert_eq!(stored(repo, device_id).await, 5);
+}
+
+async fn check_get_by_device_filters(repo: Arc<dyn TelemetryRepository>) {
+    let device_id = unique_device("storage-device-002");
+    for hours in [3, 2, 1] {
+        let telemetry = reading(&device_id, hours as f64, Duration::hours(hours));
+        repo.add(telemetry).await.unwrap();
+    }
+    repo.add(reading(
+        &unique_device("storage-device-other"),
+        0.0,
+        Duration::zero(),
+    ))
+    .await
+    .unwrap();
+
+    let all = repo
+        .get_by_device(
+            &device_id,
+            &TelemetryFilter::default(),
+            None,
+            SortOrder::Asc,
+            100,
+        )
+        .await
+        .unwrap();
+    assert_eq!(all.len(), 3);
+    assert!(all.iter().all(|t| t.device_id == device_id));
+
+    let start = Utc::now() - Duration::minutes(150);
+    let recent = repo
+        .get_by_device(
+            &device_id,
+            &TelemetryFilter::between(Some(start), None),
+            None,
+            SortOrder::Asc,
+            100,
+        )
+        .await
+        .unwrap();
+    assert_eq!(recent.len(), 2);
+
+    let limited = repo
+        .get_by_device(
+            &device_id,
+            &TelemetryFilter::default(),
+            None,
+            SortOrder::Asc,
+            1,
+        )
+        .await
+        .unwrap();
+    assert_eq!(limited.len(), 1);
+
+    let unknown = repo
+        .get_by_device(
+            &unique_device("storage-device-unknown"),
+            &TelemetryFilter::default(),
+            None,
+            SortOrder::Asc,
+            100,
+        )
+        .await
+        .unwrap();
+    assert!(unknown.is_empty());
+}
+
+async fn check_cursor_pagination(repo: Arc<dyn TelemetryRepository>) {
+    let device_id = unique_device("storage-device-006");
+    let base = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    // Insert out of order, with a duplicated timestamp
+    for offset in [2, 0, 1, 1, 3] {
+        let telemetry = reading_at(&device_id, 20.0, base + Duration::seconds(offset));
+        repo.add(telemetry).await.unwrap();
+    }
+
+    let mut pages = Vec::new();
+    let mut after = None;
+    loop {
+        let page = repo
+            .get_by_device(
+                &device_id,
+                &TelemetryFilter::default(),
+                after,
+                SortOrder::Asc,
+                2,
+            )
+            .await
+            .unwrap();
+        if page.is_empty() {
+            break;
+        }
+        after = page.last().map(TelemetryCursor::after);
+        pages.extend(page);
+    }
+
+    let positions: Vec<_> = pages.iter().map(TelemetryCursor::after).collect();
+    let mut expected = positions.clone();
+    expected.sort();
+    expected.dedup();
+    assert_eq!(positions, expected);
+    assert_eq!(positions.len(), 5);
+}
+
+async fn check_sort_order(repo: Arc<dyn TelemetryRepository>) {
+    let device_id = unique_device("storage-device-007");
+    let base = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    // Late readings arrive after newer ones
+    for offset in [3, 1, 4, 0, 2] {
+        let telemetry = reading_at(&device_id, offset as f64, base + Duration::seconds(offset));
+        repo.add(telemetry).await.unwrap();
+    }
+
+    let ascending = repo
+        .get_by_device(
+            &device_id,
+            &TelemetryFilter::default(),
+            None,
+            SortOrder::Asc,
+            100,
+        )
+        .await
+        .unwrap();
+    let temperatures: Vec<f64> = ascending.iter().map(|t| t.metrics["temperature"]).collect();
+    assert_eq!(temperatures, vec![0.0, 1.0, 2.0, 3.0, 4.0]);
+
+    let latest = repo
+        .get_by_device(
+            &device_id,
+            &TelemetryFilter::default(),
+            None,
+            SortOrder::Desc,
+            2,
+        )
+        .await
+        .unwrap();
+    let temperatures: Vec<f64> = latest.iter().map(|t| t.metrics["temperature"]).collect();
+    assert_eq!(temperatures, vec![4.0, 3.0]);
+
+    // Continuing a descending walk from a cursor, within a time window
+    let after = latest.last().map(TelemetryCursor::after);
+    let end = base + Duration::seconds(3);
+    let rest = repo
+        .get_by_device(
+            &device_id,
+            &TelemetryFilter::between(None, Some(end)),
+            after,
+            SortOrder::Desc,
+            100,
+        )
+        .await
+        .unwrap();
+    let temperatures: Vec<f64> = rest.iter().map(|t| t.metrics["temperature"]).collect();
+    assert_eq!(temperatures, vec![2.0, 1.0, 0.0]);
+}
+
+async fn check_list_devices(repo: Arc<dyn TelemetryRepository>) {
+    let base = unique_device("storage-list");
+    let prefix = format!("{}_", base);
+    for (suffix, count) in [("c", 1), ("a", 2), ("b", 1)] {
+        for i in 0..count {
+            let telemetry = reading(&format!("{}{}", prefix, suffix), 20.0, Duration::hours(i));
+            repo.add(telemetry).await.unwrap();
+        }
+    }
+    // Would match if `_` were treated as a wildcard
+    repo.add(reading(&format!("{}Xa", base), 20.0, Duration::zero()))
+        .await
+        .unwrap();
+
+    let devices = repo.list_devices(Some(&prefix), None, 100).await.unwrap();
+    let ids: Vec<_> = devices.iter().map(|d| d.device_id.clone()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            format!("{}a", prefix),
+            format!("{}b", prefix),
+            format!("{}c", prefix)
+        ]
+    );
+    assert_eq!(devices[0].record_count, 2);
+    assert!(devices[0].first_timestamp < devices[0].last_timestamp);
+
+    // Keyset paging continues after the last device of the previous page
+    let first_page = repo.list_devices(Some(&prefix), None, 2).await.unwrap();
+    assert_eq!(first_page.len(), 2);
+    let rest = repo
+        .list_devices(Some(&prefix), Some(&first_page[1].device_id), 2)
+        .await
+        .unwrap();
+    assert_eq!(rest.len(), 1);
+    assert_eq!(rest[0].device_id, format!("{}c", prefix));
+}
+
+async fn check_received_after(repo: Arc<dyn TelemetryRepository>) {
+    let device_id = unique_device("storage-received");
+    let other_id = unique_device("storage-received-other");
+    // Received later than any other test's readings, so the fleet-wide query sees only these
+    let start = Utc::now() + Duration::days(365 * 100);
+
+    // Device timestamps run against the receive order
+    let mut ids = Vec::new();
+    for (i, (device, hours)) in [(&device_id, 1), (&other_id, 3), (&device_id, 2)]
+        .into_iter()
+        .enumerate()
+    {
+        let mut telemetry = reading(device, i as f64, Duration::hours(hours));
+        telemetry.received_at = start + Duration::milliseconds(i as i64 + 1);
+        ids.push(repo.add(telemetry).await.unwrap());
+    }
+    let position = TelemetryCursor {
+        timestamp: start,
+        id: Uuid::nil(),
+    };
+    let received_ids =
+        |records: Vec<TelemetryData>| records.into_iter().map(|t| t.id).collect::<Vec<_>>();
+
+    let fleet = repo.received_after(None, position, 10).await.unwrap();
+    assert_eq!(received_ids(fleet.clone()), ids);
+
+    let device = repo
+        .received_after(Some(&device_id), position, 10)
+        .await
+        .unwrap();
+    assert_eq!(received_ids(device), vec![ids[0], ids[2]]);
+
+    // The limit keeps the earliest received, and the cursor itself is excluded
+    let first = repo.received_after(None, position, 1).await.unwrap();
+    assert_eq!(received_ids(first), vec![ids[0]]);
+    let rest = repo
+        .received_after(None, TelemetryCursor::received(&fleet[0]), 10)
+        .await
+        .unwrap();
+    assert_eq!(received_ids(rest), vec![ids[1], ids[2]]);
+}
+
+async fn check_delete_old_records(repo: Arc<dyn TelemetryRepository>) {
+    let device_id = unique_device("storage-device-003");
+    let mut ids = Vec::new();
+    for hours in [3, 2, 1] {
+        let telemetry = reading(&device_id, hours as f64, Duration::hours(hours));
+        ids.push(repo.add(telemetry).await.unwrap());
+    }
+
+    let cutoff = Utc::now() - Duration::minutes(90);
+    let deleted = repo.delete_old_records(&device_id, cutoff).await.unwrap();
+    assert_eq!(deleted, 2);
+
+    let remaining = repo
+        .get_by_device(
+            &device_id,
+            &TelemetryFilter::default(),
+            None,
+            SortOrder::Asc,
+            100,
+        )
+        .await
+        .unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert!(remaining[0].timestamp >= cutoff);
+
+    // Deleted records can no longer be looked up by ID
+    assert!(repo.get_by_id(ids[0]).await.unwrap().is_none());
+    assert!(repo.get_by_id(ids[1]).await.unwrap().is_none());
+    assert!(repo.get_by_id(ids[2]).await.unwrap().is_some());
+
+    let deleted = repo
+        .delete_old_records(&unique_device("storage-device-unknown"), cutoff)
+        .await
+        .unwrap();
+    assert_eq!(deleted, 0);
+}
+
+async fn check_aggregate(repo: Arc<dyn TelemetryRepository>) {
+    let device_id = unique_device("storage-device-005");
+    let base = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    for (offset, temperature) in [(0, 10.0), (60, 20.0), (330, 30.0)] {
+        let mut telemetry = reading_at(&device_id, temperature, base + Duration::seconds(offset));
+        if offset == 60 {
+            telemetry.metrics.insert("co2".to_string(), 400.0);
+        }
+        repo.add(telemetry).await.unwrap();
+    }
+
+    // Goes through the service so backends without native aggregation are covered too
+    let service = TelemetryService::with_repository(repo);
+    let functions = AggregateFunction::parse_list("avg,min,max,count").unwrap();
+    let (_, buckets) = service
+        .aggregate_device_telemetry(
+            &device_id,
+            None,
+            None,
+            Duration::minutes(5),
+            Some(TelemetryTier::Raw),
+            &[],
+            &functions,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(buckets.len(), 2);
+    assert_eq!(buckets[0].bucket_start, base);
+    let temperature = &buckets[0].metrics["temperature"];
+    assert_eq!(temperature.count, Some(2));
+    assert_eq!(temperature.avg, Some(15.0));
+    assert_eq!(temperature.min, Some(10.0));
+    assert_eq!(temperature.max, Some(20.0));
+    assert_eq!(buckets[0].metrics["humidity"].count, Some(2));
+    assert_eq!(buckets[0].metrics["co2"].count, Some(1));
+    assert_eq!(buckets[0].metrics["co2"].avg, Some(400.0));
+    assert!(!buckets[0].metrics.contains_key("pressure"));
+    assert_eq!(buckets[1].bucket_start, base + Duration::minutes(5));
+    assert_eq!(buckets[1].metrics["temperature"].count, Some(1));
+    assert_eq!(buckets[1].metrics["temperature"].max, Some(30.0));
+    assert!(!buckets[1].metrics.contains_key("co2"));
+
+    // Only the requested metrics are aggregated
+    let (_, buckets) = service
+        .aggregate_device_telemetry(
+            &device_id,
+            None,
+            None,
+            Duration::minutes(5),
+            Some(TelemetryTier::Raw),
+            &["co2".to_string()],
+            &functions,
+        )
+        .await
+        .unwrap();
+    assert_eq!(buckets.len(), 1);
+    assert_eq!(buckets[0].metrics.len(), 1);
+    assert_eq!(buckets[0].metrics["co2"].max, Some(400.0));
+}
+
+async fn check_rollups(repo: Arc<dyn TelemetryRepository>) {
+    let device_id = unique_device("storage-device-009");
+    let base = Utc.with_ymd_and_hms(2001, 1, 1, 0, 0, 0).unwrap();
+    for (offset, temperature) in [(10, 10.0), (50, 30.0), (90, 20.0)] {
+        let telemetry = reading_at(&device_id, temperature, base + Duration::seconds(offset));
+        repo.add(telemetry).await.unwrap();
+    }
+    repo.add_batch(vec![
+        reading_at(&device_id, 5.0, base + Duration::hours(2)),
+        reading_at(
+            &device_id,
+            7.0,
+            base + Duration::days(1) + Duration::hours(1),
+        ),
+    ])
+    .await
+    .into_iter()
+    .collect::<Result<Vec<_>, _>>()
+    .unwrap();
+
+    let temperature = &["temperature".to_string()];
+    let stats = |tier| {
+        let repo = repo.clone();
+        let device_id = device_id.clone();
+        async move {
+            repo.rollups(&device_id, tier, None, None, temperature)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|bucket| {
+                    let stats = &bucket.metrics["temperature"];
+                    (
+                        bucket.bucket_start - base,
+                        stats.count,
+                        stats.min,
+                        stats.max,
+                        stats.last,
+                    )
+                })
+                .collect::<Vec<_>>()
+        }
+    };
+
+    assert_eq!(
+        stats(TelemetryTier::Minute).await,
+        [
+            (Duration::zero(), 2, Some(10.0), Some(30.0), Some(30.0)),
+            (Duration::minutes(1), 1, Some(20.0), Some(20.0), Some(20.0)),
+            (Duration::hours(2), 1, Some(5.0), Some(5.0), Some(5.0)),
+            (Duration::hours(25), 1, Some(7.0), Some(7.0), Some(7.0)),
+        ]
+    );
+    assert_eq!(
+        stats(TelemetryTier::Hour).await,
+        [
+            (Duration::zero(), 3, Some(10.0), Some(30.0), Some(20.0)),
+            (Duration::hours(2), 1, Some(5.0), Some(5.0), Some(5.0)),
+            (Duration::hours(25), 1, Some(7.0), Some(7.0), Some(7.0)),
+        ]
+    );
+    assert_eq!(
+        stats(TelemetryTier::Day).await,
+        [
+            (Duration::zero(), 4, Some(5.0), Some(30.0), Some(5.0)),
+            (Duration::days(1), 1, Some(7.0), Some(7.0), Some(7.0)),
+        ]
+    );
+
+    // The range is rounded out to the bucket containing its start
+    let buckets = repo
+        .rollups(
+            &device_id,
+            TelemetryTier::Hour,
+            Some(base + Duration::minutes(30)),
+            Some(base + Duration::hours(2)),
+            &[],
+        )
+        .await
+        .unwrap();
+    assert_eq!(buckets.len(), 2);
+    assert_eq!(buckets[0].bucket_start, base);
+    assert_eq!(buckets[0].metrics["humidity"].count, 3);
+
+    // The service answers from the coarsest tier that fits, matching the raw readings
+    let service = TelemetryService::with_repository(repo.clone());
+    let functions = AggregateFunction::parse_list("avg,min,max,count,last").unwrap();
+    let aggregate = |tier| {
+        service.aggregate_device_telemetry(
+            &device_id,
+            None,
+            None,
+            Duration::hours(1),
+            tier,
+            &[],
+            &functions,
+        )
+    };
+    let (tier, rolled_up) = aggregate(None).await.unwrap();
+    assert_eq!(tier, TelemetryTier::Hour);
+    let (_, raw) = aggregate(Some(TelemetryTier::Raw)).await.unwrap();
+    assert_eq!(
+        serde_json::to_value(&rolled_up).unwrap(),
+        serde_json::to_value(&raw).unwrap()
+    );
+    assert_eq!(rolled_up[0].metrics["temperature"].last, Some(20.0));
+
+    // Rollups outlive the readings they summarize
+    let deleted = repo
+        .delete_old_records(&device_id, base + Duration::days(2))
+        .await
+        .unwrap();
+    assert_eq!(deleted, 5);
+    assert_eq!(stats(TelemetryTier::Day).await.len(), 2);
+
+    let deleted = repo
+        .delete_old_rollups(TelemetryTier::Minute, base + Duration::days(1))
+        .await
+        .unwrap();
+    // Two metrics in each of three buckets, plus any left by other runs
+    assert!(deleted >= 6, "{}", deleted);
+    assert_eq!(stats(TelemetryTier::Minute).await.len(), 1);
+    assert_eq!(stats(TelemetryTier::Hour).await.len(), 3);
+}
+
+async fn check_received_at(repo: Arc<dyn TelemetryRepository>) {
+    let device_id = unique_device("storage-device-008");
+    let base = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    // Readings buffered on the device arrive together, long after they were taken
+    for offset in 0..3 {
+        let mut telemetry = reading_at(&device_id, 20.0, base + Duration::minutes(offset));
+        telemetry.received_at = base + Duration::hours(1) + Duration::seconds(offset);
+        repo.add(telemetry).await.unwrap();
+    }
+
+    let all = repo
+        .get_by_device(
+            &device_id,
+            &TelemetryFilter::default(),
+            None,
+            SortOrder::Asc,
+            100,
+        )
+        .await
+        .unwrap();
+    assert_eq!(all[0].received_at, base + Duration::hours(1));
+
+    let filter = TelemetryFilter {
+        received_start_time: Some(base + Duration::hours(1) + Duration::seconds(1)),
+        ..TelemetryFilter::default()
+    };
+    let late = repo
+        .get_by_device(&device_id, &filter, None, SortOrder::Asc, 100)
+        .await
+        .unwrap();
+    assert_eq!(late.len(), 2);
+    assert_eq!(late[0].timestamp, base + Duration::minutes(1));
+
+    // Both axes combine
+    let filter = TelemetryFilter {
+        end_time: Some(base + Duration::minutes(1)),
+        received_end_time: Some(base + Duration::hours(1)),
+        ..filter
+    };
+    let none = repo
+        .get_by_device(&device_id, &filter, None, SortOrder::Asc, 100)
+        .await
+        .unwrap();
+    assert!(none.is_empty());
+
+    let devices = repo.list_devices(Some(&device_id), None, 1).await.unwrap();
+    assert_eq!(
+        devices[0].last_seen,
+        base + Duration::hours(1) + Duration::seconds(2)
+    );
+}
+
+async fn check_device_registry(registry: Arc<dyn DeviceRepository>) {
+    let device_id = unique_device("registry");
+    let device = Device::from(CreateDeviceRequest {
+        device_id: device_id.clone(),
+        name: "Walk-in freezer".to_string(),
+        device_type: "freezer-sensor".to_string(),
+        location: Some("Kitchen".to_string()),
+        tags: vec!["cold-chain".to_string(), "kitchen".to_string()],
+        status: DeviceStatus::Active,
+        expected_interval_secs: Some(60),
+    });
+
+    assert!(registry.get(&device_id).await.unwrap().is_none());
+    assert!(registry.insert(device.clone()).await.unwrap());
+    // A second registration under the same ID is refused
+    assert!(!registry.insert(device.clone()).await.unwrap());
+
+    let stored = registry.get(&device_id).await.unwrap().unwrap();
+    assert_eq!(stored.name, "Walk-in freezer");
+    assert_eq!(stored.device_type, "freezer-sensor");
+    assert_eq!(stored.location.as_deref(), Some("Kitchen"));
+    assert_eq!(stored.tags, device.tags);
+    assert_eq!(stored.status, DeviceStatus::Active);
+    assert_eq!(stored.expected_interval_secs, Some(60));
+
+    // Listing pages through registered devices in device ID order
+    let prefix = unique_device("registry-list");
+    for suffix in ["c", "a", "b"] {
+        let other = Device {
+            device_id: format!("{}{}", prefix, suffix),
+            ..device.clone()
+        };
+        assert!(registry.insert(other).await.unwrap());
+    }
+    let listed = registry.list(Some(&prefix), None, 2).await.unwrap();
+    let ids: Vec<_> = listed.iter().map(|d| d.device_id.clone()).collect();
+    assert_eq!(ids, vec![format!("{}a", prefix), format!("{}b", prefix)]);
+    assert_eq!(listed[0].tags, device.tags);
+    let rest = registry
+        .list(Some(&prefix), Some(&listed[1].device_id), 2)
+        .await
+        .unwrap();
+    assert_eq!(rest.len(), 1);
+    assert_eq!(rest[0].device_id, format!("{}c", prefix));
+
+    let updated = Device {
+        name: "Freezer 2".to_string(),
+        location: None,
+        tags: Vec::new(),
+        status: DeviceStatus::Maintenance,
+        expected_interval_secs: None,
+        ..device.clone()
+    };
+    assert!(registry.update(updated).await.unwrap());
+
+    let stored = registry.get(&device_id).await.unwrap().unwrap();
+    assert_eq!(stored.name, "Freezer 2");
+    assert_eq!(stored.location, None);
+    assert!(stored.tags.is_empty());
+    assert_eq!(stored.status, DeviceStatus::Maintenance);
+    assert_eq!(stored.expected_interval_secs, None);
+
+    assert!(registry.delete(&device_id).await.unwrap());
+    assert!(!registry.delete(&device_id).await.unwrap());
+    assert!(registry.get(&device_id).await.unwrap().is_none());
+
+    let missing = Device {
+        device_id: unique_device("registry-missing"),
+        ..device
+    };
+    assert!(!registry.update(missing).await.unwrap());
+}
+
+/// Generate the shared repository test suite for a backend
+///
+/// `$make` evaluates to `Option<Storage>`; backends that need external
+/// services return `None` to skip when unavailable.
+async fn check_alert_rules(rules: Arc<dyn AlertRuleRepository>) {
+    let rule = AlertRule::new(AlertRuleRequest {
+        name: "Freezer too warm".to_string(),
+        device: unique_device("freezer-*"),
+        metric: "temperature".to_string(),
+        op: Comparator::Gt,
+        threshold: -15.0,
+        duration_secs: 300,
+        hysteresis: 1.5,
+    });
+    rules.insert(rule.clone()).await.unwrap();
+
+    // Other suites may share the database, so only look at this rule
+    let find = |all: Vec<AlertRule>| all.into_iter().find(|r| r.id == rule.id);
+    let stored = find(rules.list().await.unwrap()).unwrap();
+    assert_eq!(stored.name, rule.name);
+    assert_eq!(stored.device, rule.device);
+    assert_eq!(stored.op, Comparator::Gt);
+    assert_eq!(stored.threshold, -15.0);
+    assert_eq!(stored.duration_secs, 300);
+    assert_eq!(stored.hysteresis, 1.5);
+
+    let updated = AlertRule {
+        op: Comparator::Lte,
+        threshold: -30.0,
+        ..rule.clone()
+    };
+    assert!(rules.update(updated).await.unwrap());
+    let stored = find(rules.list().await.unwrap()).unwrap();
+    assert_eq!(stored.op, Comparator::Lte);
+    assert_eq!(stored.threshold, -30.0);
+
+    assert!(rules.delete(rule.id).await.unwrap());
+    assert!(!rules.delete(rule.id).await.unwrap());
+    assert!(!rules.update(rule.clone()).await.unwrap());
+    assert!(find(rules.list().await.unwrap()).is_none());
+}
+
+async fn check_webhooks(webhooks: Arc<dyn WebhookRepository>) {
+    let webhook = Webhook::new(WebhookRequest {
+        url: "https://example.com/hooks/telemetry".to_string(),
+        secret: "s3cret".to_string(),
+        device: unique_device("dock-*"),
+        events: vec![WebhookEvent::ReadingAccepted, WebhookEvent::RecordsDeleted],
+    });
+    webhooks.insert(webhook.clone()).await.unwrap();
+
+    // Other suites may share the database, so only look at this webhook
+    let find = |all: Vec<Webhook>| all.into_iter().find(|w| w.id == webhook.id);
+    let stored = find(webhooks.list().await.unwrap()).unwrap();
+    assert_eq!(stored.url, webhook.url);
+    assert_eq!(stored.secret, "s3cret");
+    assert_eq!(stored.device, webhook.device);
+    assert_eq!(stored.events, webhook.events);
+
+    assert!(webhooks.delete(webhook.id).await.unwrap());
+    assert!(!webhooks.delete(webhook.id).await.unwrap());
+    assert!(find(webhooks.list().await.unwrap()).is_none());
+}
+
+macro_rules! repository_tests {
+    ($backend:ident, $make:expr) => {
+        mod $backend {
+            use super::*;
+
+            async fn storage() -> Option<Storage> {
+                $make
+            }
+
+            async fn repository() -> Option<Arc<dyn TelemetryRepository>> {
+                storage().await.map(|storage| storage.telemetry)
+            }
+
+            #[tokio::test]
+            async fn add_and_get_by_id() {
+                if let Some(repo) = repository().await {
+                    check_add_and_get_by_id(repo).await;
+                }
+            }
+
+            #[tokio::test]
+            async fn add_batch() {
+                if let Some(repo) = repository().await {
+                    check_add_batch(repo).await;
+                }
+            }
+
+            #[tokio::test]
+            async fn get_by_device_filters() {
+                if let Some(repo) = repository().await {
+                    check_get_by_device_filters(repo).await;
+                }
+            }
+
+            #[tokio::test]
+            async fn aggregate() {
+                if let Some(repo) = repository().await {
+                    check_aggregate(repo).await;
+                }
+            }
+
+            #[tokio::test]
+            async fn rollups() {
+                if let Some(repo) = repository().await {
+                    check_rollups(repo).await;
+                }
+            }
+
+            #[tokio::test]
+            async fn cursor_pagination() {
+                if let Some(repo) = repository().await {
+                    check_cursor_pagination(repo).await;
+                }
+            }
+
+            #[tokio::test]
+            async fn received_after() {
+                if let Some(repo) = repository().await {
+                    check_received_after(repo).await;
+                }
+            }
+
+            #[tokio::test]
+            async fn sort_order() {
+                if let Some(repo) = repository().await {
+                    check_sort_order(repo).await;
+                }
+            }
+
+            #[tokio::test]
+            async fn list_devices() {
+                if let Some(repo) = repository().await {
+                    check_list_devices(repo).await;
+                }
+            }
+
+            #[tokio::test]
+            async fn delete_old_records() {
+                if let Some(repo) = repository().await {
+                    check_delete_old_records(repo).await;
+                }
+            }
+
+            #[tokio::test]
+            async fn received_at() {
+                if let Some(repo) = repository().await {
+                    check_received_at(repo).await;
+                }
+            }
+
+            #[tokio::test]
+            async fn device_registry() {
+                if let Some(storage) = storage().await {
+                    check_device_registry(storage.devices).await;
+                }
+            }
+
+            #[tokio::test]
+            async fn alert_rules() {
+                if let Some(storage) = storage().await {
+                    check_alert_rules(storage.alert_rules).await;
+                }
+            }
+
+            #[tokio::test]
+            async fn webhooks() {
+                if let Some(storage) = storage().await {
+                    check_webhooks(storage.webhooks).await;
+                }
+            }
+        }
+    };
+}
+
+repository_tests!(
+    in_memory,
+    Some(Storage {
+        telemetry: Arc::new(rustegrate::storage::TelemetryStore::new()),
+        devices: Arc::new(rustegrate::storage::DeviceRegistryStore::new()),
+        alert_rules: Arc::new(rustegrate::storage::AlertRuleStore::new()),
+        webhooks: Arc::new(rustegrate::storage::WebhookStore::new()),
+    })
+);
+
+repository_tests!(durable, {
+    let store = DurableTelemetryStore::open(TelemetryStore::new(), data_dir(), FsyncPolicy::Never)
+        .await
+        .unwrap();
+    Some(Storage {
+        telemetry: Arc::new(store),
+        devices: Arc::new(rustegrate::storage::DeviceRegistryStore::new()),
+        alert_rules: Arc::new(rustegrate::storage::AlertRuleStore::new()),
+        webhooks: Arc::new(rustegrate::storage::WebhookStore::new()),
+    })
+});
+
+#[cfg(feature = "sqlite")]
+repository_tests!(sqlite, {
+    let store = rustegrate::storage::SqliteTelemetryStore::connect("sqlite::memory:")
+        .await
+        .unwrap();
+    Some(Storage {
+        devices: Arc::new(store.device_registry()),
+        alert_rules: Arc::new(store.alert_rules()),
+        webhooks: Arc::new(store.webhooks()),
+        telemetry: Arc::new(store),
+    })
+});
+
+// Runs only when TEST_POSTGRES_URL points at a reachable database; CI must set it
+#[cfg(feature = "postgres")]
+repository_tests!(postgres, {
+    match std::env::var("TEST_POSTGRES_URL") {
+        Ok(url) => {
+            let store = rustegrate::storage::PostgresTelemetryStore::connect(&url, 5)
+                .await
+                .unwrap();
+            Some(Storage {
+                devices: Arc::new(store.device_registry()),
+                alert_rules: Arc::new(store.alert_rules()),
+                webhooks: Arc::new(store.webhooks()),
+                telemetry: Arc::new(store),
+            })
+        }
+        Err(_) if std::env::var_os("CI").is_some() => {
+            panic!("TEST_POSTGRES_URL must be set to test the PostgreSQL backend in CI")
+        }
+        Err(_) => None,
+    }
+});